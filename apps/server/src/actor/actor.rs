@@ -1,13 +1,21 @@
-use std::future::Future;
+use std::{future::Future, sync::Arc, time::Duration};
 
-use async_channel::Receiver;
-use tracing::{error, instrument, trace};
+use async_channel::{Receiver, WeakSender};
+use tokio::time::sleep;
+use tracing::{error, instrument, trace, warn};
 
-use super::{ActorInputMessage, ActorOutputMessage, tasks::*};
+use super::{
+    ActorError, ActorInputMessage, ActorOutputMessage,
+    retry::{DeadLetterPayload, DeadLetterStore, RetryPolicy},
+    tasks::*,
+};
 
 #[derive(Debug, Clone)]
 pub(super) struct DefaultActor {
     in_receiver: Receiver<ActorInputMessage>,
+    requeue: WeakSender<ActorInputMessage>,
+    retry: RetryPolicy,
+    dead_letters: Arc<DeadLetterStore>,
 }
 
 pub trait PoolableActor: Clone + Send + Sync + std::fmt::Debug + 'static {
@@ -18,8 +26,49 @@ pub trait PoolableActor: Clone + Send + Sync + std::fmt::Debug + 'static {
 
 impl DefaultActor {
     #[instrument]
-    pub(super) fn new(in_receiver: Receiver<ActorInputMessage>) -> Self {
-        DefaultActor { in_receiver }
+    pub(super) fn new(
+        in_receiver: Receiver<ActorInputMessage>,
+        requeue: WeakSender<ActorInputMessage>,
+        retry: RetryPolicy,
+        dead_letters: Arc<DeadLetterStore>,
+    ) -> Self {
+        DefaultActor {
+            in_receiver,
+            requeue,
+            retry,
+            dead_letters,
+        }
+    }
+
+    #[instrument]
+    async fn run_task(msg: ActorInputMessage) -> Result<ActorOutputMessage, ActorError> {
+        match msg {
+            ActorInputMessage::FetchMetadata(input) => fetch_metadata(input).await,
+            ActorInputMessage::DeliverWebhook(input) => deliver_webhook(input).await,
+            ActorInputMessage::Retry(msg, _) => Box::pin(Self::run_task(*msg)).await,
+            ActorInputMessage::None => Ok(ActorOutputMessage {
+                msg: "Ok".to_string(),
+            }),
+        }
+    }
+
+    /// Queues `msg` again once `delay` has passed, without holding up this worker in the
+    /// meantime. A message that can't be queued because the pool has stopped is moved to the
+    /// dead letter store.
+    fn requeue(&self, msg: ActorInputMessage, attempt: u32, delay: Duration, error: ActorError) {
+        let dead_letters = self.dead_letters.clone();
+        let Some(sender) = self.requeue.upgrade() else {
+            dead_letters.push(DeadLetterPayload::Task(msg), &error, attempt);
+            return;
+        };
+        tokio::spawn(async move {
+            sleep(delay).await;
+            let retry = ActorInputMessage::Retry(Box::new(msg.clone()), attempt + 1);
+            if sender.send(retry).await.is_err() {
+                error!("{error}; the pool stopped before attempt {}", attempt + 1);
+                dead_letters.push(DeadLetterPayload::Task(msg), &error, attempt);
+            }
+        });
     }
 }
 
@@ -30,30 +79,27 @@ impl PoolableActor for DefaultActor {
         self
     }
 
+    /// Runs a message once. One that fails with a retryable error is queued again after a
+    /// backoff, and one that has used up its retries is moved to the dead letter store.
     #[instrument]
     async fn handle_message(&mut self, msg: ActorInputMessage) {
-        match async move {
-            match msg {
-                ActorInputMessage::CleanUrls(db) => clean_urls(db).await,
-                ActorInputMessage::CleanSessions(db) => clean_sessions(db).await,
-                ActorInputMessage::UpdateViews(view) => update_views(view).await,
-                ActorInputMessage::None => {
-                    {
-                        async move {
-                            Ok(ActorOutputMessage {
-                                msg: "Ok".to_string(),
-                            })
-                        }
-                    }
-                    .await
-                }
+        let (msg, attempt) = match msg {
+            ActorInputMessage::Retry(msg, attempt) => (*msg, attempt),
+            msg => (msg, 1),
+        };
+        match Self::run_task(msg.clone()).await {
+            Ok(output) => trace!("{}", output.msg),
+            Err(e) if self.retry.should_retry(&e, attempt) => {
+                let delay = self.retry.delay(attempt);
+                warn!("{e}; retrying in {delay:?} (attempt {attempt})");
+                self.requeue(msg, attempt, delay, e);
+            }
+            Err(e) => {
+                error!("{e}; giving up after {attempt} attempts");
+                self.dead_letters
+                    .push(DeadLetterPayload::Task(msg), &e, attempt);
             }
         }
-        .await
-        {
-            Ok(msg) => trace!("{}", msg.msg),
-            Err(e) => error!("{}", e.to_string()),
-        };
     }
 
     #[instrument]
@@ -0,0 +1,658 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    time::Duration,
+};
+
+use async_channel::Receiver;
+use axum::http::header;
+use chrono::NaiveDate;
+use entity::{sea_orm_active_enums::DeviceClass, views};
+use sea_orm::{DatabaseConnection, DbErr, SqlErr, entity::*};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::time::{Instant, interval_at, sleep_until};
+use tracing::{debug, error, instrument, warn};
+use ts_rs::TS;
+use utoipa::ToSchema;
+
+use super::{
+    ActorError, ActorPoolConfig, ViewInput,
+    live::{ClickEvent, ClickFeed},
+    msgs::db_err_is_retryable,
+    privacy::{ViewPrivacy, VisitorSalts},
+    retry::{DeadLetterPayload, DeadLetterStore, RetryPolicy},
+};
+use crate::{
+    geoip::GeoIp,
+    user_agent::parse_user_agent,
+    utils::HeaderMapDef,
+    visitors::{HyperLogLog, add_visitors},
+};
+
+/// Postgres caps a statement at 65535 bind parameters, so large batches are split into inserts
+/// of at most this many rows.
+const MAX_ROWS_PER_INSERT: usize = 4096;
+
+/// What recording a click does when the click channel is full. Neither policy ever holds up
+/// the redirect.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClickBackpressure {
+    /// Park the click in a bounded overflow buffer until the channel has room, dropping it
+    /// once the overflow buffer is full too.
+    #[default]
+    Buffer,
+    /// Drop the click straight away.
+    Drop,
+}
+
+impl std::str::FromStr for ClickBackpressure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "buffer" => Ok(Self::Buffer),
+            "drop" => Ok(Self::Drop),
+            other => Err(format!("Unknown click backpressure policy {other:?}")),
+        }
+    }
+}
+
+/// Running totals for click recording, shared by every pool handle and the batcher.
+#[derive(Debug, Default)]
+pub(super) struct ClickCounters {
+    pub(super) queued: AtomicUsize,
+    pub(super) overflowed: AtomicUsize,
+    pub(super) overflowing: AtomicUsize,
+    pub(super) dropped: AtomicUsize,
+    pub(super) written: AtomicUsize,
+    pub(super) failed: AtomicUsize,
+    pub(super) retrying: AtomicUsize,
+}
+
+impl ClickCounters {
+    pub(super) fn snapshot(&self, channel_len: usize) -> ClickStats {
+        ClickStats {
+            queued: self.queued.load(Ordering::Relaxed),
+            overflowed: self.overflowed.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            written: self.written.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            pending: channel_len
+                + self.overflowing.load(Ordering::Relaxed)
+                + self.retrying.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Click recording totals since the server started.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct ClickStats {
+    /// Clicks handed straight to the batcher
+    pub queued: usize,
+    /// Clicks that waited in the overflow buffer because the channel was full
+    pub overflowed: usize,
+    /// Clicks that were never recorded because every buffer was full, the pool was closed or
+    /// their link was deleted before they were written
+    pub dropped: usize,
+    /// Views written to the database
+    pub written: usize,
+    /// Views moved to the dead letter store after their insert kept failing
+    pub failed: usize,
+    /// Clicks waiting in the channel, the overflow buffer or for their insert to be retried
+    pub pending: usize,
+}
+
+/// Clicks whose insert failed, waiting to be tried again.
+#[derive(Debug)]
+struct PendingRetry {
+    due: Instant,
+    rows: Vec<ViewInput>,
+    attempt: u32,
+}
+
+/// Buffers recorded clicks and writes them to `views` in multi-row inserts, flushing once
+/// `batch_size` clicks are waiting or every `flush_interval`, whichever comes first. Inserts
+/// that fail are set aside and retried once their backoff has passed, so new clicks keep being
+/// buffered and written in the meantime.
+#[derive(Debug)]
+pub(super) struct ClickBatcher {
+    receiver: Receiver<ViewInput>,
+    conn: DatabaseConnection,
+    batch_size: usize,
+    flush_interval: Duration,
+    buffer: Vec<ViewInput>,
+    counters: Arc<ClickCounters>,
+    retry: RetryPolicy,
+    retries: Vec<PendingRetry>,
+    dead_letters: Arc<DeadLetterStore>,
+    geoip: GeoIp,
+    privacy: ViewPrivacy,
+    salts: VisitorSalts,
+    feed: ClickFeed,
+}
+
+impl ClickBatcher {
+    pub(super) fn new(
+        receiver: Receiver<ViewInput>,
+        conn: DatabaseConnection,
+        config: &ActorPoolConfig,
+        counters: Arc<ClickCounters>,
+        dead_letters: Arc<DeadLetterStore>,
+        geoip: GeoIp,
+        feed: ClickFeed,
+    ) -> Self {
+        let batch_size = config.click_batch_size.max(1);
+        Self {
+            receiver,
+            conn,
+            batch_size,
+            flush_interval: config.click_flush_interval,
+            buffer: Vec::with_capacity(batch_size),
+            counters,
+            retry: RetryPolicy::new(config),
+            retries: Vec::new(),
+            dead_letters,
+            geoip,
+            privacy: ViewPrivacy::new(&config.privacy),
+            salts: VisitorSalts::default(),
+            feed,
+        }
+    }
+
+    /// Runs until every sender is gone, then flushes whatever is left, waits out any retries
+    /// and signals `done`.
+    #[instrument(skip(self, done))]
+    pub(super) async fn run(mut self, done: mpsc::Sender<usize>) {
+        let mut ticker = interval_at(Instant::now() + self.flush_interval, self.flush_interval);
+        let mut flushed = 0;
+        loop {
+            let next_retry = self.next_retry();
+            tokio::select! {
+                click = self.receiver.recv() => match click {
+                    Ok(click) => {
+                        self.buffer.push(click);
+                        if self.buffer.len() >= self.batch_size {
+                            flushed += self.flush().await;
+                        }
+                    }
+                    Err(_) => break,
+                },
+                _ = ticker.tick() => flushed += self.flush().await,
+                _ = sleep_until(next_retry.unwrap_or_else(Instant::now)), if next_retry.is_some() => {
+                    flushed += self.retry_due().await;
+                }
+            }
+        }
+        flushed += self.flush().await;
+        while let Some(due) = self.next_retry() {
+            sleep_until(due).await;
+            flushed += self.retry_due().await;
+        }
+        debug!("Click recording stopped after writing {flushed} views");
+        let _ = done.send(flushed);
+    }
+
+    /// Writes the buffered clicks, returning how many rows were inserted.
+    #[instrument(skip(self), fields(buffered = self.buffer.len()))]
+    async fn flush(&mut self) -> usize {
+        if self.buffer.is_empty() {
+            return 0;
+        }
+        self.geoip.reload_if_changed().await;
+        let batch = std::mem::take(&mut self.buffer);
+        let chunks = batch
+            .chunks(MAX_ROWS_PER_INSERT)
+            .map(|rows| (rows.to_vec(), 1))
+            .collect();
+        let written = self.write(chunks).await;
+        self.buffer.reserve(self.batch_size);
+        written
+    }
+
+    fn next_retry(&self) -> Option<Instant> {
+        self.retries.iter().map(|retry| retry.due).min()
+    }
+
+    /// Tries again every set aside insert whose backoff has passed.
+    #[instrument(skip(self), fields(waiting = self.retries.len()))]
+    async fn retry_due(&mut self) -> usize {
+        let now = Instant::now();
+        let (due, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.retries)
+            .into_iter()
+            .partition(|retry| retry.due <= now);
+        self.retries = waiting;
+        let chunks = due
+            .into_iter()
+            .map(|retry| {
+                self.counters
+                    .retrying
+                    .fetch_sub(retry.rows.len(), Ordering::Relaxed);
+                (retry.rows, retry.attempt)
+            })
+            .collect();
+        self.write(chunks).await
+    }
+
+    /// Inserts each chunk of clicks, on the attempt given with it, returning how many rows
+    /// were inserted. The visitors of the inserted clicks are then merged into the unique
+    /// visitor sketches and the clicks published to live subscribers and webhooks. A chunk
+    /// that fails is set aside to be retried after a backoff, or moved to the dead letter
+    /// store once it has used up its retries.
+    async fn write(&mut self, chunks: Vec<(Vec<ViewInput>, u32)>) -> usize {
+        let mut written = Written::default();
+        for (rows, attempt) in chunks {
+            let models: Vec<views::ActiveModel> = rows
+                .iter()
+                .cloned()
+                .map(|row| view_model(row, &self.geoip, &self.privacy))
+                .collect();
+            match self.insert(&models).await {
+                Ok(()) => self.inserted(&mut written, rows, models).await,
+                // One bad row, such as a click on a link purged since it was recorded, fails
+                // the whole insert, so the rows are tried one at a time to keep the rest
+                Err(e) if rows.len() > 1 && !db_err_is_retryable(&e) => {
+                    for (row, model) in rows.into_iter().zip(models) {
+                        match self.insert(std::slice::from_ref(&model)).await {
+                            Ok(()) => self.inserted(&mut written, vec![row], vec![model]).await,
+                            Err(e) => self.failed(vec![row], e, attempt),
+                        }
+                    }
+                }
+                Err(e) => self.failed(rows, e, attempt),
+            }
+        }
+        if written.rows == 0 {
+            return 0;
+        }
+        if let Err(e) = add_visitors(&self.conn, written.visitors).await {
+            warn!("Unable to count unique visitors: {e}");
+        }
+        self.feed.publish(&self.conn, written.events).await;
+        self.counters
+            .written
+            .fetch_add(written.rows, Ordering::Relaxed);
+        written.rows
+    }
+
+    async fn insert(&self, models: &[views::ActiveModel]) -> Result<(), DbErr> {
+        views::Entity::insert_many(models.iter().cloned())
+            .exec_without_returning(&self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Adds inserted clicks to `written`, counting their visitors.
+    async fn inserted(
+        &mut self,
+        written: &mut Written,
+        rows: Vec<ViewInput>,
+        models: Vec<views::ActiveModel>,
+    ) {
+        written.rows += rows.len();
+        for (row, model) in rows.iter().zip(&models) {
+            let bot = matches!(model.bot, ActiveValue::Set(true));
+            let day = row.created_at.date();
+            let salt = match self.salts.get(&self.conn, day).await {
+                Ok(salt) => salt,
+                Err(e) => {
+                    warn!("Unable to get the visitor salt for {day}: {e}");
+                    None
+                }
+            };
+            if let Some(visitor) = salt.and_then(|salt| self.privacy.visitor(row, bot, &salt)) {
+                written
+                    .visitors
+                    .entry((row.id.clone(), day))
+                    .or_default()
+                    .insert(visitor);
+            }
+        }
+        written.events.extend(models.iter().map(ClickEvent::new));
+    }
+
+    /// Sets aside clicks whose insert failed to be retried, or moves them to the dead letter
+    /// store once they have used up their retries. A click on a link that no longer exists is
+    /// dropped, as it can never be written.
+    fn failed(&mut self, rows: Vec<ViewInput>, e: DbErr, attempt: u32) {
+        let count = rows.len();
+        if let [row] = rows.as_slice()
+            && matches!(e.sql_err(), Some(SqlErr::ForeignKeyConstraintViolation(_)))
+        {
+            debug!("Dropping a view of url {}, which was deleted", row.id);
+            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let e = ActorError::from(e);
+        if self.retry.should_retry(&e, attempt) {
+            let delay = self.retry.delay(attempt);
+            warn!("Unable to record {count} views: {e}; retrying in {delay:?}");
+            self.counters.retrying.fetch_add(count, Ordering::Relaxed);
+            self.retries.push(PendingRetry {
+                due: Instant::now() + delay,
+                rows,
+                attempt: attempt + 1,
+            });
+            return;
+        }
+        error!("Unable to record {count} views: {e}; giving up after {attempt} attempts");
+        self.counters.failed.fetch_add(count, Ordering::Relaxed);
+        self.dead_letters
+            .push(DeadLetterPayload::Views(rows), &e, attempt);
+    }
+}
+
+/// The clicks one write inserted, and what is passed on about them once it is done.
+#[derive(Default)]
+struct Written {
+    rows: usize,
+    visitors: HashMap<(String, NaiveDate), HyperLogLog>,
+    events: Vec<ClickEvent>,
+}
+
+/// Builds the row for a click, keeping only what the privacy settings allow. A visitor who
+/// opted out of tracking is still counted, but nothing about them is stored beyond whether
+/// they were a bot.
+fn view_model(click: ViewInput, geoip: &GeoIp, privacy: &ViewPrivacy) -> views::ActiveModel {
+    let ViewInput {
+        id,
+        cached,
+        click,
+        created_at,
+    } = click;
+    let agent = parse_user_agent(click.user_agent.as_deref());
+    // Browsers always follow a redirect with a GET
+    let bot = agent.bot || click.head;
+    if privacy.opted_out(&click) {
+        return views::ActiveModel {
+            short_link: ActiveValue::Set(id),
+            device: ActiveValue::Set(Some(DeviceClass::Unknown)),
+            bot: ActiveValue::Set(bot),
+            cache_hit: ActiveValue::Set(cached),
+            created_at: ActiveValue::Set(created_at),
+            ..Default::default()
+        };
+    }
+    let headers = match click
+        .headers
+        .as_ref()
+        .and_then(|headers| privacy.headers(headers))
+        .map(HeaderMapDef::try_from)
+        .transpose()
+    {
+        Ok(headers) => headers,
+        Err(e) => {
+            error!(
+                "Unable to serialize the headers for url {id}: {}",
+                e.to_string()
+            );
+            None
+        }
+    };
+    let (ip, ip_hash) = click.ip.map(|ip| privacy.ip(ip)).unwrap_or_default();
+    let location = click.ip.map(|ip| geoip.lookup(ip)).unwrap_or_default();
+    views::ActiveModel {
+        short_link: ActiveValue::Set(id),
+        ip: ActiveValue::Set(ip),
+        ip_hash: ActiveValue::Set(ip_hash),
+        headers: ActiveValue::Set(headers.map(|headers| json!(headers))),
+        referrer: ActiveValue::Set(click.referrer.filter(|_| privacy.keeps(&header::REFERER))),
+        user_agent: ActiveValue::Set(
+            click
+                .user_agent
+                .filter(|_| privacy.keeps(&header::USER_AGENT)),
+        ),
+        browser: ActiveValue::Set(agent.browser),
+        os: ActiveValue::Set(agent.os),
+        device: ActiveValue::Set(Some(agent.device)),
+        bot: ActiveValue::Set(bot),
+        country: ActiveValue::Set(location.country),
+        region: ActiveValue::Set(location.region),
+        city: ActiveValue::Set(location.city),
+        asn: ActiveValue::Set(location.asn.map(i64::from)),
+        as_org: ActiveValue::Set(location.as_org),
+        cache_hit: ActiveValue::Set(cached),
+        created_at: ActiveValue::Set(created_at),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_channel::{Sender, bounded};
+    use axum::http::{HeaderMap, HeaderValue};
+    use chrono::Utc;
+    use sea_orm::query::*;
+    use tokio::{sync::broadcast, time::sleep};
+
+    use super::*;
+    use crate::{
+        actor::Webhooks,
+        cache::{LinkCache, SharedMemoryCache},
+        config::{CacheConfig, GeoIpConfig, PrivacyConfig, WebhookConfig},
+        testing::{connect, create_link, link},
+        urls::click::ClickContext,
+    };
+
+    fn click() -> ViewInput {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::REFERER,
+            HeaderValue::from_static("https://example.com/"),
+        );
+        headers.insert(header::USER_AGENT, HeaderValue::from_static("curl/8.0"));
+        ViewInput {
+            id: "abc".to_string(),
+            cached: false,
+            click: ClickContext {
+                headers: Some(headers),
+                referrer: Some("https://example.com/".to_string()),
+                user_agent: Some("curl/8.0".to_string()),
+                ..Default::default()
+            },
+            created_at: Utc::now().naive_utc(),
+        }
+    }
+
+    fn privacy(allowlist: &[&str]) -> ViewPrivacy {
+        ViewPrivacy::new(&PrivacyConfig {
+            ip_salt: Some("salt".to_string()),
+            header_allowlist: allowlist.iter().map(|name| name.to_string()).collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn views_keep_the_referrer_and_user_agent_when_allowlisted() {
+        let geoip = GeoIp::from_config(&GeoIpConfig::default());
+        let view = view_model(click(), &geoip, &privacy(&["referer", "user-agent"]));
+        assert_eq!(
+            view.referrer,
+            ActiveValue::Set(Some("https://example.com/".to_string()))
+        );
+        assert_eq!(
+            view.user_agent,
+            ActiveValue::Set(Some("curl/8.0".to_string()))
+        );
+    }
+
+    #[test]
+    fn views_drop_the_referrer_and_user_agent_left_off_the_allowlist() {
+        let geoip = GeoIp::from_config(&GeoIpConfig::default());
+        let view = view_model(click(), &geoip, &privacy(&["referer"]));
+        assert_eq!(
+            view.referrer,
+            ActiveValue::Set(Some("https://example.com/".to_string()))
+        );
+        assert_eq!(view.user_agent, ActiveValue::Set(None));
+        // The parsed user agent is still kept
+        assert_eq!(view.bot, ActiveValue::Set(true));
+
+        let view = view_model(click(), &geoip, &privacy(&[]));
+        assert_eq!(view.referrer, ActiveValue::Set(None));
+        assert_eq!(view.user_agent, ActiveValue::Set(None));
+        assert_eq!(view.headers, ActiveValue::Set(None));
+    }
+
+    fn view(id: &str) -> ViewInput {
+        ViewInput {
+            id: id.to_string(),
+            ..click()
+        }
+    }
+
+    /// A running batcher, and what it reports through.
+    struct Running {
+        clicks: Sender<ViewInput>,
+        counters: Arc<ClickCounters>,
+        dead_letters: Arc<DeadLetterStore>,
+        done: mpsc::Receiver<usize>,
+    }
+
+    impl Running {
+        /// Closes the channel and waits for everything buffered to be written, returning how
+        /// many rows were.
+        async fn finish(self) -> usize {
+            drop(self.clicks);
+            tokio::task::spawn_blocking(move || self.done.recv().unwrap())
+                .await
+                .unwrap()
+        }
+    }
+
+    fn batcher(conn: &DatabaseConnection, batch_size: usize, flush_interval: Duration) -> Running {
+        let config = ActorPoolConfig {
+            click_batch_size: batch_size,
+            click_flush_interval: flush_interval,
+            ..Default::default()
+        };
+        let (clicks, receiver) = bounded(batch_size * 2);
+        let (queue, _) = bounded(1);
+        let counters = Arc::new(ClickCounters::default());
+        let dead_letters = Arc::new(DeadLetterStore::new(16));
+        let feed = ClickFeed {
+            live: broadcast::channel(16).0,
+            webhooks: Webhooks::new(
+                conn.clone(),
+                &WebhookConfig::default(),
+                &queue,
+                dead_letters.clone(),
+            ),
+            cache: LinkCache::new(SharedMemoryCache::new(&CacheConfig::default())),
+        };
+        let batcher = ClickBatcher::new(
+            receiver,
+            conn.clone(),
+            &config,
+            counters.clone(),
+            dead_letters.clone(),
+            GeoIp::from_config(&GeoIpConfig::default()),
+            feed,
+        );
+        let (done_sender, done) = mpsc::channel();
+        tokio::spawn(batcher.run(done_sender));
+        Running {
+            clicks,
+            counters,
+            dead_letters,
+            done,
+        }
+    }
+
+    async fn views_of(conn: &DatabaseConnection, id: &str) -> u64 {
+        views::Entity::find()
+            .filter(views::Column::ShortLink.eq(id))
+            .count(conn)
+            .await
+            .unwrap()
+    }
+
+    /// Waits until `id` has `count` views, failing the test if they aren't all written.
+    async fn wait_for_views(conn: &DatabaseConnection, id: &str, count: u64) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while views_of(conn, id).await < count {
+            assert!(Instant::now() < deadline, "Views were never written");
+            sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn full_batches_are_written_without_waiting_for_the_interval() {
+        let conn = connect().await;
+        let link = create_link(&conn, link(None)).await;
+        let running = batcher(&conn, 3, Duration::from_secs(60 * 60));
+
+        for _ in 0..4 {
+            running.clicks.send(view(&link.id)).await.unwrap();
+        }
+        wait_for_views(&conn, &link.id, 3).await;
+        // The fourth click waits for the next batch
+        sleep(Duration::from_millis(200)).await;
+        assert_eq!(views_of(&conn, &link.id).await, 3);
+        assert_eq!(running.counters.written.load(Ordering::Relaxed), 3);
+
+        assert_eq!(running.finish().await, 4);
+        assert_eq!(views_of(&conn, &link.id).await, 4);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn partial_batches_are_written_every_interval() {
+        let conn = connect().await;
+        let link = create_link(&conn, link(None)).await;
+        let running = batcher(&conn, 100, Duration::from_millis(100));
+
+        for _ in 0..2 {
+            running.clicks.send(view(&link.id)).await.unwrap();
+        }
+        wait_for_views(&conn, &link.id, 2).await;
+        assert_eq!(running.counters.written.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn batches_too_big_for_one_statement_are_split_into_inserts() {
+        let conn = connect().await;
+        let link = create_link(&conn, link(None)).await;
+        // More rows than fit in one statement's bind parameters
+        let count = MAX_ROWS_PER_INSERT * 2 + 1;
+        let running = batcher(&conn, count, Duration::from_secs(60 * 60));
+
+        for _ in 0..count {
+            running.clicks.send(view(&link.id)).await.unwrap();
+        }
+        let dead_letters = running.dead_letters.clone();
+        assert_eq!(running.finish().await, count);
+        assert_eq!(views_of(&conn, &link.id).await, count as u64);
+        assert!(dead_letters.list().letters.is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn clicks_on_deleted_links_dont_fail_the_rest_of_their_batch() {
+        let conn = connect().await;
+        let kept = create_link(&conn, link(None)).await;
+        let purged = link(None);
+        let running = batcher(&conn, 4, Duration::from_secs(60 * 60));
+
+        running.clicks.send(view(&kept.id)).await.unwrap();
+        running.clicks.send(view(&purged.id)).await.unwrap();
+        running.clicks.send(view(&kept.id)).await.unwrap();
+        running.clicks.send(view(&kept.id)).await.unwrap();
+        let counters = running.counters.clone();
+        let dead_letters = running.dead_letters.clone();
+        assert_eq!(running.finish().await, 3);
+        assert_eq!(views_of(&conn, &kept.id).await, 3);
+        assert_eq!(counters.dropped.load(Ordering::Relaxed), 1);
+        assert_eq!(counters.failed.load(Ordering::Relaxed), 0);
+        assert_eq!(counters.retrying.load(Ordering::Relaxed), 0);
+        assert!(dead_letters.list().letters.is_empty());
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDateTime;
+use entity::{short_link, views};
+use sea_orm::{DatabaseConnection, entity::*, query::*};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{
+    broadcast::{self, Receiver, error::RecvError},
+    watch,
+};
+use tracing::warn;
+use ts_rs::TS;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::Webhooks;
+use crate::{
+    cache::{LinkCache, ReplicaEvent},
+    utils::display_ip,
+};
+
+/// A click as it is recorded, carrying only what its view stored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct ClickEvent {
+    /// The short link that was clicked
+    pub id: String,
+    pub created_at: NaiveDateTime,
+    /// The visitor's address, or their network when addresses are truncated
+    #[ts(optional)]
+    pub ip: Option<String>,
+    /// A salted hash of the visitor's address when addresses are hashed
+    #[ts(optional)]
+    pub ip_hash: Option<String>,
+    #[ts(optional)]
+    pub referrer: Option<String>,
+    #[ts(optional)]
+    pub user_agent: Option<String>,
+    pub bot: bool,
+    #[serde(skip)]
+    #[ts(skip)]
+    pub(crate) owner: Option<Uuid>,
+}
+
+impl ClickEvent {
+    pub(super) fn new(model: &views::ActiveModel) -> Self {
+        Self {
+            id: model.short_link.try_as_ref().cloned().unwrap_or_default(),
+            created_at: model.created_at.try_as_ref().copied().unwrap_or_default(),
+            ip: model.ip.try_as_ref().copied().flatten().map(display_ip),
+            ip_hash: model.ip_hash.try_as_ref().cloned().flatten(),
+            referrer: model.referrer.try_as_ref().cloned().flatten(),
+            user_agent: model.user_agent.try_as_ref().cloned().flatten(),
+            bot: model.bot.try_as_ref().copied().unwrap_or_default(),
+            owner: None,
+        }
+    }
+}
+
+/// A click recorded by another replica, passed on with its owner so this replica's live
+/// subscribers can be matched against it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplicaClick {
+    pub owner: Option<Uuid>,
+    #[serde(flatten)]
+    pub click: ClickEvent,
+}
+
+/// Where recorded clicks go once they are written.
+#[derive(Debug, Clone)]
+pub(super) struct ClickFeed {
+    pub(super) live: broadcast::Sender<ClickEvent>,
+    pub(super) webhooks: Webhooks,
+    pub(super) cache: LinkCache,
+}
+
+impl ClickFeed {
+    /// Tags recorded clicks with the owner of their link, then sends them to the owners'
+    /// webhooks and to live subscribers, both here and on every other replica.
+    pub(super) async fn publish(&self, conn: &DatabaseConnection, mut events: Vec<ClickEvent>) {
+        if events.is_empty() {
+            return;
+        }
+        let ids: HashSet<String> = events.iter().map(|event| event.id.clone()).collect();
+        let owners: HashMap<String, Option<Uuid>> = match short_link::Entity::find()
+            .select_only()
+            .columns([short_link::Column::Id, short_link::Column::UserId])
+            .filter(short_link::Column::Id.is_in(ids))
+            .into_tuple::<(String, Option<Uuid>)>()
+            .all(conn)
+            .await
+        {
+            Ok(owners) => owners.into_iter().collect(),
+            Err(e) => {
+                warn!("Unable to look up who owns {} clicks: {e}", events.len());
+                return;
+            }
+        };
+        for event in &mut events {
+            event.owner = owners.get(&event.id).copied().flatten();
+        }
+        self.webhooks.clicks(&events).await;
+        let shared = ReplicaEvent::Clicks {
+            clicks: events
+                .iter()
+                .map(|event| ReplicaClick {
+                    owner: event.owner,
+                    click: event.clone(),
+                })
+                .collect(),
+        };
+        if let Err(e) = self.cache.publish(&shared).await {
+            warn!(
+                "Unable to send {} clicks to the other replicas: {e}",
+                events.len()
+            );
+        }
+        for event in events {
+            // Every subscriber may have gone away since the clicks were written
+            if self.live.send(event).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Passes the clicks other replicas record on to this replica's live subscribers, so a stream
+/// sees every click whichever replica it is connected to. Webhooks aren't sent these, as the
+/// replica that recorded a click has already queued its deliveries.
+pub(super) async fn relay_clicks(
+    mut events: broadcast::Receiver<ReplicaEvent>,
+    live: broadcast::Sender<ClickEvent>,
+) {
+    loop {
+        match events.recv().await {
+            Ok(ReplicaEvent::Clicks { clicks }) => {
+                for ReplicaClick { owner, mut click } in clicks {
+                    click.owner = owner;
+                    // Nobody listening isn't an error
+                    let _ = live.send(click);
+                }
+            }
+            Ok(_) => {}
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Missed {skipped} events from the other replicas, live clicks may be lost")
+            }
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+/// The clicks every replica records from the moment of subscribing.
+#[derive(Debug)]
+pub struct ClickSubscription {
+    pub(super) clicks: Receiver<ClickEvent>,
+    pub(super) stop: watch::Receiver<bool>,
+}
+
+impl ClickSubscription {
+    /// The next recorded click, or `Err` with how many clicks were skipped because this
+    /// subscriber fell behind. Returns `None` once the pool shuts down.
+    pub async fn next(&mut self) -> Option<Result<ClickEvent, u64>> {
+        if *self.stop.borrow() {
+            return None;
+        }
+        tokio::select! {
+            click = self.clicks.recv() => match click {
+                Ok(click) => Some(Ok(click)),
+                Err(RecvError::Lagged(skipped)) => Some(Err(skipped)),
+                Err(RecvError::Closed) => None,
+            },
+            _ = self.stop.wait_for(|stop| *stop) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{cache::SharedMemoryCache, config::CacheConfig};
+
+    fn click(owner: Uuid) -> ClickEvent {
+        ClickEvent {
+            id: "abc".to_string(),
+            created_at: Utc::now().naive_utc(),
+            ip: Some("192.0.2.0/24".to_string()),
+            ip_hash: None,
+            referrer: None,
+            user_agent: Some("curl/8.0".to_string()),
+            bot: false,
+            owner: Some(owner),
+        }
+    }
+
+    #[test]
+    fn shared_clicks_keep_their_owner() {
+        let owner = Uuid::new_v4();
+        let event = ReplicaEvent::Clicks {
+            clicks: vec![ReplicaClick {
+                owner: Some(owner),
+                click: click(owner),
+            }],
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        let ReplicaEvent::Clicks { clicks } = serde_json::from_str(&json).unwrap() else {
+            panic!("Decoded the wrong event from {json}");
+        };
+        assert_eq!(clicks[0].owner, Some(owner));
+        // The owner stays out of what live streams send
+        assert_eq!(clicks[0].click.owner, None);
+        assert!(
+            !serde_json::to_string(&clicks[0].click)
+                .unwrap()
+                .contains("owner")
+        );
+    }
+
+    #[tokio::test]
+    async fn clicks_reach_live_subscribers_on_other_replicas() {
+        let first = SharedMemoryCache::new(&CacheConfig::default());
+        let second = LinkCache::new(first.replica());
+        let first = LinkCache::new(first);
+        let (live, mut subscriber) = broadcast::channel(16);
+        tokio::spawn(relay_clicks(second.subscribe().unwrap(), live));
+
+        let owner = Uuid::new_v4();
+        let sent = click(owner);
+        first
+            .publish(&ReplicaEvent::Clicks {
+                clicks: vec![ReplicaClick {
+                    owner: sent.owner,
+                    click: sent.clone(),
+                }],
+            })
+            .await
+            .unwrap();
+        let received = tokio::time::timeout(Duration::from_secs(1), subscriber.recv())
+            .await
+            .expect("The click never reached the other replica");
+        assert_eq!(received.unwrap(), sent);
+    }
+}
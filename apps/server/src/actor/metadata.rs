@@ -0,0 +1,151 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use regex::Regex;
+use url::Url;
+
+const MAX_TITLE_CHARS: usize = 512;
+const MAX_DESCRIPTION_CHARS: usize = 2048;
+
+static TITLE_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)<title[^>]*>(.*?)</title\s*>").expect("Unable to compile Regex pattern")
+});
+
+static META_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<meta\s[^>]*>").expect("Unable to compile Regex pattern"));
+
+static LINK_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<link\s[^>]*>").expect("Unable to compile Regex pattern"));
+
+static ATTR_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?s)([a-zA-Z_:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#)
+        .expect("Unable to compile Regex pattern")
+});
+
+static ENTITY_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"&(#[xX][0-9a-fA-F]+|#[0-9]+|[a-zA-Z]+);").expect("Unable to compile Regex pattern")
+});
+
+/// The preview fields scraped from a link's destination page.
+#[derive(Debug, Clone, Default)]
+pub(super) struct PageMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub favicon: Option<String>,
+    pub og_image: Option<String>,
+}
+
+impl PageMetadata {
+    /// Extracts metadata from a (possibly truncated) HTML document served from `base`.
+    pub(super) fn parse(html: &str, base: &Url) -> Self {
+        let mut meta = Self::default();
+        let mut og_title = None;
+
+        if let Some(cap) = TITLE_PATTERN.captures(html) {
+            meta.title = clean_text(&cap[1], MAX_TITLE_CHARS);
+        }
+
+        for tag in META_PATTERN.find_iter(html) {
+            let attrs = attributes(tag.as_str());
+            let Some(content) = attrs.get("content") else {
+                continue;
+            };
+            let key = attrs
+                .get("property")
+                .or_else(|| attrs.get("name"))
+                .map(|key| key.to_ascii_lowercase());
+            match key.as_deref() {
+                Some("og:title") if og_title.is_none() => {
+                    og_title = clean_text(content, MAX_TITLE_CHARS);
+                }
+                Some("description") => {
+                    meta.description = clean_text(content, MAX_DESCRIPTION_CHARS);
+                }
+                Some("og:description") if meta.description.is_none() => {
+                    meta.description = clean_text(content, MAX_DESCRIPTION_CHARS);
+                }
+                Some("og:image" | "og:image:url" | "og:image:secure_url") => {
+                    meta.og_image = meta.og_image.or_else(|| resolve(base, content));
+                }
+                Some("twitter:image") if meta.og_image.is_none() => {
+                    meta.og_image = resolve(base, content);
+                }
+                _ => {}
+            }
+        }
+
+        let mut touch_icon = None;
+        for tag in LINK_PATTERN.find_iter(html) {
+            let attrs = attributes(tag.as_str());
+            let (Some(rel), Some(href)) = (attrs.get("rel"), attrs.get("href")) else {
+                continue;
+            };
+            let rel = rel.to_ascii_lowercase();
+            if rel.split_whitespace().any(|r| r == "icon") {
+                if meta.favicon.is_none() {
+                    meta.favicon = resolve(base, href);
+                }
+            } else if rel.split_whitespace().any(|r| r == "apple-touch-icon") {
+                touch_icon = touch_icon.or_else(|| resolve(base, href));
+            }
+        }
+
+        meta.title = meta.title.or(og_title);
+        meta.favicon = meta
+            .favicon
+            .or(touch_icon)
+            .or_else(|| resolve(base, "/favicon.ico"));
+        meta
+    }
+}
+
+fn attributes(tag: &str) -> HashMap<String, String> {
+    ATTR_PATTERN
+        .captures_iter(tag)
+        .map(|cap| {
+            let value = cap
+                .get(2)
+                .or_else(|| cap.get(3))
+                .or_else(|| cap.get(4))
+                .map(|v| decode_entities(v.as_str()))
+                .unwrap_or_default();
+            (cap[1].to_ascii_lowercase(), value)
+        })
+        .collect()
+}
+
+fn resolve(base: &Url, href: &str) -> Option<String> {
+    let url = base.join(href.trim()).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+fn clean_text(raw: &str, max_chars: usize) -> Option<String> {
+    let text = decode_entities(raw)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!text.is_empty()).then(|| text.chars().take(max_chars).collect())
+}
+
+fn decode_entities(raw: &str) -> String {
+    ENTITY_PATTERN
+        .replace_all(raw, |cap: &regex::Captures| {
+            let entity = &cap[1];
+            let decoded = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            };
+            decoded.map_or_else(|| cap[0].to_string(), String::from)
+        })
+        .into_owned()
+}
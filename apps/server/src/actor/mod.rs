@@ -1,9 +1,25 @@
 #[allow(clippy::module_inception)]
 pub(super) mod actor;
+mod clicks;
+mod live;
+mod metadata;
 mod msgs;
 mod pool;
+mod privacy;
+mod retry;
+mod scheduler;
 pub(super) mod tasks;
+mod webhooks;
 
 pub use actor::PoolableActor;
+pub use clicks::{ClickBackpressure, ClickStats};
+pub use live::{ClickEvent, ClickSubscription, ReplicaClick};
 pub use msgs::*;
 pub use pool::*;
+pub use privacy::{DEFAULT_HEADER_ALLOWLIST, IpStorage, ViewRetention};
+pub use retry::{DeadLetter, DeadLetterList, DeadLetterPayload, DeadLetterSummary};
+pub use scheduler::{Job, JobSchedule};
+pub use webhooks::{
+    WEBHOOK_EVENT_HEADER, WEBHOOK_ID_HEADER, WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER,
+    WebhookPayload, Webhooks, sign_webhook,
+};
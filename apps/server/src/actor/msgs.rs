@@ -1,21 +1,26 @@
-#[cfg(feature = "ips")]
-use std::net::IpAddr;
+use std::time::Duration;
 
-#[cfg(feature = "headers")]
-use axum::http::HeaderMap;
-use sea_orm::DatabaseConnection;
+use chrono::NaiveDateTime;
+use sea_orm::{DatabaseConnection, DbErr, RuntimeErr, TransactionError};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use uuid::Uuid;
 
-use crate::error::ServerError;
+use super::{ViewRetention, Webhooks, webhooks::WebhookPayload};
+use crate::{
+    cache::{CacheError, LinkCache},
+    error::ServerError,
+    urls::click::ClickContext,
+};
 
 #[derive(Debug, Clone, Default)]
 pub enum ActorInputMessage {
     #[default]
     None,
-    CleanSessions(DbInput),
-    CleanUrls(DbInput),
-    UpdateViews(ViewInput),
+    FetchMetadata(MetadataInput),
+    DeliverWebhook(WebhookInput),
+    /// A message queued again after it failed, with the attempt it is now on.
+    Retry(Box<ActorInputMessage>, u32),
 }
 
 #[derive(Debug, Clone, Default)]
@@ -23,40 +28,55 @@ pub struct DbInput {
     pub conn: DatabaseConnection,
 }
 
-#[cfg(all(feature = "headers", feature = "ips"))]
 #[derive(Debug, Clone)]
-pub struct ViewInput {
-    pub id: String,
-    pub cached: bool,
-    pub ip: IpAddr,
-    pub headers: HeaderMap,
+pub struct CleanUrlsInput {
     pub conn: DatabaseConnection,
+    pub trash_retention: Duration,
+    pub cache: LinkCache,
+    pub webhooks: Webhooks,
 }
 
-#[cfg(all(feature = "headers", not(feature = "ips")))]
 #[derive(Debug, Clone)]
-pub struct ViewInput {
-    pub id: String,
-    pub cached: bool,
-    pub headers: HeaderMap,
+pub struct ExpireViewsInput {
     pub conn: DatabaseConnection,
+    pub retention: Duration,
+    pub action: ViewRetention,
 }
 
-#[cfg(all(not(feature = "headers"), feature = "ips"))]
 #[derive(Debug, Clone)]
-pub struct ViewInput {
+pub struct PruneDeliveriesInput {
+    pub conn: DatabaseConnection,
+    pub retention: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct MetadataInput {
     pub id: String,
-    pub cached: bool,
-    pub ip: IpAddr,
+    pub url: String,
+    pub client: reqwest::Client,
+    pub timeout: Duration,
+    pub max_bytes: usize,
+    pub conn: DatabaseConnection,
+}
+
+/// One delivery to a webhook. The webhook's URL and secret are read when it is sent, so a
+/// retry goes wherever the webhook points by then.
+#[derive(Debug, Clone)]
+pub struct WebhookInput {
+    pub webhook: Uuid,
+    pub payload: WebhookPayload,
+    pub client: reqwest::Client,
+    pub timeout: Duration,
+    pub allow_private: bool,
     pub conn: DatabaseConnection,
 }
 
-#[cfg(all(not(feature = "headers"), not(feature = "ips")))]
 #[derive(Debug, Clone)]
 pub struct ViewInput {
     pub id: String,
     pub cached: bool,
-    pub conn: DatabaseConnection,
+    pub click: ClickContext,
+    pub created_at: NaiveDateTime,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -70,8 +90,138 @@ pub enum ActorError {
     DbErr(#[from] sea_orm::DbErr),
     #[error("Database transaction Error: {0}")]
     TransactionError(#[from] sea_orm::TransactionError<sea_orm::DbErr>),
+    #[error("Cache error: {0}")]
+    CacheError(#[from] CacheError),
     #[error("Actor error from server error: {0}")]
     ServerError(#[from] ServerError),
+    #[error("Webhook endpoint answered {status}")]
+    WebhookStatus { status: u16 },
+    #[error("Actor timeout: {msg}")]
+    Timeout { msg: String },
     #[error("Actor error: {msg}")]
     Basic { msg: String },
 }
+
+impl ActorError {
+    /// Whether the failure could clear up on its own, such as a dropped database connection or
+    /// a timed out request, so the message is worth trying again. Everything else is permanent.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::DbErr(e) => db_err_is_retryable(e),
+            Self::TransactionError(
+                TransactionError::Connection(e) | TransactionError::Transaction(e),
+            ) => db_err_is_retryable(e),
+            Self::CacheError(CacheError::Redis(e)) => {
+                e.is_io_error() || e.is_timeout() || e.is_connection_dropped()
+            }
+            Self::CacheError(CacheError::Decode(_)) => false,
+            Self::ServerError(ServerError::DbError(e)) => db_err_is_retryable(e),
+            Self::ServerError(ServerError::Request(e)) => e.is_timeout() || e.is_connect(),
+            Self::ServerError(_) => false,
+            // Timeouts, rate limits and server errors
+            Self::WebhookStatus { status } => matches!(status, 408 | 429 | 500..),
+            Self::Timeout { .. } => true,
+            Self::Basic { .. } => false,
+        }
+    }
+}
+
+pub(super) fn db_err_is_retryable(e: &DbErr) -> bool {
+    match e {
+        DbErr::ConnectionAcquire(_) | DbErr::Conn(_) => true,
+        DbErr::Exec(RuntimeErr::SqlxError(e)) | DbErr::Query(RuntimeErr::SqlxError(e)) => {
+            match e {
+                sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::WorkerCrashed => true,
+                // Serialization failures, deadlocks, shutdowns and lost connections
+                sqlx::Error::Database(e) => matches!(
+                    e.code().as_deref(),
+                    Some(
+                        "40001"
+                            | "40P01"
+                            | "57P01"
+                            | "57P02"
+                            | "57P03"
+                            | "08000"
+                            | "08003"
+                            | "08006"
+                    )
+                ),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::error::ConnAcquireErr;
+
+    use super::*;
+
+    fn conn_lost() -> DbErr {
+        DbErr::Conn(RuntimeErr::Internal("connection reset".to_string()))
+    }
+
+    #[test]
+    fn lost_connections_are_retried() {
+        assert!(ActorError::from(conn_lost()).is_retryable());
+        assert!(ActorError::from(DbErr::ConnectionAcquire(ConnAcquireErr::Timeout)).is_retryable());
+        assert!(
+            ActorError::from(DbErr::Exec(RuntimeErr::SqlxError(
+                sqlx::Error::PoolTimedOut
+            )))
+            .is_retryable()
+        );
+        assert!(ActorError::from(TransactionError::Connection(conn_lost())).is_retryable());
+        assert!(ActorError::from(ServerError::DbError(conn_lost())).is_retryable());
+        assert!(
+            ActorError::Timeout {
+                msg: "Took too long".to_string()
+            }
+            .is_retryable()
+        );
+    }
+
+    #[test]
+    fn permanent_failures_are_not_retried() {
+        assert!(!ActorError::from(DbErr::RecordNotFound("abc".to_string())).is_retryable());
+        assert!(
+            !ActorError::from(DbErr::Query(RuntimeErr::SqlxError(
+                sqlx::Error::RowNotFound
+            )))
+            .is_retryable()
+        );
+        assert!(
+            !ActorError::from(TransactionError::Transaction(DbErr::RecordNotFound(
+                "abc".to_string()
+            )))
+            .is_retryable()
+        );
+        let decode = serde_json::from_str::<u8>("x").unwrap_err();
+        assert!(!ActorError::from(CacheError::from(decode)).is_retryable());
+        assert!(!ActorError::from(ServerError::Unauthorized).is_retryable());
+        assert!(
+            !ActorError::Basic {
+                msg: "Bad input".to_string()
+            }
+            .is_retryable()
+        );
+    }
+
+    #[test]
+    fn webhook_statuses_are_retried_when_they_could_clear_up() {
+        for status in [408, 429, 500, 502, 503] {
+            assert!(
+                ActorError::WebhookStatus { status }.is_retryable(),
+                "{status}"
+            );
+        }
+        for status in [400, 401, 403, 404, 410, 422] {
+            assert!(
+                !ActorError::WebhookStatus { status }.is_retryable(),
+                "{status}"
+            );
+        }
+    }
+}
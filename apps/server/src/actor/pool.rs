@@ -1,48 +1,105 @@
 use std::{
-    env,
     ops::Drop,
     sync::{
-        Arc,
+        Arc, Mutex,
         atomic::{AtomicUsize, Ordering},
+        mpsc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use async_channel::{SendError, Sender, bounded};
-use chrono::TimeDelta;
-use sea_orm::DatabaseConnection;
+use async_channel::{SendError, Sender, TrySendError, bounded};
+use entity::{sea_orm_active_enums::WebhookEvent, webhook, webhook_delivery};
+use futures_util::future::join_all;
+use sea_orm::{DatabaseConnection, DbErr};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use tokio::{
     runtime::{Builder, Handle, Runtime},
-    sync::RwLock,
-    time::sleep,
+    sync::{Notify, RwLock, broadcast, watch},
+    task::JoinHandle,
+    time::{sleep, timeout},
 };
-use tracing::instrument;
+use tracing::{instrument, warn};
+use uuid::Uuid;
 
 use super::{
-    ActorInputMessage, DbInput,
+    ActorError, ActorInputMessage, ViewInput, Webhooks,
     actor::{DefaultActor, PoolableActor},
+    clicks::{ClickBackpressure, ClickBatcher, ClickCounters, ClickStats},
+    live::{ClickEvent, ClickFeed, ClickSubscription, relay_clicks},
+    retry::{DeadLetterList, DeadLetterPayload, DeadLetterStore, RetryPolicy},
+    scheduler::{Job, JobContext, JobScheduler},
 };
 use crate::{
-    config::GetConfig,
-    utils::{parse_duration, parse_time_delta},
+    cache::LinkCache,
+    config::{ConfigSource, LoadConfig, PrivacyConfig, SchedulerConfig, WebhookConfig},
+    geoip::GeoIp,
+    user_agent::UserAgentParser,
 };
 
 #[derive(Clone, Debug)]
 pub struct ActorPool {
     in_sender: Option<Sender<ActorInputMessage>>,
-    rt: Arc<Runtime>,
+    click_sender: Option<Sender<ViewInput>>,
+    click_backpressure: ClickBackpressure,
+    click_overflow_capacity: usize,
+    click_counters: Arc<ClickCounters>,
+    clicks_done: Arc<Mutex<mpsc::Receiver<usize>>>,
+    workers: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    scheduler_wake: Arc<Notify>,
+    scheduler_stop: Arc<watch::Sender<bool>>,
+    dead_letters: Arc<DeadLetterStore>,
+    live: broadcast::Sender<ClickEvent>,
+    live_stop: Arc<watch::Sender<bool>>,
+    webhooks: Webhooks,
+    rt: Option<Arc<Runtime>>,
 }
 
+/// What [`ActorPool::drain`] managed to finish before the pool stopped.
+#[derive(Debug, Clone, Default)]
+pub struct DrainReport {
+    /// Views written while draining
+    pub flushed: usize,
+    /// Clicks dropped or moved to the dead letter store while draining, plus any still
+    /// buffered when the drain timed out
+    pub dropped: usize,
+    /// Background messages still queued when the workers stopped
+    pub unprocessed: usize,
+    pub timed_out: bool,
+}
+
+/// How long dropping the last pool handle waits for buffered clicks to be written.
+const CLICK_FLUSH_TIMEOUT: Duration = Duration::from_secs(3);
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ActorPoolConfig {
-    pub clean_sessions: Duration,
-    pub clean_links: Duration,
     pub workers: usize,
     pub blocking_workers: usize,
     pub stack_size: usize,
     pub keep_alive: Duration,
     pub event_interval: u32,
+    pub trash_retention: Duration,
+    pub metadata_timeout: Duration,
+    pub metadata_max_bytes: usize,
+    pub click_batch_size: usize,
+    pub click_flush_interval: Duration,
+    pub click_channel_capacity: usize,
+    pub click_backpressure: ClickBackpressure,
+    pub click_overflow_capacity: usize,
+    pub live_channel_capacity: usize,
+    pub drain_timeout: Duration,
+    pub max_retries: u32,
+    pub retry_backoff: Duration,
+    pub retry_max_backoff: Duration,
+    pub dead_letter_capacity: usize,
+    #[serde(flatten)]
+    pub jobs: SchedulerConfig,
+    #[serde(flatten)]
+    pub privacy: PrivacyConfig,
+    #[serde(flatten)]
+    pub webhooks: WebhookConfig,
 }
 
 impl Default for ActorPoolConfig {
@@ -53,175 +110,101 @@ impl Default for ActorPoolConfig {
             stack_size: 2 * usize::pow(1024, 2),
             keep_alive: Duration::from_secs(10),
             event_interval: 61,
-            clean_sessions: Duration::from_secs(15),
-            clean_links: Duration::from_secs(1800),
+            trash_retention: Duration::from_secs(30 * 24 * 60 * 60),
+            metadata_timeout: Duration::from_secs(5),
+            metadata_max_bytes: 512 * 1024,
+            click_batch_size: 500,
+            click_flush_interval: Duration::from_secs(1),
+            click_channel_capacity: 10_000,
+            click_backpressure: ClickBackpressure::default(),
+            click_overflow_capacity: 10_000,
+            live_channel_capacity: 1024,
+            drain_timeout: Duration::from_secs(10),
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+            retry_max_backoff: Duration::from_secs(30),
+            dead_letter_capacity: 1000,
+            jobs: SchedulerConfig::default(),
+            privacy: PrivacyConfig::default(),
+            webhooks: WebhookConfig::default(),
         }
     }
 }
 
-impl GetConfig for ActorPoolConfig {
-    #[instrument]
-    fn from_env() -> Self {
-        dotenvy::dotenv().ok();
-        let workers: usize = env::var("ACTOR_WORKERS")
-            .ok()
-            .map(|s| {
-                s.parse()
-                    .expect("Unable to coerce ACTOR_WORKERS into an integer")
-            })
-            .unwrap_or(4);
-        let blocking_workers: usize = env::var("ACTOR_BLOCKING_WORKERS")
-            .ok()
-            .map(|s| {
-                s.parse()
-                    .expect("Unable to coerce ACTOR_BLOCKING_WORKERS into an integer")
-            })
-            .unwrap_or(2);
-        let stack_size: usize = env::var("ACTOR_STACK_SIZE")
-            .ok()
-            .map(|s| {
-                s.parse()
-                    .expect("Unable to coerce ACTOR_STACK_SIZE into an integer")
-            })
-            .unwrap_or(2 * usize::pow(1024, 2));
-        let keep_alive: Duration = env::var("ACTOR_KEEP_ALIVE")
-            .ok()
-            .map(|s| {
-                parse_duration(&s)
-                    .expect("Unable to coerce ACTOR_KEEP_ALIVE into a duration string")
-            })
-            .unwrap_or(Duration::from_secs(10));
-        let event_interval: u32 = env::var("ACTOR_EVENT_INTERVAL")
-            .ok()
-            .map(|s| {
-                s.parse()
-                    .expect("Unable to coerce ACTOR_EVENT_INTERVAL into an integer")
-            })
-            .unwrap_or(61);
-        let clean_sessions = env::var("SESSION_CLEAN_INTERVAL")
-            .ok()
-            .map(|s| {
-                parse_time_delta(&s)
-                    .expect("Unable to coerce SESSION_CLEAN_INTERVAL to a duration string")
-            })
-            .unwrap_or(TimeDelta::seconds(10))
-            .to_std()
-            .expect("SESSION_CLEAN_INTERVAL is too large");
-        let clean_links = env::var("SHORT_LINKS_CLEAN_INTERVAL")
-            .ok()
-            .map(|s| {
-                parse_time_delta(&s)
-                    .expect("Unable to coerce SHORT_LINKS_CLEAN_INTERVAL into a duration string")
-            })
-            .unwrap_or(TimeDelta::minutes(30))
-            .to_std()
-            .expect("SHORT_LINKS_CLEAN_INTERVAL is too large");
+impl LoadConfig for ActorPoolConfig {
+    fn load(source: &ConfigSource) -> Self {
+        let defaults = Self::default();
         Self {
-            workers,
-            blocking_workers,
-            stack_size,
-            keep_alive,
-            event_interval,
-            clean_sessions,
-            clean_links,
+            workers: source.parse("ACTOR_WORKERS").unwrap_or(defaults.workers),
+            blocking_workers: source
+                .parse("ACTOR_BLOCKING_WORKERS")
+                .unwrap_or(defaults.blocking_workers),
+            stack_size: source
+                .parse("ACTOR_STACK_SIZE")
+                .unwrap_or(defaults.stack_size),
+            keep_alive: source
+                .duration("ACTOR_KEEP_ALIVE")
+                .unwrap_or(defaults.keep_alive),
+            event_interval: source
+                .parse("ACTOR_EVENT_INTERVAL")
+                .unwrap_or(defaults.event_interval),
+            trash_retention: source
+                .long_duration("SHORT_LINKS_TRASH_RETENTION")
+                .unwrap_or(defaults.trash_retention),
+            metadata_timeout: source
+                .duration("LINK_METADATA_TIMEOUT")
+                .unwrap_or(defaults.metadata_timeout),
+            metadata_max_bytes: source
+                .parse("LINK_METADATA_MAX_BYTES")
+                .unwrap_or(defaults.metadata_max_bytes),
+            click_batch_size: source
+                .parse("CLICK_BATCH_SIZE")
+                .unwrap_or(defaults.click_batch_size),
+            click_flush_interval: source
+                .duration("CLICK_FLUSH_INTERVAL")
+                .unwrap_or(defaults.click_flush_interval),
+            click_channel_capacity: source
+                .parse("CLICK_CHANNEL_CAPACITY")
+                .unwrap_or(defaults.click_channel_capacity),
+            click_backpressure: source
+                .parse("CLICK_BACKPRESSURE")
+                .unwrap_or(defaults.click_backpressure),
+            click_overflow_capacity: source
+                .parse("CLICK_OVERFLOW_CAPACITY")
+                .unwrap_or(defaults.click_overflow_capacity),
+            live_channel_capacity: source
+                .parse("LIVE_CHANNEL_CAPACITY")
+                .unwrap_or(defaults.live_channel_capacity),
+            drain_timeout: source
+                .duration("SHUTDOWN_DRAIN_TIMEOUT")
+                .unwrap_or(defaults.drain_timeout),
+            max_retries: source
+                .parse("ACTOR_MAX_RETRIES")
+                .unwrap_or(defaults.max_retries),
+            retry_backoff: source
+                .duration("ACTOR_RETRY_BACKOFF")
+                .unwrap_or(defaults.retry_backoff),
+            retry_max_backoff: source
+                .duration("ACTOR_RETRY_MAX_BACKOFF")
+                .unwrap_or(defaults.retry_max_backoff),
+            dead_letter_capacity: source
+                .parse("DEAD_LETTER_CAPACITY")
+                .unwrap_or(defaults.dead_letter_capacity),
+            jobs: SchedulerConfig::load(source),
+            privacy: PrivacyConfig::load(source),
+            webhooks: WebhookConfig::load(source),
         }
     }
-
-    #[instrument(skip(secrets))]
-    fn from_secret(secrets: shuttle_runtime::SecretStore) -> Self {
-        let workers: usize = secrets
-            .get("ACTOR_WORKERS")
-            .map(|s| {
-                s.parse()
-                    .expect("Unable to coerce ACTOR_WORKERS into an integer")
-            })
-            .unwrap_or(4);
-        let blocking_workers: usize = secrets
-            .get("ACTOR_BLOCKING_WORKERS")
-            .map(|s| {
-                s.parse()
-                    .expect("Unable to coerce ACTOR_BLOCKING_WORKERS into an integer")
-            })
-            .unwrap_or(2);
-        let stack_size: usize = secrets
-            .get("ACTOR_STACK_SIZE")
-            .map(|s| {
-                s.parse()
-                    .expect("Unable to coerce ACTOR_STACK_SIZE into an integer")
-            })
-            .unwrap_or(2 * usize::pow(1024, 2));
-        let keep_alive: Duration = secrets
-            .get("ACTOR_KEEP_ALIVE")
-            .map(|s| parse_duration(&s).expect("Unable to coerce ACTOR_KEEP_ALIVE into a duration"))
-            .unwrap_or(Duration::from_secs(10));
-        let event_interval: u32 = secrets
-            .get("ACTOR_EVENT_INTERVAL")
-            .map(|s| {
-                s.parse()
-                    .expect("Unable to coerce ACTOR_EVENT_INTERVAL into an integer")
-            })
-            .unwrap_or(61);
-        let clean_sessions = secrets
-            .get("SESSION_CLEAN_INTERVAL")
-            .map(|s| {
-                parse_time_delta(&s)
-                    .expect("Unable to coerce SESSION_CLEAN_INTERVAL to a duration string")
-            })
-            .unwrap_or(TimeDelta::seconds(10))
-            .to_std()
-            .expect("SESSION_CLEAN_INTERVAL is too large");
-        let clean_links = secrets
-            .get("SHORT_LINKS_CLEAN_INTERVAL")
-            .map(|s| {
-                parse_time_delta(&s)
-                    .expect("Unable to coerce SHORT_LINKS_CLEAN_INTERVAL into a duration string")
-            })
-            .unwrap_or(TimeDelta::minutes(30))
-            .to_std()
-            .expect("SHORT_LINKS_CLEAN_INTERVAL is too large");
-        Self {
-            workers,
-            blocking_workers,
-            stack_size,
-            keep_alive,
-            event_interval,
-            clean_sessions,
-            clean_links,
-        }
-    }
-}
-
-async fn schedule_clean_sessions(
-    in_sender: Sender<ActorInputMessage>,
-    duration: Duration,
-    conn: DatabaseConnection,
-) -> Result<(), SendError<ActorInputMessage>> {
-    loop {
-        in_sender
-            .send(ActorInputMessage::CleanSessions(DbInput {
-                conn: conn.clone(),
-            }))
-            .await?;
-        sleep(duration).await;
-    }
-}
-
-async fn schedule_clean_links(
-    in_sender: Sender<ActorInputMessage>,
-    duration: Duration,
-    conn: DatabaseConnection,
-) -> Result<(), SendError<ActorInputMessage>> {
-    loop {
-        in_sender
-            .send(ActorInputMessage::CleanUrls(DbInput { conn: conn.clone() }))
-            .await?;
-        sleep(duration).await;
-    }
 }
 
 impl ActorPool {
     #[instrument]
-    pub fn new(config: &ActorPoolConfig, conn: DatabaseConnection) -> Self {
+    pub fn new(
+        config: &ActorPoolConfig,
+        conn: DatabaseConnection,
+        cache: LinkCache,
+        geoip: GeoIp,
+    ) -> Self {
         let num_channels = (config.workers + config.blocking_workers + 2) * 2;
         let (in_sender, in_receiver) = bounded(num_channels);
         let rt = Builder::new_multi_thread()
@@ -238,32 +221,111 @@ impl ActorPool {
             .event_interval(config.event_interval)
             .build()
             .expect("Unable to build tokio runtime");
-        for _ in 0..config.workers {
-            let in_receiver = in_receiver.clone();
-            rt.spawn(async move { DefaultActor::new(in_receiver).run().await });
+        let retry = RetryPolicy::new(config);
+        let dead_letters = Arc::new(DeadLetterStore::new(config.dead_letter_capacity));
+        let mut workers = (0..config.workers)
+            .map(|_| {
+                let in_receiver = in_receiver.clone();
+                let requeue = in_sender.downgrade();
+                let dead_letters = dead_letters.clone();
+                rt.spawn(async move {
+                    DefaultActor::new(in_receiver, requeue, retry, dead_letters)
+                        .run()
+                        .await
+                })
+            })
+            .collect::<Vec<_>>();
+        let (click_sender, click_receiver) = bounded(config.click_channel_capacity.max(1));
+        let (done_sender, clicks_done) = mpsc::channel();
+        // Compile the user agent rules now rather than on the first click
+        UserAgentParser::bundled();
+        let click_counters = Arc::new(ClickCounters::default());
+        let (live, _) = broadcast::channel(config.live_channel_capacity.max(1));
+        let webhooks = Webhooks::new(
+            conn.clone(),
+            &config.webhooks,
+            &in_sender,
+            dead_letters.clone(),
+        );
+        let batcher = ClickBatcher::new(
+            click_receiver,
+            conn.clone(),
+            config,
+            click_counters.clone(),
+            dead_letters.clone(),
+            geoip,
+            ClickFeed {
+                live: live.clone(),
+                webhooks: webhooks.clone(),
+                cache: cache.clone(),
+            },
+        );
+        rt.spawn(batcher.run(done_sender));
+        if let Some(events) = cache.subscribe() {
+            rt.spawn(relay_clicks(events, live.clone()));
         }
-        let duration = config.clean_sessions;
-        let in_cleaner = in_sender.clone();
-        let db_conn = conn.clone();
-        rt.spawn(async move { schedule_clean_sessions(in_cleaner, duration, db_conn).await });
-        let duration = config.clean_links;
-        let in_cleaner = in_sender.clone();
-        rt.spawn(async move { schedule_clean_links(in_cleaner, duration, conn).await });
+        let scheduler_wake = Arc::new(Notify::new());
+        let (scheduler_stop, stop) = watch::channel(false);
+        let scheduler = JobScheduler {
+            context: JobContext {
+                conn,
+                cache,
+                trash_retention: config.trash_retention,
+                view_retention: config.privacy.retention,
+                view_retention_action: config.privacy.retention_action,
+                webhooks: webhooks.clone(),
+                webhook_delivery_retention: config.webhooks.delivery_retention,
+            },
+            jobs: vec![
+                (Job::CleanSessions, config.jobs.clean_sessions.clone()),
+                (Job::CleanLinks, config.jobs.clean_links.clone()),
+                (Job::ParseUserAgents, config.jobs.parse_user_agents.clone()),
+                (Job::RollupViews, config.jobs.rollup_views.clone()),
+                (Job::ExpireViews, config.jobs.expire_views.clone()),
+                (
+                    Job::PruneWebhookDeliveries,
+                    config.jobs.prune_webhook_deliveries.clone(),
+                ),
+            ],
+            replica: Uuid::new_v4(),
+            lease: config.jobs.lease,
+            poll_interval: config.jobs.poll_interval,
+            wake: scheduler_wake.clone(),
+            stop,
+        };
+        workers.push(rt.spawn(scheduler.run()));
 
         Self {
             in_sender: Some(in_sender),
-            rt: Arc::new(rt),
+            click_sender: Some(click_sender),
+            click_backpressure: config.click_backpressure,
+            click_overflow_capacity: config.click_overflow_capacity,
+            click_counters,
+            clicks_done: Arc::new(Mutex::new(clicks_done)),
+            workers: Arc::new(Mutex::new(workers)),
+            scheduler_wake,
+            scheduler_stop: Arc::new(scheduler_stop),
+            dead_letters,
+            live,
+            live_stop: Arc::new(watch::channel(false).0),
+            webhooks,
+            rt: Some(Arc::new(rt)),
         }
     }
 
     #[instrument]
-    pub fn new_locked(config: &ActorPoolConfig, conn: DatabaseConnection) -> Arc<RwLock<Self>> {
-        Arc::new(RwLock::new(Self::new(config, conn)))
+    pub fn new_locked(
+        config: &ActorPoolConfig,
+        conn: DatabaseConnection,
+        cache: LinkCache,
+        geoip: GeoIp,
+    ) -> Arc<RwLock<Self>> {
+        Arc::new(RwLock::new(Self::new(config, conn, cache, geoip)))
     }
 
     #[instrument]
     pub fn handle(&self) -> &Handle {
-        self.rt.handle()
+        self.rt.as_ref().expect("The actor pool is closed").handle()
     }
 
     #[instrument]
@@ -274,6 +336,31 @@ impl ActorPool {
         }
     }
 
+    /// Queues `msg` without waiting for room, for callers such as request handlers that
+    /// shouldn't stall on background work. When the queue is full the message goes to the dead
+    /// letter store to be replayed.
+    #[instrument]
+    pub fn try_send(&self, msg: ActorInputMessage) -> Result<(), SendError<ActorInputMessage>> {
+        let Some(in_sender) = &self.in_sender else {
+            return Err(SendError(msg));
+        };
+        match in_sender.try_send(msg) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(msg)) => {
+                warn!("The actor queue is full, moving the message to the dead letter store");
+                self.dead_letters.push(
+                    DeadLetterPayload::Task(msg),
+                    &ActorError::Basic {
+                        msg: "The actor queue was full".to_string(),
+                    },
+                    0,
+                );
+                Ok(())
+            }
+            Err(TrySendError::Closed(msg)) => Err(SendError(msg)),
+        }
+    }
+
     #[instrument]
     pub fn send_blocking(
         &self,
@@ -285,12 +372,214 @@ impl ActorPool {
         }
     }
 
+    /// Queues a click for the batched `views` insert without waiting. When the click channel is
+    /// full the configured backpressure policy decides whether the click is parked in the
+    /// overflow buffer or dropped; either way the caller carries on and losses only show up in
+    /// [`ActorPool::click_stats`].
+    #[instrument]
+    pub fn record_click(&self, view: ViewInput) {
+        let counters = &self.click_counters;
+        let Some(click_sender) = &self.click_sender else {
+            counters.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+        let view = match click_sender.try_send(view) {
+            Ok(()) => {
+                counters.queued.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            Err(TrySendError::Full(view)) => view,
+            Err(TrySendError::Closed(_)) => {
+                counters.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        };
+        let overflow_reserved = self.click_backpressure == ClickBackpressure::Buffer
+            && counters
+                .overflowing
+                .try_update(Ordering::AcqRel, Ordering::Acquire, |parked| {
+                    (parked < self.click_overflow_capacity).then_some(parked + 1)
+                })
+                .is_ok();
+        if !overflow_reserved {
+            if counters.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+                warn!("The click channel is full, views are being dropped");
+            }
+            return;
+        }
+        let Some(rt) = &self.rt else {
+            counters.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+        counters.overflowed.fetch_add(1, Ordering::Relaxed);
+        let click_sender = click_sender.clone();
+        let counters = counters.clone();
+        rt.spawn(async move {
+            if click_sender.send(view).await.is_err() {
+                counters.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            counters.overflowing.fetch_sub(1, Ordering::AcqRel);
+        });
+    }
+
+    /// Wakes the job scheduler so a job marked due runs without waiting for the next poll.
+    #[instrument]
+    pub fn wake_scheduler(&self) {
+        self.scheduler_wake.notify_one();
+    }
+
+    #[instrument]
+    pub fn dead_letters(&self) -> DeadLetterList {
+        self.dead_letters.list()
+    }
+
+    /// Sends dead letter `id`, or every dead letter when `id` is `None`, back through the pool,
+    /// returning how many were replayed. A message that can't be queued stays in the store.
+    #[instrument]
+    pub async fn replay_dead_letters(&self, id: Option<u64>) -> usize {
+        let mut replayed = 0;
+        for letter in self.dead_letters.take(id) {
+            match &letter.payload {
+                DeadLetterPayload::Task(msg) => {
+                    if self.send(msg.clone()).await.is_err() {
+                        self.dead_letters.restore(letter);
+                        continue;
+                    }
+                }
+                DeadLetterPayload::Views(views) => {
+                    for view in views {
+                        self.record_click(view.clone());
+                    }
+                }
+            }
+            replayed += 1;
+        }
+        replayed
+    }
+
+    /// Subscribes to the clicks recorded from now on. With a cache shared through Redis this
+    /// includes the clicks other replicas record.
+    #[instrument]
+    pub fn subscribe_clicks(&self) -> ClickSubscription {
+        ClickSubscription {
+            clicks: self.live.subscribe(),
+            stop: self.live_stop.subscribe(),
+        }
+    }
+
+    /// Ends every click subscription, so open live streams don't hold up shutdown.
+    #[instrument]
+    pub fn close_live_streams(&self) {
+        self.live_stop.send_replace(true);
+    }
+
+    /// Queues `event` for the webhooks `user` has subscribed to it, with `data` as the payload.
+    /// Links without an owner have nobody to notify.
+    #[instrument(skip(data))]
+    pub async fn notify_webhooks(
+        &self,
+        user: Option<Uuid>,
+        event: WebhookEvent,
+        data: &impl Serialize,
+    ) {
+        if let Some(user) = user {
+            self.webhooks.notify(user, event, json!(data)).await;
+        }
+    }
+
+    /// Sends a `ping` to `hook` right away and returns the logged attempt.
+    #[instrument(skip(hook), fields(webhook = %hook.id))]
+    pub async fn test_webhook(
+        &self,
+        hook: &webhook::Model,
+    ) -> Result<webhook_delivery::Model, DbErr> {
+        self.webhooks.test(hook).await
+    }
+
+    #[instrument]
+    pub fn click_stats(&self) -> ClickStats {
+        let channel_len = self.click_sender.as_ref().map_or(0, |sender| sender.len());
+        self.click_counters.snapshot(channel_len)
+    }
+
+    /// Stops the pool for shutdown. Parked clicks are handed to the batcher, both channels are
+    /// closed so the batcher writes out its buffer and the workers finish what is queued, and
+    /// everything is given until `drain_timeout` to complete.
+    #[instrument]
+    pub async fn drain(&self, drain_timeout: Duration) -> DrainReport {
+        let deadline = Instant::now() + drain_timeout;
+        let counters = &self.click_counters;
+        let before = self.click_stats();
+
+        while counters.overflowing.load(Ordering::Acquire) > 0 && Instant::now() < deadline {
+            sleep(Duration::from_millis(10)).await;
+        }
+        if let Some(click_sender) = &self.click_sender {
+            click_sender.close();
+        }
+        let mut timed_out = true;
+        while Instant::now() < deadline {
+            if self.clicks_flushed() {
+                timed_out = false;
+                break;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        let mut unprocessed = 0;
+        self.scheduler_stop.send_replace(true);
+        if let Some(in_sender) = &self.in_sender {
+            in_sender.close();
+            let workers = match self.workers.lock() {
+                Ok(mut workers) => std::mem::take(&mut *workers),
+                Err(_) => Vec::new(),
+            };
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if timeout(remaining, join_all(workers)).await.is_err() {
+                timed_out = true;
+            }
+            unprocessed = in_sender.len();
+        }
+
+        let after = self.click_stats();
+        DrainReport {
+            flushed: after.written - before.written,
+            dropped: (after.dropped - before.dropped)
+                + (after.failed - before.failed)
+                + after.pending,
+            unprocessed,
+            timed_out,
+        }
+    }
+
+    fn clicks_flushed(&self) -> bool {
+        match self.clicks_done.lock() {
+            Ok(clicks_done) => !matches!(clicks_done.try_recv(), Err(mpsc::TryRecvError::Empty)),
+            Err(_) => true,
+        }
+    }
+
     #[instrument]
     pub fn close(&mut self) {
-        if let Some(rt) = Arc::into_inner(self.rt.clone()) {
-            rt.shutdown_timeout(Duration::from_secs(3));
+        // Once the last handle lets go of its click sender the channel closes, and the batcher
+        // writes out whatever it still holds before exiting.
+        if self.click_sender.take().is_some()
+            && Arc::strong_count(&self.clicks_done) == 1
+            && let Ok(clicks_done) = self.clicks_done.lock()
+            && let Err(mpsc::RecvTimeoutError::Timeout) =
+                clicks_done.recv_timeout(CLICK_FLUSH_TIMEOUT)
+        {
+            warn!("Timed out waiting for buffered clicks to be written");
         }
         self.in_sender.take();
+        if let Some(rt) = self.rt.take().and_then(Arc::into_inner) {
+            // Blocking on the runtime's threads isn't allowed from inside another runtime
+            if Handle::try_current().is_ok() {
+                rt.shutdown_background();
+            } else {
+                rt.shutdown_timeout(Duration::from_secs(3));
+            }
+        }
     }
 }
 
@@ -0,0 +1,284 @@
+use std::{collections::HashMap, net::IpAddr};
+
+use axum::http::{HeaderMap, HeaderName};
+use chrono::{Days, NaiveDate, Utc};
+use entity::visitor_salt;
+use sea_orm::{
+    DatabaseConnection, DbErr, entity::*, prelude::IpNetwork, query::*, sea_query::OnConflict,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+use uuid::Uuid;
+
+use super::ViewInput;
+use crate::{config::PrivacyConfig, urls::click::ClickContext};
+
+/// How much of a visitor's IP address a view keeps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpStorage {
+    /// The whole address.
+    #[default]
+    Full,
+    /// Only the network, cut down to `VIEW_IPV4_PREFIX` or `VIEW_IPV6_PREFIX` bits.
+    Truncate,
+    /// A salted SHA-256 of the address in `ip_hash`, which still tells visitors apart.
+    Hash,
+    /// Nothing at all.
+    Drop,
+}
+
+impl std::str::FromStr for IpStorage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "full" => Ok(Self::Full),
+            "truncate" => Ok(Self::Truncate),
+            "hash" => Ok(Self::Hash),
+            "drop" => Ok(Self::Drop),
+            other => Err(format!("Unknown IP storage mode {other:?}")),
+        }
+    }
+}
+
+/// What the retention job does with views older than `VIEW_RETENTION`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewRetention {
+    /// Delete the views.
+    #[default]
+    Delete,
+    /// Keep the views for counting but strip everything that identifies the visitor.
+    Anonymize,
+}
+
+impl std::str::FromStr for ViewRetention {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "delete" => Ok(Self::Delete),
+            "anonymize" => Ok(Self::Anonymize),
+            other => Err(format!("Unknown view retention action {other:?}")),
+        }
+    }
+}
+
+/// The headers a view keeps when `VIEW_HEADER_ALLOWLIST` isn't set.
+pub const DEFAULT_HEADER_ALLOWLIST: [&str; 3] = ["user-agent", "referer", "accept-language"];
+
+/// Decides what a view may remember about the visitor.
+#[derive(Debug, Clone)]
+pub(super) struct ViewPrivacy {
+    ip: IpStorage,
+    ipv4_prefix: u8,
+    ipv6_prefix: u8,
+    salt: String,
+    headers: Vec<HeaderName>,
+    honor_do_not_track: bool,
+}
+
+impl ViewPrivacy {
+    pub(super) fn new(config: &PrivacyConfig) -> Self {
+        let salt = config.ip_salt.clone().unwrap_or_else(|| {
+            warn!(
+                "VIEW_IP_SALT isn't set, so IP hashes use a random salt and won't match across \
+                 restarts or replicas"
+            );
+            Uuid::new_v4().simple().to_string()
+        });
+        let headers = config
+            .header_allowlist
+            .iter()
+            .filter_map(|name| match HeaderName::try_from(name.trim()) {
+                Ok(name) => Some(name),
+                Err(e) => {
+                    warn!("Ignoring {name:?} in the view header allowlist: {e}");
+                    None
+                }
+            })
+            .collect();
+        Self {
+            ip: config.ip_storage,
+            ipv4_prefix: config.ipv4_prefix.min(32),
+            ipv6_prefix: config.ipv6_prefix.min(128),
+            salt,
+            headers,
+            honor_do_not_track: config.honor_do_not_track,
+        }
+    }
+
+    /// Whether the visitor asked not to be tracked, and that wish is honoured.
+    pub(super) fn opted_out(&self, click: &ClickContext) -> bool {
+        self.honor_do_not_track && click.do_not_track
+    }
+
+    /// The address and hash to store for `ip`, as allowed by the storage mode.
+    pub(super) fn ip(&self, ip: IpAddr) -> (Option<IpNetwork>, Option<String>) {
+        let full = match ip {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        match self.ip {
+            IpStorage::Full => (IpNetwork::new(ip, full).ok(), None),
+            IpStorage::Truncate => {
+                let prefix = match ip {
+                    IpAddr::V4(_) => self.ipv4_prefix,
+                    IpAddr::V6(_) => self.ipv6_prefix,
+                };
+                let network = IpNetwork::new(ip, prefix)
+                    .and_then(|network| IpNetwork::new(network.network(), prefix));
+                (network.ok(), None)
+            }
+            IpStorage::Hash => {
+                let mut hasher = Sha256::new();
+                hasher.update(self.salt.as_bytes());
+                match ip {
+                    IpAddr::V4(ip) => hasher.update(ip.octets()),
+                    IpAddr::V6(ip) => hasher.update(ip.octets()),
+                }
+                (None, Some(format!("{:x}", hasher.finalize())))
+            }
+            IpStorage::Drop => (None, None),
+        }
+    }
+
+    /// The fingerprint a click counts towards unique visitors with: a hash of the IP address
+    /// and user agent salted with `salt`, the random salt of the click's day, so a visitor
+    /// can't be followed from one day to the next. Bots, visitors who opted out and clicks
+    /// without an IP address have none.
+    pub(super) fn visitor(&self, click: &ViewInput, bot: bool, salt: &[u8]) -> Option<u64> {
+        let ip = click.click.ip?;
+        if bot || self.opted_out(&click.click) {
+            return None;
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(salt);
+        match ip {
+            IpAddr::V4(ip) => hasher.update(ip.octets()),
+            IpAddr::V6(ip) => hasher.update(ip.octets()),
+        }
+        hasher.update(click.click.user_agent.as_deref().unwrap_or_default());
+        let digest = hasher.finalize();
+        let mut fingerprint = [0; 8];
+        fingerprint.copy_from_slice(&digest[..8]);
+        Some(u64::from_be_bytes(fingerprint))
+    }
+
+    /// Whether header `name` is allowlisted, which also decides whether the view keeps it in its
+    /// own column, such as the referrer or user agent.
+    pub(super) fn keeps(&self, name: &HeaderName) -> bool {
+        self.headers.contains(name)
+    }
+
+    /// Only the allowlisted headers, or `None` when none of them were sent.
+    pub(super) fn headers(&self, headers: &HeaderMap) -> Option<HeaderMap> {
+        let mut kept = HeaderMap::new();
+        for name in &self.headers {
+            for value in headers.get_all(name) {
+                kept.append(name.clone(), value.clone());
+            }
+        }
+        (!kept.is_empty()).then_some(kept)
+    }
+}
+
+/// The random salts visitor fingerprints are made with, one per day. A day's salt is created
+/// by the first replica that needs it and shared with the others through `visitor_salt`. Once
+/// a day is over its salt is only kept until the end of the next one, for clicks still waiting
+/// to be written at midnight, and is then deleted, so nobody can recompute that day's
+/// fingerprints afterwards.
+#[derive(Debug, Default)]
+pub(super) struct VisitorSalts {
+    salts: HashMap<NaiveDate, Vec<u8>>,
+}
+
+impl VisitorSalts {
+    /// The salt of `day`, or `None` once it has been deleted.
+    pub(super) async fn get(
+        &mut self,
+        conn: &DatabaseConnection,
+        day: NaiveDate,
+    ) -> Result<Option<Vec<u8>>, DbErr> {
+        let oldest = Utc::now().date_naive() - Days::new(1);
+        self.salts.retain(|day, _| *day >= oldest);
+        if day < oldest {
+            return Ok(None);
+        }
+        if let Some(salt) = self.salts.get(&day) {
+            return Ok(Some(salt.clone()));
+        }
+        visitor_salt::Entity::delete_many()
+            .filter(visitor_salt::Column::Day.lt(oldest))
+            .exec(conn)
+            .await?;
+        visitor_salt::Entity::insert(visitor_salt::ActiveModel {
+            day: Set(day),
+            salt: Set(rand::random::<[u8; 32]>().to_vec()),
+        })
+        .on_conflict(
+            OnConflict::column(visitor_salt::Column::Day)
+                .do_nothing()
+                .to_owned(),
+        )
+        .do_nothing()
+        .exec(conn)
+        .await?;
+        let salt = visitor_salt::Entity::find_by_id(day)
+            .one(conn)
+            .await?
+            .map(|row| row.salt);
+        if let Some(salt) = &salt {
+            self.salts.insert(day, salt.clone());
+        }
+        Ok(salt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::connect;
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn replicas_share_a_salt_until_the_day_after() {
+        let conn = connect().await;
+        let today = Utc::now().date_naive();
+        let stale = today - Days::new(2);
+        visitor_salt::Entity::insert(visitor_salt::ActiveModel {
+            day: Set(stale),
+            salt: Set(vec![0; 32]),
+        })
+        .on_conflict(
+            OnConflict::column(visitor_salt::Column::Day)
+                .do_nothing()
+                .to_owned(),
+        )
+        .do_nothing()
+        .exec(&conn)
+        .await
+        .unwrap();
+
+        let mut first = VisitorSalts::default();
+        let mut second = VisitorSalts::default();
+        let salt = first.get(&conn, today).await.unwrap().unwrap();
+        assert_eq!(salt.len(), 32);
+        assert_eq!(second.get(&conn, today).await.unwrap(), Some(salt.clone()));
+        assert_ne!(
+            first.get(&conn, today - Days::new(1)).await.unwrap(),
+            Some(salt)
+        );
+
+        assert_eq!(first.get(&conn, stale).await.unwrap(), None);
+        assert!(
+            visitor_salt::Entity::find_by_id(stale)
+                .one(&conn)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::ActiveEnum;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use ts_rs::TS;
+use utoipa::ToSchema;
+
+use super::{ActorError, ActorInputMessage, ActorPoolConfig, ViewInput};
+
+/// How often a failed message is retried, with the delay doubling after every attempt.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct RetryPolicy {
+    pub(super) max_retries: u32,
+    pub(super) backoff: Duration,
+    pub(super) max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub(super) fn new(config: &ActorPoolConfig) -> Self {
+        Self {
+            max_retries: config.max_retries,
+            backoff: config.retry_backoff,
+            max_backoff: config.retry_max_backoff,
+        }
+    }
+
+    /// Whether a message that failed on attempt `attempt` (starting at 1) should be tried again.
+    pub(super) fn should_retry(&self, error: &ActorError, attempt: u32) -> bool {
+        error.is_retryable() && attempt <= self.max_retries
+    }
+
+    /// The delay before the retry that follows attempt `attempt`.
+    pub(super) fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+}
+
+/// Work that failed for good and was set aside.
+#[derive(Debug, Clone)]
+pub enum DeadLetterPayload {
+    Task(ActorInputMessage),
+    Views(Vec<ViewInput>),
+}
+
+impl DeadLetterPayload {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Task(msg) => task_kind(msg),
+            Self::Views(_) => "views",
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Self::Task(msg) => describe_task(msg),
+            Self::Views(views) => format!("Record {} views", views.len()),
+        }
+    }
+}
+
+fn task_kind(msg: &ActorInputMessage) -> &'static str {
+    match msg {
+        ActorInputMessage::FetchMetadata(_) => "fetch_metadata",
+        ActorInputMessage::DeliverWebhook(_) => "deliver_webhook",
+        ActorInputMessage::None => "none",
+        ActorInputMessage::Retry(msg, _) => task_kind(msg),
+    }
+}
+
+fn describe_task(msg: &ActorInputMessage) -> String {
+    match msg {
+        ActorInputMessage::FetchMetadata(input) => {
+            format!("Fetch metadata for url {} from {}", input.id, input.url)
+        }
+        ActorInputMessage::DeliverWebhook(input) => format!(
+            "Deliver {} {} to webhook {}",
+            input.payload.event.to_value(),
+            input.payload.id,
+            input.webhook
+        ),
+        ActorInputMessage::None => "Empty message".to_string(),
+        ActorInputMessage::Retry(msg, _) => describe_task(msg),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub id: u64,
+    pub payload: DeadLetterPayload,
+    pub error: String,
+    pub retryable: bool,
+    pub attempts: u32,
+    pub failed_at: NaiveDateTime,
+}
+
+/// A dead letter as shown to operators.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct DeadLetterSummary {
+    pub id: u64,
+    pub kind: String,
+    pub description: String,
+    pub error: String,
+    /// Whether the last error was one that could clear up on its own
+    pub retryable: bool,
+    pub attempts: u32,
+    pub failed_at: NaiveDateTime,
+}
+
+impl From<&DeadLetter> for DeadLetterSummary {
+    fn from(letter: &DeadLetter) -> Self {
+        Self {
+            id: letter.id,
+            kind: letter.payload.kind().to_string(),
+            description: letter.payload.describe(),
+            error: letter.error.clone(),
+            retryable: letter.retryable,
+            attempts: letter.attempts,
+            failed_at: letter.failed_at,
+        }
+    }
+}
+
+/// The dead letter store's contents.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct DeadLetterList {
+    pub capacity: usize,
+    /// Dead letters pushed out to make room for newer ones
+    pub evicted: usize,
+    pub letters: Vec<DeadLetterSummary>,
+}
+
+/// Keeps the most recent `capacity` dead letters in memory, evicting the oldest once full.
+#[derive(Debug)]
+pub(super) struct DeadLetterStore {
+    capacity: usize,
+    next_id: AtomicU64,
+    evicted: AtomicUsize,
+    letters: Mutex<VecDeque<DeadLetter>>,
+}
+
+impl DeadLetterStore {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            next_id: AtomicU64::new(1),
+            evicted: AtomicUsize::new(0),
+            letters: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub(super) fn push(&self, payload: DeadLetterPayload, error: &ActorError, attempts: u32) {
+        let letter = DeadLetter {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            payload,
+            error: error.to_string(),
+            retryable: error.is_retryable(),
+            attempts,
+            failed_at: Utc::now().naive_utc(),
+        };
+        if self.capacity == 0 {
+            self.evicted.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let Ok(mut letters) = self.letters.lock() else {
+            warn!(
+                "The dead letter store is poisoned, discarding dead letter {}",
+                letter.id
+            );
+            return;
+        };
+        while letters.len() >= self.capacity {
+            letters.pop_front();
+            self.evicted.fetch_add(1, Ordering::Relaxed);
+        }
+        letters.push_back(letter);
+    }
+
+    pub(super) fn list(&self) -> DeadLetterList {
+        DeadLetterList {
+            capacity: self.capacity,
+            evicted: self.evicted.load(Ordering::Relaxed),
+            letters: self
+                .letters
+                .lock()
+                .map(|letters| letters.iter().map(DeadLetterSummary::from).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Removes and returns the dead letter `id`, or every dead letter when `id` is `None`.
+    pub(super) fn take(&self, id: Option<u64>) -> Vec<DeadLetter> {
+        let Ok(mut letters) = self.letters.lock() else {
+            return Vec::new();
+        };
+        match id {
+            Some(id) => letters
+                .iter()
+                .position(|letter| letter.id == id)
+                .and_then(|index| letters.remove(index))
+                .into_iter()
+                .collect(),
+            None => letters.drain(..).collect(),
+        }
+    }
+
+    /// Puts back a dead letter that could not be replayed.
+    pub(super) fn restore(&self, letter: DeadLetter) {
+        if let Ok(mut letters) = self.letters.lock()
+            && letters.len() < self.capacity
+        {
+            letters.push_front(letter);
+        } else {
+            self.evicted.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 3,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(3),
+        }
+    }
+
+    #[test]
+    fn delays_double_up_to_the_cap() {
+        let policy = policy();
+        let delays: Vec<Duration> = (1..=6).map(|attempt| policy.delay(attempt)).collect();
+        assert_eq!(
+            delays,
+            [500, 1000, 2000, 3000, 3000, 3000].map(Duration::from_millis)
+        );
+        // Far past the cap the multiplier saturates instead of overflowing
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(3));
+        assert_eq!(policy.delay(0), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn retries_stop_after_max_retries() {
+        let policy = policy();
+        let timeout = ActorError::Timeout {
+            msg: "Took too long".to_string(),
+        };
+        assert!(policy.should_retry(&timeout, 1));
+        assert!(policy.should_retry(&timeout, 3));
+        assert!(!policy.should_retry(&timeout, 4));
+        let basic = ActorError::Basic {
+            msg: "Bad input".to_string(),
+        };
+        assert!(!policy.should_retry(&basic, 1));
+    }
+
+    #[test]
+    fn the_store_keeps_the_newest_letters() {
+        let store = DeadLetterStore::new(2);
+        let error = ActorError::Basic {
+            msg: "Bad input".to_string(),
+        };
+        for _ in 0..3 {
+            store.push(DeadLetterPayload::Views(Vec::new()), &error, 1);
+        }
+        let list = store.list();
+        assert_eq!(list.evicted, 1);
+        assert_eq!(
+            list.letters
+                .iter()
+                .map(|letter| letter.id)
+                .collect::<Vec<_>>(),
+            [2, 3]
+        );
+
+        let taken = store.take(Some(2));
+        assert_eq!(taken.len(), 1);
+        assert!(store.take(Some(2)).is_empty());
+        store.restore(taken.into_iter().next().unwrap());
+        assert_eq!(store.take(None).len(), 2);
+        assert!(store.list().letters.is_empty());
+    }
+}
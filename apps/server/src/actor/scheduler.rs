@@ -0,0 +1,500 @@
+use std::{fmt, str::FromStr, sync::Arc, time::Duration};
+
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use entity::{job, sea_orm_active_enums::JobOutcome};
+use sea_orm::{
+    DatabaseConnection, DbErr,
+    entity::*,
+    query::*,
+    sea_query::{Expr, OnConflict},
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{Notify, watch},
+    time::{Instant, interval_at, sleep},
+};
+use tracing::{error, info, instrument, trace, warn};
+use uuid::Uuid;
+
+use super::{
+    ActorError, ActorOutputMessage, CleanUrlsInput, DbInput, ExpireViewsInput,
+    PruneDeliveriesInput, Webhooks,
+    privacy::ViewRetention,
+    tasks::{
+        clean_sessions, clean_urls, expire_views, parse_user_agents, prune_webhook_deliveries,
+        rollup_views,
+    },
+};
+use crate::{cache::LinkCache, utils::parse_time_delta};
+
+/// When a job runs: either a fixed interval such as `15s` or `30m`, or a cron expression. Cron
+/// expressions take the usual five fields, an optional leading seconds field and trailing year
+/// field, or a shorthand like `@daily`, and are evaluated in UTC.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct JobSchedule {
+    source: String,
+    kind: ScheduleKind,
+}
+
+#[derive(Clone, Debug)]
+enum ScheduleKind {
+    Every(TimeDelta),
+    Cron(Box<cron::Schedule>),
+}
+
+impl JobSchedule {
+    pub fn every(interval: Duration) -> Self {
+        Self {
+            source: format!("{}s", interval.as_secs().max(1)),
+            kind: ScheduleKind::Every(
+                TimeDelta::from_std(interval.max(Duration::from_secs(1))).unwrap_or(TimeDelta::MAX),
+            ),
+        }
+    }
+
+    /// The first time the job is due after a run that finished at `at`.
+    pub fn next_after(&self, at: NaiveDateTime) -> NaiveDateTime {
+        match &self.kind {
+            ScheduleKind::Every(interval) => at
+                .checked_add_signed(*interval)
+                .unwrap_or(NaiveDateTime::MAX),
+            ScheduleKind::Cron(schedule) => schedule
+                .after(&at.and_utc())
+                .next()
+                .map(|next| next.naive_utc())
+                .unwrap_or(NaiveDateTime::MAX),
+        }
+    }
+}
+
+impl FromStr for JobSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let source = s.trim();
+        let kind = if source.starts_with('@') || source.contains(char::is_whitespace) {
+            let fields = source.split_whitespace().count();
+            let expression = if fields == 5 {
+                format!("0 {source}")
+            } else {
+                source.to_string()
+            };
+            let schedule = cron::Schedule::from_str(&expression)
+                .map_err(|e| format!("Invalid cron schedule {source:?}: {e}"))?;
+            ScheduleKind::Cron(Box::new(schedule))
+        } else {
+            let interval = parse_time_delta(source)
+                .map_err(|e| format!("Invalid job interval {source:?}: {e}"))?;
+            if interval <= TimeDelta::zero() {
+                return Err(format!("Invalid job interval {source:?}"));
+            }
+            ScheduleKind::Every(interval)
+        };
+        Ok(Self {
+            source: source.to_string(),
+            kind,
+        })
+    }
+}
+
+impl TryFrom<String> for JobSchedule {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<JobSchedule> for String {
+    fn from(schedule: JobSchedule) -> Self {
+        schedule.source
+    }
+}
+
+impl fmt::Display for JobSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// The maintenance jobs the scheduler knows how to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Job {
+    CleanSessions,
+    CleanLinks,
+    ParseUserAgents,
+    RollupViews,
+    ExpireViews,
+    PruneWebhookDeliveries,
+}
+
+impl Job {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::CleanSessions => "clean_sessions",
+            Self::CleanLinks => "clean_links",
+            Self::ParseUserAgents => "parse_user_agents",
+            Self::RollupViews => "rollup_views",
+            Self::ExpireViews => "expire_views",
+            Self::PruneWebhookDeliveries => "prune_webhook_deliveries",
+        }
+    }
+}
+
+/// Everything a job needs to run.
+#[derive(Debug, Clone)]
+pub(super) struct JobContext {
+    pub(super) conn: DatabaseConnection,
+    pub(super) cache: LinkCache,
+    pub(super) trash_retention: Duration,
+    pub(super) view_retention: Option<Duration>,
+    pub(super) view_retention_action: ViewRetention,
+    pub(super) webhooks: Webhooks,
+    pub(super) webhook_delivery_retention: Duration,
+}
+
+impl JobContext {
+    #[instrument(skip(self))]
+    async fn run(&self, job: Job) -> Result<ActorOutputMessage, ActorError> {
+        match job {
+            Job::CleanSessions => {
+                clean_sessions(DbInput {
+                    conn: self.conn.clone(),
+                })
+                .await
+            }
+            Job::CleanLinks => {
+                clean_urls(CleanUrlsInput {
+                    conn: self.conn.clone(),
+                    trash_retention: self.trash_retention,
+                    cache: self.cache.clone(),
+                    webhooks: self.webhooks.clone(),
+                })
+                .await
+            }
+            Job::ParseUserAgents => {
+                parse_user_agents(DbInput {
+                    conn: self.conn.clone(),
+                })
+                .await
+            }
+            Job::RollupViews => {
+                rollup_views(DbInput {
+                    conn: self.conn.clone(),
+                })
+                .await
+            }
+            Job::ExpireViews => match self.view_retention {
+                Some(retention) => {
+                    expire_views(ExpireViewsInput {
+                        conn: self.conn.clone(),
+                        retention,
+                        action: self.view_retention_action,
+                    })
+                    .await
+                }
+                None => Ok(ActorOutputMessage {
+                    msg: "Views are kept forever as VIEW_RETENTION isn't set".to_string(),
+                }),
+            },
+            Job::PruneWebhookDeliveries => {
+                prune_webhook_deliveries(PruneDeliveriesInput {
+                    conn: self.conn.clone(),
+                    retention: self.webhook_delivery_retention,
+                })
+                .await
+            }
+        }
+    }
+}
+
+/// Runs the scheduled jobs, recording every run in the `job` table.
+///
+/// Replicas share the table, and a job only runs on the replica that claims it. Claiming sets
+/// `locked_by` and a `locked_until` lease in the same statement that checks the job is due and
+/// unclaimed, so exactly one replica wins; a replica that dies mid-run holds the job until the
+/// lease runs out. While a job runs the lease is extended every third of its length, and a run
+/// whose lease is lost anyway is stopped, so two replicas never run the same job at once.
+#[derive(Debug)]
+pub(super) struct JobScheduler {
+    pub(super) context: JobContext,
+    pub(super) jobs: Vec<(Job, JobSchedule)>,
+    pub(super) replica: Uuid,
+    pub(super) lease: Duration,
+    pub(super) poll_interval: Duration,
+    pub(super) wake: Arc<Notify>,
+    pub(super) stop: watch::Receiver<bool>,
+}
+
+impl JobScheduler {
+    #[instrument(skip(self), fields(replica = %self.replica))]
+    pub(super) async fn run(mut self) {
+        let mut registered = false;
+        while !*self.stop.borrow() {
+            if !registered {
+                registered = match self.register().await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        error!("Unable to register the scheduled jobs: {}", e.to_string());
+                        false
+                    }
+                };
+            }
+            if registered {
+                self.run_due().await;
+            }
+            tokio::select! {
+                _ = sleep(self.poll_interval) => (),
+                _ = self.wake.notified() => (),
+                _ = self.stop.changed() => (),
+            }
+        }
+        trace!("Job scheduler stopped");
+    }
+
+    /// Adds a row for every job. New jobs, and jobs whose schedule changed, are due straight
+    /// away; the rest keep their stored next run.
+    async fn register(&self) -> Result<(), DbErr> {
+        let now = Utc::now().naive_utc();
+        let rows = self.jobs.iter().map(|(job, schedule)| job::ActiveModel {
+            name: Set(job.name().to_string()),
+            schedule: Set(schedule.to_string()),
+            next_run_at: Set(now),
+            ..Default::default()
+        });
+        job::Entity::insert_many(rows)
+            .on_conflict(
+                OnConflict::column(job::Column::Name)
+                    .update_column(job::Column::Schedule)
+                    .value(
+                        job::Column::NextRunAt,
+                        Expr::cust(
+                            "CASE WHEN job.schedule = excluded.schedule THEN job.next_run_at ELSE \
+                             excluded.next_run_at END",
+                        ),
+                    )
+                    .to_owned(),
+            )
+            .exec_without_returning(&self.context.conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn run_due(&self) {
+        for (job, schedule) in &self.jobs {
+            if *self.stop.borrow() {
+                return;
+            }
+            match self.claim(*job).await {
+                Ok(true) => self.execute(*job, schedule).await,
+                Ok(false) => (),
+                Err(e) => error!("Unable to claim the {} job: {}", job.name(), e.to_string()),
+            }
+        }
+    }
+
+    async fn claim(&self, job: Job) -> Result<bool, DbErr> {
+        let now = Utc::now().naive_utc();
+        let lease = TimeDelta::from_std(self.lease).unwrap_or(TimeDelta::MAX);
+        let claimed = job::Entity::update_many()
+            .set(job::ActiveModel {
+                locked_by: Set(Some(self.replica)),
+                locked_until: Set(now.checked_add_signed(lease)),
+                ..Default::default()
+            })
+            .filter(job::Column::Name.eq(job.name()))
+            .filter(job::Column::NextRunAt.lte(now))
+            .filter(
+                Condition::any()
+                    .add(job::Column::LockedUntil.is_null())
+                    .add(job::Column::LockedUntil.lt(now)),
+            )
+            .exec(&self.context.conn)
+            .await?;
+        Ok(claimed.rows_affected == 1)
+    }
+
+    /// Pushes the lease on a claimed job back to a full `lease` from now, returning whether
+    /// this replica still held it.
+    async fn extend(&self, job: Job) -> Result<bool, DbErr> {
+        let lease = TimeDelta::from_std(self.lease).unwrap_or(TimeDelta::MAX);
+        let extended = job::Entity::update_many()
+            .set(job::ActiveModel {
+                locked_until: Set(Utc::now().naive_utc().checked_add_signed(lease)),
+                ..Default::default()
+            })
+            .filter(job::Column::Name.eq(job.name()))
+            .filter(job::Column::LockedBy.eq(self.replica))
+            .exec(&self.context.conn)
+            .await?;
+        Ok(extended.rows_affected == 1)
+    }
+
+    async fn execute(&self, job: Job, schedule: &JobSchedule) {
+        let started_at = Utc::now().naive_utc();
+        let timer = Instant::now();
+        let every = (self.lease / 3).max(Duration::from_secs(1));
+        let mut heartbeat = interval_at(timer + every, every);
+        let mut lease_until = timer + self.lease;
+        let run = self.context.run(job);
+        tokio::pin!(run);
+        let result = loop {
+            tokio::select! {
+                result = &mut run => break Some(result),
+                _ = heartbeat.tick() => match self.extend(job).await {
+                    Ok(true) => lease_until = Instant::now() + self.lease,
+                    Ok(false) => break None,
+                    Err(e) if Instant::now() >= lease_until => {
+                        error!("Unable to extend the {} job's lease: {}", job.name(), e.to_string());
+                        break None;
+                    }
+                    Err(e) => {
+                        warn!("Unable to extend the {} job's lease: {}", job.name(), e.to_string());
+                    }
+                },
+            }
+        };
+        let duration = timer.elapsed();
+        let Some(result) = result else {
+            error!(
+                "The {} job was stopped after {duration:?} as its lease was lost; another replica \
+                 may have claimed it",
+                job.name()
+            );
+            return;
+        };
+        let finished_at = Utc::now().naive_utc();
+
+        let (outcome, last_error) = match result {
+            Ok(msg) => {
+                info!(
+                    "The {} job finished in {duration:?}: {}",
+                    job.name(),
+                    msg.msg
+                );
+                (JobOutcome::Success, None)
+            }
+            Err(e) => {
+                error!("The {} job failed after {duration:?}: {e}", job.name());
+                (JobOutcome::Failure, Some(e.to_string()))
+            }
+        };
+
+        let recorded = job::Entity::update_many()
+            .set(job::ActiveModel {
+                next_run_at: Set(schedule.next_after(finished_at)),
+                locked_by: Set(None),
+                locked_until: Set(None),
+                last_started_at: Set(Some(started_at)),
+                last_finished_at: Set(Some(finished_at)),
+                last_duration_ms: Set(Some(duration.as_millis().try_into().unwrap_or(i64::MAX))),
+                last_outcome: Set(Some(outcome)),
+                last_error: Set(last_error),
+                ..Default::default()
+            })
+            .filter(job::Column::Name.eq(job.name()))
+            .filter(job::Column::LockedBy.eq(self.replica))
+            .exec(&self.context.conn)
+            .await;
+        match recorded {
+            Ok(recorded) if recorded.rows_affected == 0 => error!(
+                "The {} job run wasn't recorded as its lease was lost before it finished",
+                job.name()
+            ),
+            Ok(_) => (),
+            Err(e) => error!(
+                "Unable to record the {} job run: {}",
+                job.name(),
+                e.to_string()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32, min: u32, sec: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 10, day)
+            .unwrap()
+            .and_hms_opt(hour, min, sec)
+            .unwrap()
+    }
+
+    fn schedule(source: &str) -> JobSchedule {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn intervals_run_after_the_last_run() {
+        assert_eq!(schedule("15s").next_after(at(1, 0, 0, 0)), at(1, 0, 0, 15));
+        assert_eq!(
+            schedule(" 30m ").next_after(at(1, 23, 45, 0)),
+            at(2, 0, 15, 0)
+        );
+        assert_eq!(schedule("1d").next_after(at(1, 12, 0, 0)), at(2, 12, 0, 0));
+        assert_eq!(
+            JobSchedule::every(Duration::from_secs(90)).next_after(at(1, 0, 0, 0)),
+            at(1, 0, 1, 30)
+        );
+        // Sub-second intervals would spin
+        assert_eq!(
+            JobSchedule::every(Duration::ZERO).next_after(at(1, 0, 0, 0)),
+            at(1, 0, 0, 1)
+        );
+    }
+
+    #[test]
+    fn cron_expressions_run_at_the_next_match() {
+        // Five fields, with seconds added
+        assert_eq!(
+            schedule("*/15 * * * *").next_after(at(1, 10, 7, 30)),
+            at(1, 10, 15, 0)
+        );
+        assert_eq!(
+            schedule("0 3 * * *").next_after(at(1, 3, 0, 0)),
+            at(2, 3, 0, 0)
+        );
+        // Six fields, starting with seconds
+        assert_eq!(
+            schedule("30 0 * * * *").next_after(at(1, 10, 0, 30)),
+            at(1, 11, 0, 30)
+        );
+        assert_eq!(
+            schedule("@daily").next_after(at(1, 10, 0, 0)),
+            at(2, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn schedules_keep_their_source() {
+        assert_eq!(schedule(" @hourly ").to_string(), "@hourly");
+        assert_eq!(String::from(schedule("5m")), "5m");
+        assert_eq!(
+            JobSchedule::every(Duration::from_secs(300)).to_string(),
+            "300s"
+        );
+    }
+
+    #[test]
+    fn invalid_schedules_are_rejected() {
+        for source in [
+            "",
+            "0s",
+            "-5m",
+            "soon",
+            "* * *",
+            "61 * * * *",
+            "@fortnightly",
+        ] {
+            assert!(
+                source.parse::<JobSchedule>().is_err(),
+                "{source:?} was accepted"
+            );
+        }
+    }
+}
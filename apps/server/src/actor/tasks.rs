@@ -1,30 +1,81 @@
-use chrono::Utc;
-use entity::{sessions, short_link, views};
-#[cfg(feature = "ips")]
-use sea_orm::prelude::IpNetwork;
-use sea_orm::{DbConn, entity::*, query::*};
+use std::{collections::HashMap, time::Duration};
+
+use axum::http::header;
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta, Utc};
+use entity::{
+    daily_views, hourly_views, sea_orm_active_enums::WebhookEvent, sessions, short_link, views,
+    webhook, webhook_delivery,
+};
+use sea_orm::{
+    FromQueryResult,
+    entity::*,
+    query::*,
+    sea_query::{Expr, OnConflict},
+};
 use serde_json::json;
-use tracing::{error, instrument, trace};
+use tracing::{error, instrument};
+use url::Url;
+
+use super::{
+    ActorError, ActorOutputMessage, CleanUrlsInput, DbInput, ExpireViewsInput, MetadataInput,
+    PruneDeliveriesInput, ViewRetention, WebhookInput, metadata::PageMetadata, webhooks::deliver,
+};
+use crate::{
+    error::ServerError,
+    urls::stats::{REFERRER, USER_AGENT},
+    user_agent::{ParsedUserAgent, UserAgentParser},
+    utils::ensure_public_url,
+};
 
-use super::{ActorError, ActorOutputMessage, DbInput, ViewInput};
-#[cfg(feature = "headers")]
-use crate::utils::HeaderMapDef;
+const MAX_METADATA_REDIRECTS: usize = 5;
+/// How many views the user agent backfill reads at a time.
+const USER_AGENT_BATCH: u64 = 1000;
+/// The most views one backfill run parses, so a large backlog is spread over several runs.
+const USER_AGENT_MAX_PER_RUN: usize = 100_000;
+/// How many views the retention job deletes or anonymizes per statement.
+const VIEW_RETENTION_BATCH: u64 = 10_000;
+/// How many views the rollup job folds into the rollup tables per transaction.
+const ROLLUP_BATCH: u64 = 5000;
+/// The most views one rollup run folds in, so a large backlog is spread over several runs.
+const ROLLUP_MAX_PER_RUN: usize = 500_000;
+/// The referring host, which is all an anonymized view keeps of its referrer.
+const REFERRER_HOST: &str = r#"lower(substring(COALESCE("views"."referrer", "views"."headers"->'referer'->>0) from '^[A-Za-z][A-Za-z0-9+.-]*://([^/:?#@]+)'))"#;
 
 #[instrument]
-pub(super) async fn clean_urls(db: DbInput) -> Result<ActorOutputMessage, ActorError> {
-    let DbInput { conn } = db;
+pub(super) async fn clean_urls(input: CleanUrlsInput) -> Result<ActorOutputMessage, ActorError> {
+    let CleanUrlsInput {
+        conn,
+        trash_retention,
+        cache,
+        webhooks,
+    } = input;
+
+    let now = Utc::now().naive_utc();
+    let trash_cutoff = TimeDelta::from_std(trash_retention)
+        .ok()
+        .and_then(|retention| now.checked_sub_signed(retention))
+        .unwrap_or(NaiveDateTime::MIN);
 
     let txn = conn.begin().await?;
 
     let links = short_link::Entity::find()
-        .filter(short_link::Column::ExpiryDate.lt(Utc::now().naive_utc()))
+        .filter(
+            Condition::any()
+                .add(short_link::Column::ExpiryDate.lt(now))
+                .add(short_link::Column::DeletedAt.lt(trash_cutoff)),
+        )
         .all(&txn)
         .await?;
 
     let count = links.len();
+    let ids: Vec<String> = links.iter().map(|link| link.id.clone()).collect();
 
+    let mut expired = Vec::new();
     for link in links {
-        link.delete(&txn).await?;
+        link.clone().delete(&txn).await?;
+        if link.deleted_at.is_none() {
+            expired.push(link);
+        }
     }
 
     match txn.commit().await {
@@ -38,8 +89,19 @@ pub(super) async fn clean_urls(db: DbInput) -> Result<ActorOutputMessage, ActorE
         }
     };
 
+    cache.invalidate(&ids).await?;
+
+    // Links already in the trash were reported when they were deleted
+    for link in expired {
+        if let Some(user) = link.user_id {
+            webhooks
+                .notify(user, WebhookEvent::LinkExpired, json!(link))
+                .await;
+        }
+    }
+
     Ok(ActorOutputMessage {
-        msg: format!("Short links were cleaned deleting {count} urls"),
+        msg: format!("Short links were cleaned deleting {count} expired or trashed urls"),
     })
 }
 
@@ -76,119 +138,449 @@ pub(super) async fn clean_sessions(db: DbInput) -> Result<ActorOutputMessage, Ac
     })
 }
 
-#[cfg(all(not(feature = "ips"), not(feature = "headers")))]
+/// Parses the user agent of views recorded before parsing happened at record time.
 #[instrument]
-pub(super) async fn update_views(msg: ViewInput) -> Result<ActorOutputMessage, ActorError> {
-    let ViewInput { id, cached, conn } = msg;
-    let view = views::ActiveModel {
-        short_link: ActiveValue::Set(id.clone()),
-        cache_hit: ActiveValue::Set(cached),
-        created_at: ActiveValue::Set(Utc::now().naive_utc()),
-        ..Default::default()
-    };
-    match view.insert(&conn).await {
-        Ok(model) => {
-            trace!("Views updated successfully for url {id}: {model:?}");
-            Ok(ActorOutputMessage {
-                msg: format!("Views updated successfully for url {id}: {model:?}"),
-            })
+pub(super) async fn parse_user_agents(db: DbInput) -> Result<ActorOutputMessage, ActorError> {
+    let DbInput { conn } = db;
+    let parser = UserAgentParser::bundled();
+
+    let mut parsed = 0;
+    while parsed < USER_AGENT_MAX_PER_RUN {
+        let batch: Vec<(i32, Option<String>)> = views::Entity::find()
+            .select_only()
+            .column(views::Column::Id)
+            .column_as(Expr::cust(USER_AGENT), "user_agent")
+            .filter(views::Column::Device.is_null())
+            .order_by_asc(views::Column::Id)
+            .limit(USER_AGENT_BATCH)
+            .into_tuple()
+            .all(&conn)
+            .await?;
+        let count = batch.len();
+        if count == 0 {
+            break;
         }
-        Err(e) => {
-            error!("Error updating views for url {id}: {}", e.to_string());
-            Err(e.into())
+
+        // Most views share a handful of user agents, so update every view with the same
+        // result in one statement
+        let mut groups: HashMap<ParsedUserAgent, Vec<i32>> = HashMap::new();
+        for (id, user_agent) in batch {
+            groups
+                .entry(parser.parse(user_agent.as_deref()))
+                .or_default()
+                .push(id);
+        }
+        let txn = conn.begin().await?;
+        for (agent, ids) in groups {
+            views::Entity::update_many()
+                .set(views::ActiveModel {
+                    browser: Set(agent.browser),
+                    os: Set(agent.os),
+                    device: Set(Some(agent.device)),
+                    bot: Set(agent.bot),
+                    ..Default::default()
+                })
+                .filter(views::Column::Id.is_in(ids))
+                .exec(&txn)
+                .await?;
+        }
+        txn.commit().await?;
+
+        parsed += count;
+        if (count as u64) < USER_AGENT_BATCH {
+            break;
         }
     }
+
+    Ok(ActorOutputMessage {
+        msg: format!("Parsed the user agent of {parsed} views"),
+    })
 }
 
-#[cfg(all(feature = "ips", not(feature = "headers")))]
+#[derive(Debug, FromQueryResult)]
+struct Rollup {
+    short_link: String,
+    hour: NaiveDateTime,
+    referrer_host: String,
+    views: i64,
+    bot_views: i64,
+}
+
+/// Adds the views the rollup tables don't count yet to their hourly and daily rows, marking
+/// them rolled up in the same transaction so each view is counted exactly once. Views whose
+/// user agent hasn't been parsed wait until it has, as their bot flag isn't known yet.
 #[instrument]
-pub(super) async fn update_views(msg: ViewInput) -> Result<ActorOutputMessage, ActorError> {
-    let ViewInput {
-        id,
-        cached,
-        ip,
-        conn,
-    } = msg;
-    let view = views::ActiveModel {
-        short_link: ActiveValue::Set(id.clone()),
-        ip: ActiveValue::Set(IpNetwork::new(ip, 0).ok()),
-        cache_hit: ActiveValue::Set(cached),
-        created_at: ActiveValue::Set(Utc::now().naive_utc()),
-        ..Default::default()
-    };
-    insert_view(&id, view, &conn).await
+pub(super) async fn rollup_views(db: DbInput) -> Result<ActorOutputMessage, ActorError> {
+    let DbInput { conn } = db;
+
+    let mut rolled = 0;
+    while rolled < ROLLUP_MAX_PER_RUN {
+        let txn = conn.begin().await?;
+        let ids: Vec<i32> = views::Entity::find()
+            .select_only()
+            .column(views::Column::Id)
+            .filter(views::Column::RolledUp.eq(false))
+            .filter(views::Column::Device.is_not_null())
+            .order_by_asc(views::Column::Id)
+            .limit(ROLLUP_BATCH)
+            .lock_exclusive()
+            .into_tuple()
+            .all(&txn)
+            .await?;
+        let count = ids.len();
+        if count == 0 {
+            break;
+        }
+
+        let hour = Expr::cust(r#"date_trunc('hour', "views"."created_at")"#);
+        let referrer = Expr::cust(format!("COALESCE({REFERRER}, '')"));
+        let hourly = views::Entity::find()
+            .select_only()
+            .column(views::Column::ShortLink)
+            .column_as(hour.clone(), "hour")
+            .column_as(referrer.clone(), "referrer_host")
+            .column_as(
+                Expr::cust(r#"COUNT(*) FILTER (WHERE NOT "views"."bot")"#),
+                "views",
+            )
+            .column_as(
+                Expr::cust(r#"COUNT(*) FILTER (WHERE "views"."bot")"#),
+                "bot_views",
+            )
+            .filter(views::Column::Id.is_in(ids.clone()))
+            .group_by(views::Column::ShortLink)
+            .group_by(hour)
+            .group_by(referrer)
+            .into_model::<Rollup>()
+            .all(&txn)
+            .await?;
+        let mut daily: HashMap<(String, NaiveDate, String), (i64, i64)> = HashMap::new();
+        for row in &hourly {
+            let day = daily
+                .entry((
+                    row.short_link.clone(),
+                    row.hour.date(),
+                    row.referrer_host.clone(),
+                ))
+                .or_default();
+            day.0 += row.views;
+            day.1 += row.bot_views;
+        }
+
+        hourly_views::Entity::insert_many(hourly.into_iter().map(|row| {
+            hourly_views::ActiveModel {
+                short_link: Set(row.short_link),
+                hour: Set(row.hour),
+                referrer_host: Set(row.referrer_host),
+                views: Set(row.views),
+                bot_views: Set(row.bot_views),
+            }
+        }))
+        .on_conflict(
+            OnConflict::columns([
+                hourly_views::Column::ShortLink,
+                hourly_views::Column::Hour,
+                hourly_views::Column::ReferrerHost,
+            ])
+            .value(
+                hourly_views::Column::Views,
+                Expr::col((hourly_views::Entity, hourly_views::Column::Views))
+                    .add(Expr::cust(r#""excluded"."views""#)),
+            )
+            .value(
+                hourly_views::Column::BotViews,
+                Expr::col((hourly_views::Entity, hourly_views::Column::BotViews))
+                    .add(Expr::cust(r#""excluded"."bot_views""#)),
+            )
+            .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await?;
+        daily_views::Entity::insert_many(daily.into_iter().map(
+            |((short_link, day, referrer_host), (views, bot_views))| daily_views::ActiveModel {
+                short_link: Set(short_link),
+                day: Set(day),
+                referrer_host: Set(referrer_host),
+                views: Set(views),
+                bot_views: Set(bot_views),
+            },
+        ))
+        .on_conflict(
+            OnConflict::columns([
+                daily_views::Column::ShortLink,
+                daily_views::Column::Day,
+                daily_views::Column::ReferrerHost,
+            ])
+            .value(
+                daily_views::Column::Views,
+                Expr::col((daily_views::Entity, daily_views::Column::Views))
+                    .add(Expr::cust(r#""excluded"."views""#)),
+            )
+            .value(
+                daily_views::Column::BotViews,
+                Expr::col((daily_views::Entity, daily_views::Column::BotViews))
+                    .add(Expr::cust(r#""excluded"."bot_views""#)),
+            )
+            .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await?;
+        views::Entity::update_many()
+            .set(views::ActiveModel {
+                rolled_up: Set(true),
+                ..Default::default()
+            })
+            .filter(views::Column::Id.is_in(ids))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+
+        rolled += count;
+        if (count as u64) < ROLLUP_BATCH {
+            break;
+        }
+    }
+
+    Ok(ActorOutputMessage {
+        msg: format!("Rolled up {rolled} views"),
+    })
 }
 
-#[cfg(all(feature = "headers", not(feature = "ips")))]
+/// Deletes the views older than the retention period, or strips them down to what counting
+/// needs: no address, hash, headers, user agent, region or city, and only the referring host.
 #[instrument]
-pub(super) async fn update_views(msg: ViewInput) -> Result<ActorOutputMessage, ActorError> {
-    let ViewInput {
-        id,
-        cached,
-        headers,
+pub(super) async fn expire_views(
+    input: ExpireViewsInput,
+) -> Result<ActorOutputMessage, ActorError> {
+    let ExpireViewsInput {
         conn,
-    } = msg;
-    let headers: HeaderMapDef = match headers.try_into() {
-        Ok(hm) => hm,
-        Err(e) => {
-            error!("Unable to serialize the headers: {}", e.to_string());
-            return Err(e.into());
+        retention,
+        action,
+    } = input;
+    let cutoff = TimeDelta::from_std(retention)
+        .ok()
+        .and_then(|retention| Utc::now().naive_utc().checked_sub_signed(retention))
+        .unwrap_or(NaiveDateTime::MIN);
+
+    let mut expired = 0;
+    loop {
+        // Views are only expired once the rollups count them
+        let mut batch = views::Entity::find()
+            .select_only()
+            .column(views::Column::Id)
+            .filter(views::Column::CreatedAt.lt(cutoff))
+            .filter(views::Column::RolledUp.eq(true));
+        if action == ViewRetention::Anonymize {
+            batch = batch.filter(
+                Condition::any()
+                    .add(views::Column::Ip.is_not_null())
+                    .add(views::Column::IpHash.is_not_null())
+                    .add(views::Column::Headers.is_not_null())
+                    .add(views::Column::UserAgent.is_not_null())
+                    .add(views::Column::Region.is_not_null())
+                    .add(views::Column::City.is_not_null())
+                    .add(views::Column::Referrer.like("%/%")),
+            );
         }
+        let batch = views::Column::Id.in_subquery(batch.limit(VIEW_RETENTION_BATCH).into_query());
+        let count = match action {
+            ViewRetention::Delete => {
+                views::Entity::delete_many()
+                    .filter(batch)
+                    .exec(&conn)
+                    .await?
+                    .rows_affected
+            }
+            ViewRetention::Anonymize => {
+                views::Entity::update_many()
+                    .col_expr(views::Column::Referrer, Expr::cust(REFERRER_HOST))
+                    .set(views::ActiveModel {
+                        ip: Set(None),
+                        ip_hash: Set(None),
+                        headers: Set(None),
+                        user_agent: Set(None),
+                        region: Set(None),
+                        city: Set(None),
+                        ..Default::default()
+                    })
+                    .filter(batch)
+                    .exec(&conn)
+                    .await?
+                    .rows_affected
+            }
+        };
+        expired += count;
+        if count < VIEW_RETENTION_BATCH {
+            break;
+        }
+    }
+
+    let done = match action {
+        ViewRetention::Delete => "Deleted",
+        ViewRetention::Anonymize => "Anonymized",
     };
-    let view = views::ActiveModel {
-        short_link: ActiveValue::Set(id.clone()),
-        headers: ActiveValue::Set(Some(json!(headers))),
-        cache_hit: ActiveValue::Set(cached),
-        created_at: ActiveValue::Set(Utc::now().naive_utc()),
-        ..Default::default()
+    Ok(ActorOutputMessage {
+        msg: format!("{done} {expired} views recorded before {cutoff}"),
+    })
+}
+
+/// Deletes webhook delivery log entries older than the retention.
+#[instrument]
+pub(super) async fn prune_webhook_deliveries(
+    input: PruneDeliveriesInput,
+) -> Result<ActorOutputMessage, ActorError> {
+    let PruneDeliveriesInput { conn, retention } = input;
+
+    let cutoff = TimeDelta::from_std(retention)
+        .ok()
+        .and_then(|retention| Utc::now().naive_utc().checked_sub_signed(retention))
+        .unwrap_or(NaiveDateTime::MIN);
+
+    let pruned = webhook_delivery::Entity::delete_many()
+        .filter(webhook_delivery::Column::CreatedAt.lt(cutoff))
+        .exec(&conn)
+        .await?
+        .rows_affected;
+
+    Ok(ActorOutputMessage {
+        msg: format!("Pruned {pruned} webhook deliveries made before {cutoff}"),
+    })
+}
+
+/// Makes one attempt at a webhook delivery. A webhook that was removed or disabled since the
+/// delivery was queued is skipped.
+#[instrument]
+pub(super) async fn deliver_webhook(input: WebhookInput) -> Result<ActorOutputMessage, ActorError> {
+    let Some(hook) = webhook::Entity::find_by_id(input.webhook)
+        .filter(webhook::Column::Enabled.eq(true))
+        .one(&input.conn)
+        .await?
+    else {
+        return Ok(ActorOutputMessage {
+            msg: format!(
+                "Webhook {} was removed or disabled before delivery {}",
+                input.webhook, input.payload.id
+            ),
+        });
     };
-    insert_view(&id, view, &conn).await
+
+    let status = deliver(&input, &hook).await?;
+
+    Ok(ActorOutputMessage {
+        msg: format!(
+            "Delivered {} to webhook {} with status {status}",
+            input.payload.id, hook.id
+        ),
+    })
 }
 
-#[cfg(all(feature = "headers", feature = "ips"))]
 #[instrument]
-pub(super) async fn update_views(msg: ViewInput) -> Result<ActorOutputMessage, ActorError> {
-    let ViewInput {
-        ip,
+pub(super) async fn fetch_metadata(input: MetadataInput) -> Result<ActorOutputMessage, ActorError> {
+    let MetadataInput {
         id,
-        cached,
-        headers,
+        url,
+        client,
+        timeout,
+        max_bytes,
         conn,
-    } = msg;
-    let headers: HeaderMapDef = match headers.try_into() {
-        Ok(hm) => hm,
-        Err(e) => {
-            error!("Unable to serialize the headers: {}", e.to_string());
-            return Err(e.into());
-        }
-    };
-    let view = views::ActiveModel {
-        short_link: ActiveValue::Set(id.clone()),
-        ip: ActiveValue::Set(IpNetwork::new(ip, 0).ok()),
-        headers: ActiveValue::Set(Some(json!(headers))),
-        cache_hit: ActiveValue::Set(cached),
-        created_at: ActiveValue::Set(chrono::Utc::now().naive_utc()),
-        ..Default::default()
+    } = input;
+
+    let url = Url::parse(&url).map_err(ServerError::from)?;
+    let (url, html) =
+        match tokio::time::timeout(timeout, fetch_page(&client, url, timeout, max_bytes)).await {
+            Ok(page) => page?,
+            Err(_) => {
+                return Err(ActorError::Timeout {
+                    msg: format!("Timed out fetching metadata for url {id}"),
+                });
+            }
+        };
+    let meta = PageMetadata::parse(&html, &url);
+
+    let Some(link) = short_link::Entity::find_by_id(&id).one(&conn).await? else {
+        return Ok(ActorOutputMessage {
+            msg: format!("Url {id} was removed before its metadata was fetched"),
+        });
     };
-    insert_view(&id, view, &conn).await
+
+    // Only fill in what the owner hasn't already set themselves
+    let mut active = link.clone().into_active_model();
+    if link.title.is_none() && meta.title.is_some() {
+        active.title = ActiveValue::Set(meta.title);
+    }
+    if link.description.is_none() && meta.description.is_some() {
+        active.description = ActiveValue::Set(meta.description);
+    }
+    active.favicon = ActiveValue::Set(meta.favicon);
+    active.og_image = ActiveValue::Set(meta.og_image);
+    active.metadata_fetched_at = ActiveValue::Set(Some(Utc::now().naive_utc()));
+    active.update(&conn).await?;
+
+    Ok(ActorOutputMessage {
+        msg: format!("Fetched metadata for url {id} from {url}"),
+    })
 }
 
-#[instrument]
-async fn insert_view(
-    id: &str,
-    view: views::ActiveModel,
-    conn: &DbConn,
-) -> Result<ActorOutputMessage, ActorError> {
-    match view.insert(conn).await {
-        Ok(model) => {
-            trace!("Views updated successfully for url {id}: {model:?}");
-            Ok(ActorOutputMessage {
-                msg: format!("Views updated successfully for url {id}: {model:?}"),
-            })
+/// Fetches up to `max_bytes` of an HTML page, checking every redirect hop against the SSRF guard.
+#[instrument(skip(client))]
+async fn fetch_page(
+    client: &reqwest::Client,
+    mut url: Url,
+    timeout: Duration,
+    max_bytes: usize,
+) -> Result<(Url, String), ActorError> {
+    for _ in 0..=MAX_METADATA_REDIRECTS {
+        ensure_public_url(&url).await?;
+        let mut res = client
+            .get(url.clone())
+            .header(
+                header::USER_AGENT,
+                concat!("micro-url/", env!("CARGO_PKG_VERSION")),
+            )
+            .header(header::ACCEPT, "text/html,application/xhtml+xml")
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(ServerError::from)?;
+
+        if res.status().is_redirection() {
+            let Some(location) = res
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+            else {
+                return Err(ActorError::Basic {
+                    msg: format!("Redirect from {url} has no location"),
+                });
+            };
+            url = url.join(location).map_err(ServerError::from)?;
+            continue;
         }
-        Err(e) => {
-            error!("Error updating views for url {id}: {}", e.to_string());
-            Err(e.into())
+        if !res.status().is_success() {
+            return Err(ActorError::Basic {
+                msg: format!("Fetching {url} returned {}", res.status()),
+            });
+        }
+        let is_html = res
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| content_type.contains("html"));
+        if !is_html {
+            return Ok((url, String::new()));
         }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = res.chunk().await.map_err(ServerError::from)? {
+            let remaining = max_bytes.saturating_sub(body.len());
+            body.extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+            if body.len() >= max_bytes {
+                break;
+            }
+        }
+        return Ok((url, String::from_utf8_lossy(&body).into_owned()));
     }
+    Err(ActorError::Basic {
+        msg: format!("Too many redirects fetching metadata from {url}"),
+    })
 }
@@ -0,0 +1,778 @@
+use std::{sync::Arc, time::Duration};
+
+use async_channel::{Sender, TrySendError, WeakSender};
+use axum::http::header;
+use chrono::{NaiveDateTime, Utc};
+use entity::{sea_orm_active_enums::WebhookEvent, webhook, webhook_delivery};
+use hmac::{Hmac, Mac};
+use sea_orm::{
+    DatabaseConnection, DbErr,
+    entity::*,
+    query::*,
+    sea_query::{Expr, OnConflict},
+};
+use serde::Serialize;
+use serde_json::{Value, json};
+use sha2::Sha256;
+use tracing::{instrument, warn};
+use ts_rs::TS;
+use url::Url;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{
+    ActorError, ActorInputMessage, WebhookInput,
+    live::ClickEvent,
+    retry::{DeadLetterPayload, DeadLetterStore},
+};
+use crate::{
+    config::WebhookConfig,
+    error::ServerError,
+    utils::{ensure_public_url, public_client},
+};
+
+/// The header carrying the delivery id, which stays the same across retries.
+pub const WEBHOOK_ID_HEADER: &str = "x-webhook-id";
+pub const WEBHOOK_EVENT_HEADER: &str = "x-webhook-event";
+/// The header carrying the unix time the attempt was signed at.
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+/// The header carrying `sha256=` and the hex HMAC-SHA256 of `{timestamp}.{body}`, keyed with
+/// the webhook's secret.
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// The body of every webhook delivery.
+#[derive(Debug, Clone, Serialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct WebhookPayload {
+    /// The delivery's id, the same for every attempt so receivers can drop duplicates
+    pub id: Uuid,
+    pub event: WebhookEvent,
+    pub created_at: NaiveDateTime,
+    /// The link for link events, or the `ClickEvent` for `click.recorded`
+    pub data: Value,
+}
+
+impl WebhookPayload {
+    fn new(event: WebhookEvent, data: Value) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            event,
+            created_at: Utc::now().naive_utc(),
+            data,
+        }
+    }
+}
+
+/// Signs `body` as sent at `timestamp`, in the form of the signature header.
+pub fn sign_webhook(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={:x}", mac.finalize().into_bytes())
+}
+
+/// Queues deliveries to the webhooks subscribed to an event. Deliveries go through the actor
+/// pool, so a failed attempt is retried with backoff and ends up in the dead letter store once
+/// the retries run out.
+#[derive(Debug, Clone)]
+pub struct Webhooks {
+    conn: DatabaseConnection,
+    client: reqwest::Client,
+    // Holding a strong sender would keep the pool's queue open after it shuts down
+    sender: WeakSender<ActorInputMessage>,
+    timeout: Duration,
+    allow_private: bool,
+    dead_letters: Arc<DeadLetterStore>,
+}
+
+impl Webhooks {
+    pub(super) fn new(
+        conn: DatabaseConnection,
+        config: &WebhookConfig,
+        sender: &Sender<ActorInputMessage>,
+        dead_letters: Arc<DeadLetterStore>,
+    ) -> Self {
+        let client = if config.allow_private {
+            reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .expect("Unable to build the webhook client")
+        } else {
+            public_client()
+        };
+        Self {
+            conn,
+            client,
+            sender: sender.downgrade(),
+            timeout: config.timeout,
+            allow_private: config.allow_private,
+            dead_letters,
+        }
+    }
+
+    fn input(&self, webhook: Uuid, payload: WebhookPayload) -> WebhookInput {
+        WebhookInput {
+            webhook,
+            payload,
+            client: self.client.clone(),
+            timeout: self.timeout,
+            allow_private: self.allow_private,
+            conn: self.conn.clone(),
+        }
+    }
+
+    /// Queues `event` for every enabled webhook of `user` subscribed to it. Events are raised
+    /// from request handlers, so like clicks a full queue isn't waited on and deliveries that
+    /// don't fit go to the dead letter store.
+    #[instrument(skip(self, data))]
+    pub(super) async fn notify(&self, user: Uuid, event: WebhookEvent, data: Value) {
+        let hooks: Vec<Uuid> = match webhook::Entity::find()
+            .select_only()
+            .column(webhook::Column::Id)
+            .filter(webhook::Column::UserId.eq(user))
+            .filter(webhook::Column::Enabled.eq(true))
+            .filter(Expr::cust_with_values(
+                r#"$1 = ANY("webhook"."events")"#,
+                [event.to_value()],
+            ))
+            .into_tuple()
+            .all(&self.conn)
+            .await
+        {
+            Ok(hooks) => hooks,
+            Err(e) => {
+                warn!(
+                    "Unable to look up the webhooks for {}: {e}",
+                    event.to_value()
+                );
+                return;
+            }
+        };
+        let Some(sender) = self.sender.upgrade() else {
+            return;
+        };
+        for hook in hooks {
+            // Each hook gets its own delivery id, which keys its entry in the delivery log
+            let payload = WebhookPayload::new(event, data.clone());
+            let msg = ActorInputMessage::DeliverWebhook(self.input(hook, payload));
+            if !self.enqueue(&sender, msg) {
+                return;
+            }
+        }
+    }
+
+    /// Queues recorded clicks for the webhooks subscribed to `click.recorded`, each hook seeing
+    /// its owner's clicks thinned out to its sample rate.
+    #[instrument(skip(self, events), fields(events = events.len()))]
+    pub(super) async fn clicks(&self, events: &[ClickEvent]) {
+        let owners: Vec<Uuid> = events.iter().filter_map(|event| event.owner).collect();
+        if owners.is_empty() {
+            return;
+        }
+        let hooks: Vec<(Uuid, Uuid, f64)> = match webhook::Entity::find()
+            .select_only()
+            .columns([
+                webhook::Column::Id,
+                webhook::Column::UserId,
+                webhook::Column::ClickSampleRate,
+            ])
+            .filter(webhook::Column::UserId.is_in(owners))
+            .filter(webhook::Column::Enabled.eq(true))
+            .filter(Expr::cust_with_values(
+                r#"$1 = ANY("webhook"."events")"#,
+                [WebhookEvent::ClickRecorded.to_value()],
+            ))
+            .into_tuple()
+            .all(&self.conn)
+            .await
+        {
+            Ok(hooks) => hooks,
+            Err(e) => {
+                warn!(
+                    "Unable to look up the webhooks for {} clicks: {e}",
+                    events.len()
+                );
+                return;
+            }
+        };
+        let Some(sender) = self.sender.upgrade() else {
+            return;
+        };
+        for event in events {
+            let Some(owner) = event.owner else {
+                continue;
+            };
+            for (hook, _, sample_rate) in hooks.iter().filter(|(_, user, _)| *user == owner) {
+                if rand::random::<f64>() >= *sample_rate {
+                    continue;
+                }
+                let payload = WebhookPayload::new(WebhookEvent::ClickRecorded, json!(event));
+                let msg = ActorInputMessage::DeliverWebhook(self.input(*hook, payload));
+                if !self.enqueue(&sender, msg) {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Queues a delivery without waiting, moving it to the dead letter store to be replayed when
+    /// the queue is full. Returns `false` once the pool has shut down.
+    fn enqueue(&self, sender: &Sender<ActorInputMessage>, msg: ActorInputMessage) -> bool {
+        match sender.try_send(msg) {
+            Ok(()) => true,
+            Err(TrySendError::Full(msg)) => {
+                self.dead_letters.push(
+                    DeadLetterPayload::Task(msg),
+                    &ActorError::Basic {
+                        msg: "The actor queue was full".to_string(),
+                    },
+                    0,
+                );
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+
+    /// Sends a `ping` to `hook` straight away, whether or not it is enabled, and returns the
+    /// logged attempt. A test delivery isn't retried.
+    #[instrument(skip(self, hook), fields(webhook = %hook.id))]
+    pub(super) async fn test(
+        &self,
+        hook: &webhook::Model,
+    ) -> Result<webhook_delivery::Model, DbErr> {
+        let payload = WebhookPayload::new(WebhookEvent::Ping, json!({ "webhook": hook.id }));
+        let id = payload.id;
+        let input = self.input(hook.id, payload);
+        if let Err(e) = deliver(&input, hook).await {
+            warn!("Test delivery to webhook {} failed: {e}", hook.id);
+        }
+        webhook_delivery::Entity::find_by_id(id)
+            .one(&self.conn)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(format!("Webhook delivery {id}")))
+    }
+}
+
+/// Makes one attempt at a delivery and records it in the delivery log, returning the status
+/// the endpoint answered with.
+#[instrument(skip(input, hook), fields(webhook = %hook.id, delivery = %input.payload.id))]
+pub(super) async fn deliver(
+    input: &WebhookInput,
+    hook: &webhook::Model,
+) -> Result<u16, ActorError> {
+    let result = post(input, hook).await;
+    let (status, error) = match &result {
+        Ok(status) => (Some(*status), None),
+        Err(e @ ActorError::WebhookStatus { status }) => (Some(*status), Some(e.to_string())),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    if let Err(e) = log_attempt(&input.conn, &input.payload, hook.id, status, error).await {
+        warn!("Unable to log webhook delivery {}: {e}", input.payload.id);
+    }
+    result
+}
+
+async fn post(input: &WebhookInput, hook: &webhook::Model) -> Result<u16, ActorError> {
+    let url = Url::parse(&hook.url).map_err(ServerError::from)?;
+    if !input.allow_private {
+        ensure_public_url(&url).await?;
+    }
+    let body = serde_json::to_vec(&input.payload).map_err(|e| ActorError::Basic {
+        msg: format!(
+            "Unable to serialize webhook delivery {}: {e}",
+            input.payload.id
+        ),
+    })?;
+    let timestamp = Utc::now().timestamp();
+    let res = input
+        .client
+        .post(url)
+        .header(
+            header::USER_AGENT,
+            concat!("micro-url/", env!("CARGO_PKG_VERSION")),
+        )
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(WEBHOOK_ID_HEADER, input.payload.id.to_string())
+        .header(WEBHOOK_EVENT_HEADER, input.payload.event.to_value())
+        .header(WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string())
+        .header(
+            WEBHOOK_SIGNATURE_HEADER,
+            sign_webhook(&hook.secret, timestamp, &body),
+        )
+        .timeout(input.timeout)
+        .body(body)
+        .send()
+        .await
+        .map_err(ServerError::from)?;
+    let status = res.status().as_u16();
+    if !res.status().is_success() {
+        return Err(ActorError::WebhookStatus { status });
+    }
+    Ok(status)
+}
+
+/// Records an attempt, adding to the attempt count when the delivery was tried before.
+async fn log_attempt(
+    conn: &DatabaseConnection,
+    payload: &WebhookPayload,
+    webhook: Uuid,
+    status: Option<u16>,
+    error: Option<String>,
+) -> Result<(), DbErr> {
+    let now = Utc::now().naive_utc();
+    let delivered = error.is_none();
+    let attempt = webhook_delivery::ActiveModel {
+        id: ActiveValue::Set(payload.id),
+        webhook_id: ActiveValue::Set(webhook),
+        event: ActiveValue::Set(payload.event),
+        payload: ActiveValue::Set(json!(payload)),
+        attempts: ActiveValue::Set(1),
+        status_code: ActiveValue::Set(status.map(i32::from)),
+        error: ActiveValue::Set(error),
+        delivered: ActiveValue::Set(delivered),
+        created_at: ActiveValue::Set(now),
+        updated_at: ActiveValue::Set(now),
+    };
+    webhook_delivery::Entity::insert(attempt)
+        .on_conflict(
+            OnConflict::column(webhook_delivery::Column::Id)
+                .value(
+                    webhook_delivery::Column::Attempts,
+                    Expr::col((webhook_delivery::Entity, webhook_delivery::Column::Attempts))
+                        .add(1),
+                )
+                .update_columns([
+                    webhook_delivery::Column::StatusCode,
+                    webhook_delivery::Column::Error,
+                    webhook_delivery::Column::Delivered,
+                    webhook_delivery::Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, VecDeque},
+        sync::Mutex,
+        time::Instant,
+    };
+
+    use async_channel::bounded;
+    use axum::{
+        Router,
+        body::Bytes,
+        extract::{Path, State},
+        http::{HeaderMap, StatusCode},
+        routing,
+    };
+    use tokio::{net::TcpListener, time::sleep};
+
+    use super::*;
+    use crate::{
+        actor::{
+            actor::{DefaultActor, PoolableActor},
+            retry::RetryPolicy,
+        },
+        testing::{connect, create_user},
+    };
+
+    const BACKOFF: Duration = Duration::from_millis(200);
+
+    /// A request the stand-in endpoint received.
+    #[derive(Debug, Clone)]
+    struct Received {
+        hook: String,
+        headers: HeaderMap,
+        body: Bytes,
+        at: Instant,
+    }
+
+    /// A local webhook endpoint answering each hook with its scripted statuses in turn, then
+    /// with 200 once they run out.
+    #[derive(Debug, Clone, Default)]
+    struct Endpoint {
+        statuses: Arc<Mutex<HashMap<String, VecDeque<u16>>>>,
+        received: Arc<Mutex<Vec<Received>>>,
+    }
+
+    impl Endpoint {
+        async fn start() -> (Self, String) {
+            let endpoint = Self::default();
+            let app = Router::new()
+                .route("/{hook}", routing::post(Self::receive))
+                .with_state(endpoint.clone());
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let base = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            (endpoint, base)
+        }
+
+        async fn receive(
+            State(endpoint): State<Self>,
+            Path(hook): Path<String>,
+            headers: HeaderMap,
+            body: Bytes,
+        ) -> StatusCode {
+            let status = endpoint
+                .statuses
+                .lock()
+                .unwrap()
+                .get_mut(&hook)
+                .and_then(VecDeque::pop_front)
+                .unwrap_or(200);
+            endpoint.received.lock().unwrap().push(Received {
+                hook,
+                headers,
+                body,
+                at: Instant::now(),
+            });
+            StatusCode::from_u16(status).unwrap()
+        }
+
+        fn answer(&self, hook: &str, statuses: &[u16]) {
+            self.statuses
+                .lock()
+                .unwrap()
+                .insert(hook.to_string(), statuses.iter().copied().collect());
+        }
+
+        fn received(&self) -> Vec<Received> {
+            self.received.lock().unwrap().clone()
+        }
+
+        /// Waits for `count` requests, failing the test if they don't all arrive.
+        async fn wait_for(&self, count: usize) -> Vec<Received> {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while self.received.lock().unwrap().len() < count {
+                assert!(
+                    Instant::now() < deadline,
+                    "Only got {} of {count} requests",
+                    self.received().len()
+                );
+                sleep(Duration::from_millis(20)).await;
+            }
+            self.received()
+        }
+    }
+
+    fn header<'a>(received: &'a Received, name: &str) -> &'a str {
+        received.headers[name].to_str().unwrap()
+    }
+
+    /// Webhooks delivering through one worker with a short backoff, as the actor pool would.
+    /// The queue stays open for as long as the returned sender is held.
+    fn webhooks(
+        conn: &DatabaseConnection,
+    ) -> (Webhooks, Arc<DeadLetterStore>, Sender<ActorInputMessage>) {
+        let (sender, receiver) = bounded(16);
+        let dead_letters = Arc::new(DeadLetterStore::new(16));
+        let config = WebhookConfig {
+            timeout: Duration::from_secs(5),
+            allow_private: true,
+            ..Default::default()
+        };
+        let webhooks = Webhooks::new(conn.clone(), &config, &sender, dead_letters.clone());
+        let retry = RetryPolicy {
+            max_retries: 3,
+            backoff: BACKOFF,
+            max_backoff: Duration::from_secs(5),
+        };
+        let mut actor =
+            DefaultActor::new(receiver, sender.downgrade(), retry, dead_letters.clone());
+        tokio::spawn(async move { actor.run().await });
+        (webhooks, dead_letters, sender)
+    }
+
+    fn hook(user: Uuid, base: &str, name: &str) -> webhook::Model {
+        let now = Utc::now().naive_utc();
+        webhook::Model {
+            id: Uuid::new_v4(),
+            user_id: user,
+            url: format!("{base}/{name}"),
+            secret: format!("whsec_{name}"),
+            events: vec![WebhookEvent::LinkCreated],
+            click_sample_rate: 1.0,
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    async fn create_hook(
+        conn: &DatabaseConnection,
+        user: Uuid,
+        base: &str,
+        name: &str,
+    ) -> webhook::Model {
+        hook(user, base, name)
+            .into_active_model()
+            .insert(conn)
+            .await
+            .unwrap()
+    }
+
+    /// A delivery that is posted straight to the endpoint, without a database to log it in.
+    fn offline_input(hook: &webhook::Model) -> WebhookInput {
+        WebhookInput {
+            webhook: hook.id,
+            payload: WebhookPayload::new(WebhookEvent::LinkCreated, json!({ "id": "abc" })),
+            client: reqwest::Client::new(),
+            timeout: Duration::from_secs(5),
+            allow_private: true,
+            conn: DatabaseConnection::default(),
+        }
+    }
+
+    async fn deliveries(conn: &DatabaseConnection, hook: Uuid) -> Vec<webhook_delivery::Model> {
+        webhook_delivery::Entity::find()
+            .filter(webhook_delivery::Column::WebhookId.eq(hook))
+            .all(conn)
+            .await
+            .unwrap()
+    }
+
+    /// Waits until `attempts` attempts at delivering to `hook` have been logged.
+    async fn settled(
+        conn: &DatabaseConnection,
+        hook: Uuid,
+        attempts: i32,
+    ) -> Vec<webhook_delivery::Model> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let logged = deliveries(conn, hook).await;
+            if logged.iter().map(|delivery| delivery.attempts).sum::<i32>() >= attempts {
+                return logged;
+            }
+            assert!(Instant::now() < deadline, "Only logged {logged:?}");
+            sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn deliveries_are_signed() {
+        let conn = connect().await;
+        let (endpoint, base) = Endpoint::start().await;
+        let user = create_user(&conn).await;
+        let hook = create_hook(&conn, user, &base, "signed").await;
+        let (webhooks, _, _queue) = webhooks(&conn);
+
+        webhooks
+            .notify(user, WebhookEvent::LinkCreated, json!({ "id": "abc" }))
+            .await;
+        let received = endpoint.wait_for(1).await;
+        let request = &received[0];
+        let timestamp: i64 = header(request, WEBHOOK_TIMESTAMP_HEADER).parse().unwrap();
+        assert_eq!(
+            header(request, WEBHOOK_SIGNATURE_HEADER),
+            sign_webhook(&hook.secret, timestamp, &request.body)
+        );
+        assert_eq!(header(request, WEBHOOK_EVENT_HEADER), "link.created");
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["id"], header(request, WEBHOOK_ID_HEADER));
+        assert_eq!(body["event"], "link.created");
+        assert_eq!(body["data"], json!({ "id": "abc" }));
+
+        let logged = settled(&conn, hook.id, 1).await;
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].id.to_string(), header(request, WEBHOOK_ID_HEADER));
+        assert_eq!(logged[0].attempts, 1);
+        assert_eq!(logged[0].status_code, Some(200));
+        assert!(logged[0].delivered);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn server_errors_and_rate_limits_are_retried_with_backoff() {
+        let conn = connect().await;
+        let (endpoint, base) = Endpoint::start().await;
+        let user = create_user(&conn).await;
+        let hook = create_hook(&conn, user, &base, "flaky").await;
+        endpoint.answer("flaky", &[503, 429]);
+        let (webhooks, dead_letters, _queue) = webhooks(&conn);
+
+        webhooks
+            .notify(user, WebhookEvent::LinkCreated, json!({ "id": "abc" }))
+            .await;
+        let received = endpoint.wait_for(3).await;
+        // The delay doubles after every attempt
+        assert!(received[1].at - received[0].at >= BACKOFF);
+        assert!(received[2].at - received[1].at >= BACKOFF * 2);
+        let id = header(&received[0], WEBHOOK_ID_HEADER);
+        assert!(
+            received
+                .iter()
+                .all(|request| header(request, WEBHOOK_ID_HEADER) == id)
+        );
+
+        let logged = settled(&conn, hook.id, 3).await;
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].attempts, 3);
+        assert_eq!(logged[0].status_code, Some(200));
+        assert_eq!(logged[0].error, None);
+        assert!(logged[0].delivered);
+        assert!(dead_letters.list().letters.is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn client_errors_are_not_retried() {
+        let conn = connect().await;
+        let (endpoint, base) = Endpoint::start().await;
+        let user = create_user(&conn).await;
+        let hook = create_hook(&conn, user, &base, "gone").await;
+        endpoint.answer("gone", &[410, 410]);
+        let (webhooks, dead_letters, _queue) = webhooks(&conn);
+
+        webhooks
+            .notify(user, WebhookEvent::LinkCreated, json!({ "id": "abc" }))
+            .await;
+        endpoint.wait_for(1).await;
+        sleep(BACKOFF * 3).await;
+        assert_eq!(endpoint.received().len(), 1);
+
+        let logged = settled(&conn, hook.id, 1).await;
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].attempts, 1);
+        assert_eq!(logged[0].status_code, Some(410));
+        assert!(!logged[0].delivered);
+        let letters = dead_letters.list().letters;
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].attempts, 1);
+        assert!(!letters[0].retryable);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn every_hook_gets_its_own_delivery() {
+        let conn = connect().await;
+        let (endpoint, base) = Endpoint::start().await;
+        let user = create_user(&conn).await;
+        let first = create_hook(&conn, user, &base, "first").await;
+        let second = create_hook(&conn, user, &base, "second").await;
+        endpoint.answer("second", &[500]);
+        let (webhooks, _, _queue) = webhooks(&conn);
+
+        webhooks
+            .notify(user, WebhookEvent::LinkCreated, json!({ "id": "abc" }))
+            .await;
+        let received = endpoint.wait_for(3).await;
+        let ids = |hook: &str| -> Vec<String> {
+            received
+                .iter()
+                .filter(|request| request.hook == hook)
+                .map(|request| header(request, WEBHOOK_ID_HEADER).to_string())
+                .collect()
+        };
+        assert_eq!(ids("first").len(), 1);
+        assert_eq!(ids("second").len(), 2);
+        assert_ne!(ids("first")[0], ids("second")[0]);
+
+        let first = settled(&conn, first.id, 1).await;
+        let second = settled(&conn, second.id, 2).await;
+        assert_eq!(first.len(), 1);
+        assert_eq!(second.len(), 1);
+        assert_ne!(first[0].id, second[0].id);
+        assert_eq!(first[0].attempts, 1);
+        assert_eq!(second[0].attempts, 2);
+        assert!(first[0].delivered && second[0].delivered);
+    }
+
+    #[tokio::test]
+    async fn posts_are_signed() {
+        let (endpoint, base) = Endpoint::start().await;
+        let hook = hook(Uuid::new_v4(), &base, "signed");
+        let input = offline_input(&hook);
+
+        assert_eq!(post(&input, &hook).await.unwrap(), 200);
+        let received = endpoint.wait_for(1).await;
+        let request = &received[0];
+        let timestamp: i64 = header(request, WEBHOOK_TIMESTAMP_HEADER).parse().unwrap();
+        assert!((Utc::now().timestamp() - timestamp).abs() < 5);
+        assert_eq!(
+            header(request, WEBHOOK_SIGNATURE_HEADER),
+            sign_webhook(&hook.secret, timestamp, &request.body)
+        );
+        assert_eq!(
+            header(request, WEBHOOK_ID_HEADER),
+            input.payload.id.to_string()
+        );
+        assert_eq!(header(request, WEBHOOK_EVENT_HEADER), "link.created");
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["data"], json!({ "id": "abc" }));
+    }
+
+    #[tokio::test]
+    async fn failed_posts_are_retried_only_when_they_could_clear_up() {
+        let (endpoint, base) = Endpoint::start().await;
+        let hook = hook(Uuid::new_v4(), &base, "flaky");
+        endpoint.answer("flaky", &[503, 429, 410]);
+        let input = offline_input(&hook);
+
+        for (status, retryable) in [(503, true), (429, true), (410, false)] {
+            let e = post(&input, &hook).await.unwrap_err();
+            assert!(matches!(e, ActorError::WebhookStatus { status: s } if s == status));
+            assert_eq!(e.is_retryable(), retryable, "{status}");
+        }
+        assert_eq!(post(&input, &hook).await.unwrap(), 200);
+        // The endpoint is never sent to when private addresses aren't allowed
+        let public_only = WebhookInput {
+            allow_private: false,
+            ..input
+        };
+        assert!(post(&public_only, &hook).await.is_err());
+        assert_eq!(endpoint.received().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn deliveries_that_dont_fit_go_to_the_dead_letter_store() {
+        let (sender, receiver) = bounded(1);
+        let dead_letters = Arc::new(DeadLetterStore::new(16));
+        let webhooks = Webhooks::new(
+            DatabaseConnection::default(),
+            &WebhookConfig::default(),
+            &sender,
+            dead_letters.clone(),
+        );
+        let hook = hook(Uuid::new_v4(), "http://127.0.0.1", "full");
+        let delivery = || {
+            ActorInputMessage::DeliverWebhook(webhooks.input(hook.id, offline_input(&hook).payload))
+        };
+
+        assert!(webhooks.enqueue(&sender, delivery()));
+        assert!(webhooks.enqueue(&sender, delivery()));
+        assert_eq!(receiver.len(), 1);
+        let letters = dead_letters.list().letters;
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].attempts, 0);
+
+        receiver.close();
+        assert!(!webhooks.enqueue(&sender, delivery()));
+        assert_eq!(dead_letters.list().letters.len(), 1);
+    }
+
+    #[test]
+    fn signatures_cover_the_timestamp_and_body() {
+        let body = br#"{"hello":"world"}"#;
+        assert_eq!(
+            sign_webhook("whsec_test", 1_700_000_000, body),
+            "sha256=f592bbf3951cfc94e560eecfb5d9dd4da6b0fff2e626235f8ab4b54860925d0b"
+        );
+        assert_ne!(
+            sign_webhook("whsec_test", 1_700_000_001, body),
+            sign_webhook("whsec_test", 1_700_000_000, body)
+        );
+        assert_ne!(
+            sign_webhook("whsec_other", 1_700_000_000, body),
+            sign_webhook("whsec_test", 1_700_000_000, body)
+        );
+    }
+}
@@ -0,0 +1,23 @@
+pub mod routes;
+pub mod structs;
+
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::state::ServerState;
+
+pub const ADMIN_TAG: &str = "admin";
+pub const ADMIN_PREFIX: &str = "/api/admin";
+
+pub fn admin_router(state: ServerState) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(routes::list_domain_rules, routes::add_domain_rule))
+        .routes(routes!(routes::delete_domain_rule))
+        .routes(routes!(routes::import_blocklist))
+        .routes(routes!(routes::click_stats))
+        .routes(routes!(routes::list_jobs))
+        .routes(routes!(routes::run_job))
+        .routes(routes!(routes::list_dead_letters))
+        .routes(routes!(routes::replay_dead_letters))
+        .routes(routes!(routes::replay_dead_letter))
+        .with_state(state)
+}
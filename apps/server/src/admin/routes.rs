@@ -0,0 +1,374 @@
+use axum::{
+    Json, debug_handler,
+    extract::{Path, State},
+};
+use axum_extra::extract::cookie::PrivateCookieJar;
+use chrono::Utc;
+use entity::{domain_rule, job, sea_orm_active_enums::DomainAction, sessions, user};
+use sea_orm::{entity::*, query::*, sea_query::OnConflict};
+use tracing::instrument;
+
+use super::structs::{
+    ClickStatsResponse, DeadLetterResponse, DomainRuleList, DomainRuleResponse,
+    ImportBlocklistRequest, JobResponse, NewDomainRuleRequest, OperatorError,
+};
+use crate::{
+    state::ServerState,
+    urls::domains::{normalize_pattern, parse_hosts},
+};
+
+const IMPORT_CHUNK_SIZE: usize = 1000;
+
+/// Resolves the session cookie to a user listed in `OPERATOR_EMAILS`.
+#[instrument]
+pub(crate) async fn require_operator(
+    jar: &PrivateCookieJar,
+    state: &ServerState,
+) -> Result<user::Model, OperatorError> {
+    let Some(cookie) = jar.get("sid").map(|cookie| cookie.value().to_owned()) else {
+        return Err(OperatorError::NotLoggedIn);
+    };
+
+    let Some(user) = sessions::Entity::find()
+        .filter(sessions::Column::SessionId.eq(cookie))
+        .columns([
+            user::Column::UserId,
+            user::Column::Email,
+            user::Column::Name,
+            user::Column::UpdatedAt,
+            user::Column::CreatedAt,
+        ])
+        .right_join(user::Entity)
+        .into_model::<user::Model>()
+        .one(&state.conn)
+        .await?
+    else {
+        return Err(OperatorError::NotLoggedIn);
+    };
+
+    if !state.config.admin.is_operator(&user.email) {
+        return Err(OperatorError::NotOperator);
+    }
+
+    Ok(user)
+}
+
+// /api/admin/domains
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    get,
+    path = "/domains",
+    context_path = super::ADMIN_PREFIX,
+    responses(DomainRuleResponse),
+    tag = super::ADMIN_TAG,
+    security(("session_id" = []))
+)]
+pub async fn list_domain_rules(
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<DomainRuleResponse, DomainRuleResponse> {
+    require_operator(&jar, &state).await?;
+
+    let rules = domain_rule::Entity::find()
+        .order_by_asc(domain_rule::Column::Pattern)
+        .all(&state.conn)
+        .await?;
+    let (allow_only, file_rules) = state.domain_rules_summary()?;
+
+    Ok(DomainRuleResponse::Rules(DomainRuleList {
+        allow_only,
+        file_rules,
+        rules,
+    }))
+}
+
+// /api/admin/domains
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    post,
+    path = "/domains",
+    context_path = super::ADMIN_PREFIX,
+    request_body = NewDomainRuleRequest,
+    responses(DomainRuleResponse),
+    tag = super::ADMIN_TAG,
+    security(("session_id" = []))
+)]
+pub async fn add_domain_rule(
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+    Json(payload): Json<NewDomainRuleRequest>,
+) -> Result<DomainRuleResponse, DomainRuleResponse> {
+    let operator = require_operator(&jar, &state).await?;
+
+    let Some(pattern) = normalize_pattern(&payload.pattern) else {
+        return Err(DomainRuleResponse::InvalidPattern(
+            format!("{:?} is not a host name or *. wildcard", payload.pattern).into(),
+        ));
+    };
+
+    let rule = domain_rule::ActiveModel {
+        id: ActiveValue::not_set(),
+        pattern: ActiveValue::set(pattern),
+        action: ActiveValue::set(payload.action),
+        note: ActiveValue::set(payload.note),
+        created_by: ActiveValue::set(Some(operator.user_id)),
+        created_at: ActiveValue::set(Utc::now().naive_utc()),
+    };
+
+    let rule = domain_rule::Entity::insert(rule)
+        .on_conflict(
+            OnConflict::column(domain_rule::Column::Pattern)
+                .update_columns([
+                    domain_rule::Column::Action,
+                    domain_rule::Column::Note,
+                    domain_rule::Column::CreatedBy,
+                ])
+                .to_owned(),
+        )
+        .exec_with_returning(&state.conn)
+        .await?;
+
+    state.reload_domain_rules().await?;
+
+    Ok(DomainRuleResponse::Rule(rule))
+}
+
+// /api/admin/domains/{id}
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    delete,
+    path = "/domains/{id}",
+    context_path = super::ADMIN_PREFIX,
+    params(("id", description = "The domain rule ID")),
+    responses(DomainRuleResponse),
+    tag = super::ADMIN_TAG,
+    security(("session_id" = []))
+)]
+pub async fn delete_domain_rule(
+    jar: PrivateCookieJar,
+    Path(id): Path<i32>,
+    State(state): State<ServerState>,
+) -> Result<DomainRuleResponse, DomainRuleResponse> {
+    require_operator(&jar, &state).await?;
+
+    let res = domain_rule::Entity::delete_by_id(id)
+        .exec(&state.conn)
+        .await?;
+    if res.rows_affected == 0 {
+        return Err(DomainRuleResponse::RuleNotFound);
+    }
+
+    state.reload_domain_rules().await?;
+
+    Ok(DomainRuleResponse::Success(
+        format!("Deleted domain rule {id}").into(),
+    ))
+}
+
+// /api/admin/domains/import
+#[instrument(skip(payload))]
+#[debug_handler]
+#[utoipa::path(
+    post,
+    path = "/domains/import",
+    context_path = super::ADMIN_PREFIX,
+    request_body = ImportBlocklistRequest,
+    responses(DomainRuleResponse),
+    tag = super::ADMIN_TAG,
+    security(("session_id" = []))
+)]
+pub async fn import_blocklist(
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+    Json(payload): Json<ImportBlocklistRequest>,
+) -> Result<DomainRuleResponse, DomainRuleResponse> {
+    let operator = require_operator(&jar, &state).await?;
+
+    let hosts = parse_hosts(&payload.contents);
+    if hosts.is_empty() {
+        return Err(DomainRuleResponse::InvalidPattern(
+            "The blocklist does not contain any host names".into(),
+        ));
+    }
+
+    let now = Utc::now().naive_utc();
+    let before = domain_rule::Entity::find().count(&state.conn).await?;
+    let txn = state.conn.begin().await?;
+    for chunk in hosts.chunks(IMPORT_CHUNK_SIZE) {
+        domain_rule::Entity::insert_many(chunk.iter().map(|host| domain_rule::ActiveModel {
+            id: ActiveValue::not_set(),
+            pattern: ActiveValue::set(host.clone()),
+            action: ActiveValue::set(DomainAction::Deny),
+            note: ActiveValue::set(payload.note.clone()),
+            created_by: ActiveValue::set(Some(operator.user_id)),
+            created_at: ActiveValue::set(now),
+        }))
+        .on_conflict(
+            OnConflict::column(domain_rule::Column::Pattern)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await?;
+    }
+    txn.commit().await?;
+    let imported = domain_rule::Entity::find().count(&state.conn).await? - before;
+
+    state.reload_domain_rules().await?;
+
+    Ok(DomainRuleResponse::Success(
+        format!("Imported {imported} of {} blocklisted hosts", hosts.len()).into(),
+    ))
+}
+
+// /api/admin/clicks
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    get,
+    path = "/clicks",
+    context_path = super::ADMIN_PREFIX,
+    responses(ClickStatsResponse),
+    tag = super::ADMIN_TAG,
+    security(("session_id" = []))
+)]
+pub async fn click_stats(
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<ClickStatsResponse, ClickStatsResponse> {
+    require_operator(&jar, &state).await?;
+    Ok(ClickStatsResponse::Stats(state.pool.click_stats()))
+}
+
+// /api/admin/jobs
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    get,
+    path = "/jobs",
+    context_path = super::ADMIN_PREFIX,
+    responses(JobResponse),
+    tag = super::ADMIN_TAG,
+    security(("session_id" = []))
+)]
+pub async fn list_jobs(
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<JobResponse, JobResponse> {
+    require_operator(&jar, &state).await?;
+
+    let jobs = job::Entity::find()
+        .order_by_asc(job::Column::Name)
+        .all(&state.conn)
+        .await?;
+
+    Ok(JobResponse::Jobs(jobs))
+}
+
+// /api/admin/jobs/{name}/run
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    post,
+    path = "/jobs/{name}/run",
+    context_path = super::ADMIN_PREFIX,
+    params(("name", description = "The job name")),
+    responses(JobResponse),
+    tag = super::ADMIN_TAG,
+    security(("session_id" = []))
+)]
+pub async fn run_job(
+    jar: PrivateCookieJar,
+    Path(name): Path<String>,
+    State(state): State<ServerState>,
+) -> Result<JobResponse, JobResponse> {
+    require_operator(&jar, &state).await?;
+
+    // Mark the job due rather than running it here, so the leader lock still decides which
+    // replica runs it
+    let Some(job) = job::Entity::update_many()
+        .set(job::ActiveModel {
+            next_run_at: ActiveValue::set(Utc::now().naive_utc()),
+            ..Default::default()
+        })
+        .filter(job::Column::Name.eq(&name))
+        .exec_with_returning(&state.conn)
+        .await?
+        .pop()
+    else {
+        return Err(JobResponse::JobNotFound);
+    };
+    state.pool.wake_scheduler();
+
+    Ok(JobResponse::Job(job))
+}
+
+// /api/admin/dead-letters
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    get,
+    path = "/dead-letters",
+    context_path = super::ADMIN_PREFIX,
+    responses(DeadLetterResponse),
+    tag = super::ADMIN_TAG,
+    security(("session_id" = []))
+)]
+pub async fn list_dead_letters(
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<DeadLetterResponse, DeadLetterResponse> {
+    require_operator(&jar, &state).await?;
+    Ok(DeadLetterResponse::DeadLetters(state.pool.dead_letters()))
+}
+
+// /api/admin/dead-letters/replay
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    post,
+    path = "/dead-letters/replay",
+    context_path = super::ADMIN_PREFIX,
+    responses(DeadLetterResponse),
+    tag = super::ADMIN_TAG,
+    security(("session_id" = []))
+)]
+pub async fn replay_dead_letters(
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<DeadLetterResponse, DeadLetterResponse> {
+    require_operator(&jar, &state).await?;
+    let replayed = state.pool.replay_dead_letters(None).await;
+    Ok(DeadLetterResponse::Success(
+        format!("Replayed {replayed} dead letters").into(),
+    ))
+}
+
+// /api/admin/dead-letters/{id}/replay
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    post,
+    path = "/dead-letters/{id}/replay",
+    context_path = super::ADMIN_PREFIX,
+    params(("id", description = "The dead letter id")),
+    responses(DeadLetterResponse),
+    tag = super::ADMIN_TAG,
+    security(("session_id" = []))
+)]
+pub async fn replay_dead_letter(
+    jar: PrivateCookieJar,
+    Path(id): Path<u64>,
+    State(state): State<ServerState>,
+) -> Result<DeadLetterResponse, DeadLetterResponse> {
+    require_operator(&jar, &state).await?;
+    if state.pool.replay_dead_letters(Some(id)).await == 0 {
+        return Err(DeadLetterResponse::DeadLetterNotFound);
+    }
+    Ok(DeadLetterResponse::Success(
+        format!("Replayed dead letter {id}").into(),
+    ))
+}
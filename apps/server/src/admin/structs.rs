@@ -0,0 +1,340 @@
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use entity::{domain_rule, job, sea_orm_active_enums::DomainAction};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{error, info, instrument, warn};
+use ts_rs::TS;
+use utoipa::{IntoResponses, ToSchema};
+
+use crate::{
+    actor::{ClickStats, DeadLetterList},
+    error::{ArcMutexError, ServerError},
+    utils::{BasicError, BasicResponse},
+};
+
+#[derive(Error, Debug)]
+pub enum OperatorError {
+    #[error("User not logged in")]
+    NotLoggedIn,
+    #[error("Only operators may manage this server")]
+    NotOperator,
+    #[error("Database error: {0}")]
+    DbErr(#[from] sea_orm::DbErr),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct NewDomainRuleRequest {
+    pub pattern: String,
+    pub action: DomainAction,
+    #[ts(optional)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct ImportBlocklistRequest {
+    pub contents: String,
+    #[ts(optional)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct DomainRuleList {
+    pub allow_only: bool,
+    pub file_rules: usize,
+    pub rules: Vec<domain_rule::Model>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, IntoResponses, TS)]
+#[serde(untagged)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub enum DomainRuleResponse {
+    #[response(status = StatusCode::UNAUTHORIZED)]
+    InvalidSession(#[to_schema] BasicError),
+    #[response(status = StatusCode::FORBIDDEN)]
+    NotOperator(#[to_schema] BasicError),
+    #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
+    DatabaseError(#[to_schema] BasicError),
+    #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
+    CacheError(#[to_schema] BasicError),
+    #[response(status = StatusCode::BAD_REQUEST)]
+    InvalidPattern(#[to_schema] BasicError),
+    #[response(status = StatusCode::NOT_FOUND)]
+    RuleNotFound,
+    #[response(status = StatusCode::OK)]
+    Rules(#[to_schema] DomainRuleList),
+    #[response(status = StatusCode::OK)]
+    Rule(#[to_schema] domain_rule::Model),
+    #[response(status = StatusCode::OK)]
+    Success(#[to_schema] BasicResponse),
+}
+
+impl IntoResponse for DomainRuleResponse {
+    #[instrument]
+    fn into_response(self) -> Response {
+        match self {
+            Self::InvalidSession(e) => {
+                error!(%e);
+                (StatusCode::UNAUTHORIZED, Json(e)).into_response()
+            }
+            Self::NotOperator(e) => {
+                warn!(%e);
+                (StatusCode::FORBIDDEN, Json(e)).into_response()
+            }
+            Self::DatabaseError(e) => {
+                error!(%e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
+            }
+            Self::CacheError(e) => {
+                error!(%e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
+            }
+            Self::InvalidPattern(e) => {
+                error!(%e);
+                (StatusCode::BAD_REQUEST, Json(e)).into_response()
+            }
+            Self::RuleNotFound => {
+                error!("Domain rule not found");
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(BasicError {
+                        error: "Domain rule not found".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+            Self::Rules(rules) => {
+                info!("{} domain rules", rules.rules.len());
+                (StatusCode::OK, Json(rules)).into_response()
+            }
+            Self::Rule(rule) => {
+                info!("{rule:?}");
+                (StatusCode::OK, Json(rule)).into_response()
+            }
+            Self::Success(msg) => {
+                info!("{}", msg.message);
+                (StatusCode::OK, Json(msg)).into_response()
+            }
+        }
+    }
+}
+
+impl From<OperatorError> for DomainRuleResponse {
+    fn from(e: OperatorError) -> Self {
+        match e {
+            OperatorError::NotLoggedIn => Self::InvalidSession(e.to_string().into()),
+            OperatorError::NotOperator => Self::NotOperator(e.to_string().into()),
+            OperatorError::DbErr(e) => Self::DatabaseError(e.to_string().into()),
+        }
+    }
+}
+
+impl From<sea_orm::DbErr> for DomainRuleResponse {
+    fn from(e: sea_orm::DbErr) -> Self {
+        Self::DatabaseError(e.to_string().into())
+    }
+}
+
+impl From<ArcMutexError> for DomainRuleResponse {
+    fn from(value: ArcMutexError) -> Self {
+        Self::CacheError(value.to_string().into())
+    }
+}
+
+impl From<ServerError> for DomainRuleResponse {
+    fn from(e: ServerError) -> Self {
+        Self::DatabaseError(e.to_string().into())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, IntoResponses, TS)]
+#[serde(untagged)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub enum ClickStatsResponse {
+    #[response(status = StatusCode::UNAUTHORIZED)]
+    InvalidSession(#[to_schema] BasicError),
+    #[response(status = StatusCode::FORBIDDEN)]
+    NotOperator(#[to_schema] BasicError),
+    #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
+    DatabaseError(#[to_schema] BasicError),
+    #[response(status = StatusCode::OK)]
+    Stats(#[to_schema] ClickStats),
+}
+
+impl IntoResponse for ClickStatsResponse {
+    #[instrument]
+    fn into_response(self) -> Response {
+        match self {
+            Self::InvalidSession(e) => {
+                error!(%e);
+                (StatusCode::UNAUTHORIZED, Json(e)).into_response()
+            }
+            Self::NotOperator(e) => {
+                warn!(%e);
+                (StatusCode::FORBIDDEN, Json(e)).into_response()
+            }
+            Self::DatabaseError(e) => {
+                error!(%e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
+            }
+            Self::Stats(stats) => {
+                info!("{stats:?}");
+                (StatusCode::OK, Json(stats)).into_response()
+            }
+        }
+    }
+}
+
+impl From<OperatorError> for ClickStatsResponse {
+    fn from(e: OperatorError) -> Self {
+        match e {
+            OperatorError::NotLoggedIn => Self::InvalidSession(e.to_string().into()),
+            OperatorError::NotOperator => Self::NotOperator(e.to_string().into()),
+            OperatorError::DbErr(e) => Self::DatabaseError(e.to_string().into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, IntoResponses, TS)]
+#[serde(untagged)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub enum JobResponse {
+    #[response(status = StatusCode::UNAUTHORIZED)]
+    InvalidSession(#[to_schema] BasicError),
+    #[response(status = StatusCode::FORBIDDEN)]
+    NotOperator(#[to_schema] BasicError),
+    #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
+    DatabaseError(#[to_schema] BasicError),
+    #[response(status = StatusCode::NOT_FOUND)]
+    JobNotFound,
+    #[response(status = StatusCode::OK)]
+    Jobs(#[to_schema] Vec<job::Model>),
+    #[response(status = StatusCode::ACCEPTED)]
+    Job(#[to_schema] job::Model),
+}
+
+impl IntoResponse for JobResponse {
+    #[instrument]
+    fn into_response(self) -> Response {
+        match self {
+            Self::InvalidSession(e) => {
+                error!(%e);
+                (StatusCode::UNAUTHORIZED, Json(e)).into_response()
+            }
+            Self::NotOperator(e) => {
+                warn!(%e);
+                (StatusCode::FORBIDDEN, Json(e)).into_response()
+            }
+            Self::DatabaseError(e) => {
+                error!(%e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
+            }
+            Self::JobNotFound => {
+                error!("Job not found");
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(BasicError {
+                        error: "Job not found".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+            Self::Jobs(jobs) => {
+                info!("{} jobs", jobs.len());
+                (StatusCode::OK, Json(jobs)).into_response()
+            }
+            Self::Job(job) => {
+                info!("Triggered {job:?}");
+                (StatusCode::ACCEPTED, Json(job)).into_response()
+            }
+        }
+    }
+}
+
+impl From<OperatorError> for JobResponse {
+    fn from(e: OperatorError) -> Self {
+        match e {
+            OperatorError::NotLoggedIn => Self::InvalidSession(e.to_string().into()),
+            OperatorError::NotOperator => Self::NotOperator(e.to_string().into()),
+            OperatorError::DbErr(e) => Self::DatabaseError(e.to_string().into()),
+        }
+    }
+}
+
+impl From<sea_orm::DbErr> for JobResponse {
+    fn from(e: sea_orm::DbErr) -> Self {
+        Self::DatabaseError(e.to_string().into())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, IntoResponses, TS)]
+#[serde(untagged)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub enum DeadLetterResponse {
+    #[response(status = StatusCode::UNAUTHORIZED)]
+    InvalidSession(#[to_schema] BasicError),
+    #[response(status = StatusCode::FORBIDDEN)]
+    NotOperator(#[to_schema] BasicError),
+    #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
+    DatabaseError(#[to_schema] BasicError),
+    #[response(status = StatusCode::NOT_FOUND)]
+    DeadLetterNotFound,
+    #[response(status = StatusCode::OK)]
+    DeadLetters(#[to_schema] DeadLetterList),
+    #[response(status = StatusCode::ACCEPTED)]
+    Success(#[to_schema] BasicResponse),
+}
+
+impl IntoResponse for DeadLetterResponse {
+    #[instrument]
+    fn into_response(self) -> Response {
+        match self {
+            Self::InvalidSession(e) => {
+                error!(%e);
+                (StatusCode::UNAUTHORIZED, Json(e)).into_response()
+            }
+            Self::NotOperator(e) => {
+                warn!(%e);
+                (StatusCode::FORBIDDEN, Json(e)).into_response()
+            }
+            Self::DatabaseError(e) => {
+                error!(%e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
+            }
+            Self::DeadLetterNotFound => {
+                error!("Dead letter not found");
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(BasicError {
+                        error: "Dead letter not found".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+            Self::DeadLetters(list) => {
+                info!("{} dead letters", list.letters.len());
+                (StatusCode::OK, Json(list)).into_response()
+            }
+            Self::Success(msg) => {
+                info!("{}", msg.message);
+                (StatusCode::ACCEPTED, Json(msg)).into_response()
+            }
+        }
+    }
+}
+
+impl From<OperatorError> for DeadLetterResponse {
+    fn from(e: OperatorError) -> Self {
+        match e {
+            OperatorError::NotLoggedIn => Self::InvalidSession(e.to_string().into()),
+            OperatorError::NotOperator => Self::NotOperator(e.to_string().into()),
+            OperatorError::DbErr(e) => Self::DatabaseError(e.to_string().into()),
+        }
+    }
+}
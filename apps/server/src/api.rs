@@ -8,7 +8,7 @@ use utoipa::{
 use utoipa_axum::{router::OpenApiRouter, routes};
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::{state::ServerState, urls, user, utils};
+use crate::{actor, admin, state::ServerState, urls, user, utils, webhooks};
 
 #[derive(Debug, Serialize)]
 pub struct SecurityDef;
@@ -40,6 +40,8 @@ impl Modify for SecurityDef {
             user::structs::UserLinksAndViews,
             user::structs::OidcName,
             urls::structs::NewUrlRequest,
+            actor::ClickEvent,
+            actor::WebhookPayload,
             utils::BasicError,
             utils::BasicResponse
         ),
@@ -49,6 +51,8 @@ impl Modify for SecurityDef {
         (name = user::USER_TAG, description = "User API routes"),
         (name = user::OIDC_TAG, description = "OIDC users API routes"),
         (name = user::LOCAL_TAG, description = "Local users API routes"),
+        (name = admin::ADMIN_TAG, description = "Operator-only admin API routes"),
+        (name = webhooks::WEBHOOK_TAG, description = "Outgoing webhook API routes"),
     )
 )]
 pub struct ApiDoc;
@@ -72,6 +76,8 @@ pub fn router(state: ServerState) -> Router {
         .merge(user::user_router(state.clone()))
         .merge(user::oidc_router(state.clone()))
         .merge(user::local_router(state.clone()))
+        .merge(admin::admin_router(state.clone()))
+        .merge(webhooks::webhook_router(state.clone()))
         .split_for_parts();
 
     router.merge(SwaggerUi::new("/api/ui/swagger").url("/api/doc/openapi.json", api))
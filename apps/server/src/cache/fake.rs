@@ -0,0 +1,234 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use async_trait::async_trait;
+use tokio::sync::broadcast;
+
+use super::{
+    CacheBackend, CacheError, CachedLink, MemoryCache, REPLICA_EVENT_CAPACITY, ReplicaEvent,
+};
+use crate::config::CacheConfig;
+
+/// An in-memory stand-in for [`super::RedisCache`], for running several replicas in one process
+/// without a Redis server.
+///
+/// Every cache made with [`SharedMemoryCache::replica`] reads and writes the same store, drops
+/// its local copies when another replica changes a slug and hears the events the other
+/// replicas publish, the same way replicas sharing a Redis server do.
+#[derive(Debug, Clone)]
+pub struct SharedMemoryCache {
+    store: MemoryCache,
+    replicas: Arc<Mutex<Vec<Replica>>>,
+    id: usize,
+    local: MemoryCache,
+    events: broadcast::Sender<ReplicaEvent>,
+    config: CacheConfig,
+}
+
+#[derive(Debug)]
+struct Replica {
+    local: MemoryCache,
+    events: broadcast::Sender<ReplicaEvent>,
+}
+
+impl SharedMemoryCache {
+    pub fn new(config: &CacheConfig) -> Self {
+        Self::join(
+            MemoryCache::new(config),
+            Arc::new(Mutex::new(Vec::new())),
+            config,
+        )
+    }
+
+    /// Another replica sharing this cache's store, invalidations and events.
+    pub fn replica(&self) -> Self {
+        Self::join(self.store.clone(), self.replicas.clone(), &self.config)
+    }
+
+    fn join(store: MemoryCache, replicas: Arc<Mutex<Vec<Replica>>>, config: &CacheConfig) -> Self {
+        let local = Self::local_cache(config);
+        let events = broadcast::channel(REPLICA_EVENT_CAPACITY).0;
+        let id = {
+            let mut replicas = replicas.lock().unwrap_or_else(PoisonError::into_inner);
+            replicas.push(Replica {
+                local: local.clone(),
+                events: events.clone(),
+            });
+            replicas.len() - 1
+        };
+        Self {
+            store,
+            replicas,
+            id,
+            local,
+            events,
+            config: config.clone(),
+        }
+    }
+
+    fn local_cache(config: &CacheConfig) -> MemoryCache {
+        MemoryCache::with_ttl(
+            config,
+            config.local_ttl.min(config.ttl),
+            config.local_ttl.min(config.negative_ttl),
+        )
+    }
+
+    fn broadcast(&self, keys: &[String]) {
+        for replica in self
+            .replicas
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            replica.local.invalidate_local(keys);
+        }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for SharedMemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<CachedLink>, CacheError> {
+        if let Some(cached) = self.local.get_local(key) {
+            return Ok(Some(cached));
+        }
+        let cached = self.store.get_local(key);
+        if let Some(cached) = &cached {
+            self.local.put_local(key, cached.clone());
+        }
+        Ok(cached)
+    }
+
+    async fn put(&self, key: &str, link: CachedLink) -> Result<(), CacheError> {
+        self.broadcast(&[key.to_owned()]);
+        self.store.put_local(key, link.clone());
+        self.local.put_local(key, link);
+        Ok(())
+    }
+
+    async fn invalidate(&self, keys: &[String]) -> Result<usize, CacheError> {
+        self.broadcast(keys);
+        Ok(self.store.invalidate_local(keys))
+    }
+
+    async fn publish(&self, event: &ReplicaEvent) -> Result<(), CacheError> {
+        let replicas = self.replicas.lock().unwrap_or_else(PoisonError::into_inner);
+        for (id, replica) in replicas.iter().enumerate() {
+            if id != self.id {
+                let _ = replica.events.send(event.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Option<broadcast::Receiver<ReplicaEvent>> {
+        Some(self.events.subscribe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{TimeDelta, Utc};
+
+    use super::*;
+
+    fn redirect(url: &str) -> CachedLink {
+        CachedLink::Redirect {
+            url: url.to_owned(),
+            expires_at: None,
+            max_clicks: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn updates_and_deletes_reach_other_replicas() {
+        let first = SharedMemoryCache::new(&CacheConfig::default());
+        let second = first.replica();
+
+        first
+            .put("abc", redirect("https://one.example"))
+            .await
+            .unwrap();
+        // Leaves a local copy on the second replica, which the next writes must drop
+        assert_eq!(
+            second.get("abc").await.unwrap(),
+            Some(redirect("https://one.example"))
+        );
+
+        first
+            .put("abc", redirect("https://two.example"))
+            .await
+            .unwrap();
+        assert_eq!(
+            second.get("abc").await.unwrap(),
+            Some(redirect("https://two.example"))
+        );
+
+        assert_eq!(first.invalidate(&["abc".to_owned()]).await.unwrap(), 1);
+        assert_eq!(second.get("abc").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn ttl_is_capped_at_expiry() {
+        let first = SharedMemoryCache::new(&CacheConfig::default());
+        let second = first.replica();
+        let expires_at = Utc::now().naive_utc() + TimeDelta::milliseconds(200);
+        let link = CachedLink::Redirect {
+            url: "https://example.com".to_owned(),
+            expires_at: Some(expires_at),
+            max_clicks: None,
+        };
+        let ttl = link.time_to_live(Duration::from_secs(3600), Duration::from_secs(30));
+        assert!(ttl <= Duration::from_millis(200), "{ttl:?}");
+
+        first.put("abc", link.clone()).await.unwrap();
+        assert_eq!(second.get("abc").await.unwrap(), Some(link));
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(second.get("abc").await.unwrap(), None);
+        assert_eq!(first.get("abc").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn missing_entries_are_evicted() {
+        let config = CacheConfig {
+            negative_ttl: Duration::from_millis(100),
+            ..CacheConfig::default()
+        };
+        let first = SharedMemoryCache::new(&config);
+        let second = first.replica();
+
+        first.put("abc", CachedLink::Missing).await.unwrap();
+        assert_eq!(second.get("abc").await.unwrap(), Some(CachedLink::Missing));
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(second.get("abc").await.unwrap(), None);
+        assert_eq!(first.get("abc").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn events_reach_every_other_replica() {
+        let first = SharedMemoryCache::new(&CacheConfig::default());
+        let second = first.replica();
+        let third = first.replica();
+        let mut on_first = first.subscribe().unwrap();
+        let mut on_second = second.subscribe().unwrap();
+        let mut on_third = third.subscribe().unwrap();
+
+        first
+            .publish(&ReplicaEvent::DomainRulesChanged)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            on_second.try_recv().unwrap(),
+            ReplicaEvent::DomainRulesChanged
+        );
+        assert_eq!(
+            on_third.try_recv().unwrap(),
+            ReplicaEvent::DomainRulesChanged
+        );
+        assert!(on_first.try_recv().is_err());
+    }
+}
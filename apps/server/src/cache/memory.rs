@@ -0,0 +1,106 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use moka::{Expiry, sync::SegmentedCache};
+
+use super::{CacheBackend, CacheError, CachedLink};
+use crate::config::CacheConfig;
+
+struct LinkExpiry {
+    ttl: Duration,
+    negative_ttl: Duration,
+}
+
+impl Expiry<String, CachedLink> for LinkExpiry {
+    fn expire_after_create(
+        &self,
+        _key: &String,
+        value: &CachedLink,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        Some(value.time_to_live(self.ttl, self.negative_ttl))
+    }
+
+    fn expire_after_update(
+        &self,
+        _key: &String,
+        value: &CachedLink,
+        _updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        Some(value.time_to_live(self.ttl, self.negative_ttl))
+    }
+}
+
+/// A cache private to this process.
+///
+/// Entries are spread over independently locked shards and reads never block each other.
+/// Clones share the same entries.
+#[derive(Clone)]
+pub struct MemoryCache {
+    inner: SegmentedCache<String, CachedLink>,
+}
+
+impl fmt::Debug for MemoryCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryCache")
+            .field("entries", &self.inner.entry_count())
+            .finish()
+    }
+}
+
+impl MemoryCache {
+    pub fn new(config: &CacheConfig) -> Self {
+        Self::with_ttl(config, config.ttl, config.negative_ttl)
+    }
+
+    pub(super) fn with_ttl(config: &CacheConfig, ttl: Duration, negative_ttl: Duration) -> Self {
+        let inner = SegmentedCache::builder(config.shards.max(1))
+            .max_capacity(config.capacity)
+            .expire_after(LinkExpiry { ttl, negative_ttl })
+            .build();
+        Self { inner }
+    }
+
+    pub(super) fn get_local(&self, key: &str) -> Option<CachedLink> {
+        let cached = self.inner.get(key)?;
+        if cached.is_expired() {
+            self.inner.invalidate(key);
+            return None;
+        }
+        Some(cached)
+    }
+
+    pub(super) fn put_local(&self, key: &str, link: CachedLink) {
+        self.inner.insert(key.to_owned(), link);
+    }
+
+    pub(super) fn invalidate_local(&self, keys: &[String]) -> usize {
+        keys.iter()
+            .filter(|key| self.inner.remove(key.as_str()).is_some())
+            .count()
+    }
+
+    pub(super) fn clear(&self) {
+        self.inner.invalidate_all();
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<CachedLink>, CacheError> {
+        Ok(self.get_local(key))
+    }
+
+    async fn put(&self, key: &str, link: CachedLink) -> Result<(), CacheError> {
+        self.put_local(key, link);
+        Ok(())
+    }
+
+    async fn invalidate(&self, keys: &[String]) -> Result<usize, CacheError> {
+        Ok(self.invalidate_local(keys))
+    }
+}
@@ -0,0 +1,181 @@
+mod fake;
+mod memory;
+mod redis;
+
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use entity::short_link;
+pub use fake::SharedMemoryCache;
+pub use memory::MemoryCache;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+pub use self::redis::RedisCache;
+use crate::{actor::ReplicaClick, config::CacheConfig};
+
+#[derive(Error, Debug)]
+pub enum CacheError {
+    #[error("Redis error: {0}")]
+    Redis(#[from] ::redis::RedisError),
+    #[error("Unable to decode a cached link: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+/// A cached redirect lookup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CachedLink {
+    /// The slug resolves to `url` until `expires_at`, if the link has an expiry date.
+    Redirect {
+        url: String,
+        expires_at: Option<NaiveDateTime>,
+        #[serde(default)]
+        max_clicks: Option<i64>,
+    },
+    /// The slug is not a live link, so the database does not need to be asked again.
+    Missing,
+}
+
+impl From<&short_link::Model> for CachedLink {
+    fn from(link: &short_link::Model) -> Self {
+        Self::Redirect {
+            url: link.original_url.clone(),
+            expires_at: link.expiry_date,
+            max_clicks: link.max_clicks,
+        }
+    }
+}
+
+impl CachedLink {
+    /// How long this entry may be cached for, capped by the link's own expiry.
+    pub fn time_to_live(&self, ttl: Duration, negative_ttl: Duration) -> Duration {
+        match self {
+            Self::Redirect {
+                expires_at: Some(expires_at),
+                ..
+            } => (*expires_at - Utc::now().naive_utc())
+                .to_std()
+                .map_or(Duration::ZERO, |remaining| remaining.min(ttl)),
+            Self::Redirect { .. } => ttl,
+            Self::Missing => negative_ttl,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        matches!(self, Self::Redirect { expires_at: Some(expires_at), .. } if *expires_at <= Utc::now().naive_utc())
+    }
+}
+
+/// How many events from other replicas a subscriber can fall behind by.
+const REPLICA_EVENT_CAPACITY: usize = 1024;
+
+/// A change made on one replica that every other replica has to act on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReplicaEvent {
+    /// The stored domain rules changed and have to be reloaded.
+    DomainRulesChanged,
+    /// Clicks were recorded, for the live subscribers of every replica.
+    Clicks { clicks: Vec<ReplicaClick> },
+}
+
+/// Storage for the redirect cache.
+///
+/// Backends that are shared between replicas must make sure an invalidation on one replica is
+/// seen by every other replica before their next lookup of that slug.
+#[async_trait]
+pub trait CacheBackend: Debug + Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<CachedLink>, CacheError>;
+    async fn put(&self, key: &str, link: CachedLink) -> Result<(), CacheError>;
+    /// Evicts every given slug, returning how many were cached.
+    async fn invalidate(&self, keys: &[String]) -> Result<usize, CacheError>;
+    /// Sends `event` to every other replica sharing this backend. A backend private to one
+    /// process has no one to tell.
+    async fn publish(&self, _event: &ReplicaEvent) -> Result<(), CacheError> {
+        Ok(())
+    }
+    /// The events the other replicas publish, or `None` when the backend isn't shared.
+    fn subscribe(&self) -> Option<broadcast::Receiver<ReplicaEvent>> {
+        None
+    }
+}
+
+/// The slug to destination cache consulted before the database on every redirect.
+///
+/// Cloning is cheap and every clone shares the same backend, so the actor pool can evict links
+/// it removes in the background.
+#[derive(Debug, Clone)]
+pub struct LinkCache {
+    backend: Arc<dyn CacheBackend>,
+}
+
+impl LinkCache {
+    pub fn new(backend: impl CacheBackend + 'static) -> Self {
+        Self {
+            backend: Arc::new(backend),
+        }
+    }
+
+    /// Builds the backend selected by the configuration, connecting to Redis when
+    /// `REDIS_URL` is set.
+    #[tracing::instrument(skip(config))]
+    pub async fn from_config(config: &CacheConfig) -> Result<Self, CacheError> {
+        Ok(match config.redis_url.as_deref() {
+            Some(url) => Self::new(RedisCache::connect(url, config).await?),
+            None => Self::new(MemoryCache::new(config)),
+        })
+    }
+
+    /// Looks up a slug, never returning a destination whose link has expired.
+    #[tracing::instrument]
+    pub async fn get(&self, key: &str) -> Result<Option<CachedLink>, CacheError> {
+        Ok(self
+            .backend
+            .get(key)
+            .await?
+            .filter(|cached| !cached.is_expired()))
+    }
+
+    /// Caches a link's destination under its slug.
+    #[tracing::instrument(skip(link), fields(id = link.id))]
+    pub async fn put(&self, link: &short_link::Model) -> Result<(), CacheError> {
+        self.backend.put(&link.id, link.into()).await
+    }
+
+    /// Remembers that a slug does not resolve to a live link.
+    #[tracing::instrument]
+    pub async fn put_missing(&self, key: &str) -> Result<(), CacheError> {
+        self.backend.put(key, CachedLink::Missing).await
+    }
+
+    /// Evicts every given slug, returning how many were cached.
+    #[tracing::instrument(skip(keys))]
+    pub async fn invalidate<I, K>(&self, keys: I) -> Result<usize, CacheError>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        let keys: Vec<String> = keys
+            .into_iter()
+            .map(|key| key.as_ref().to_owned())
+            .collect();
+        if keys.is_empty() {
+            return Ok(0);
+        }
+        self.backend.invalidate(&keys).await
+    }
+
+    /// Tells every other replica about `event`.
+    #[tracing::instrument]
+    pub async fn publish(&self, event: &ReplicaEvent) -> Result<(), CacheError> {
+        self.backend.publish(event).await
+    }
+
+    /// The events the other replicas publish, or `None` when this replica is on its own.
+    pub fn subscribe(&self) -> Option<broadcast::Receiver<ReplicaEvent>> {
+        self.backend.subscribe()
+    }
+}
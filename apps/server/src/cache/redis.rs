@@ -0,0 +1,241 @@
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use redis::{
+    AsyncCommands, Client,
+    aio::{ConnectionManager, PubSub},
+};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::broadcast, time::sleep};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::{
+    CacheBackend, CacheError, CachedLink, MemoryCache, REPLICA_EVENT_CAPACITY, ReplicaEvent,
+};
+use crate::config::CacheConfig;
+
+const KEY_PREFIX: &str = "micro-url:link:";
+const INVALIDATION_CHANNEL: &str = "micro-url:invalidate";
+const EVENT_CHANNEL: &str = "micro-url:events";
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// Published on [`INVALIDATION_CHANNEL`] whenever a replica changes or evicts a slug.
+#[derive(Debug, Serialize, Deserialize)]
+struct Invalidation {
+    origin: Uuid,
+    keys: Vec<String>,
+}
+
+/// Published on [`EVENT_CHANNEL`] for every [`ReplicaEvent`].
+#[derive(Debug, Serialize, Deserialize)]
+struct Published {
+    origin: Uuid,
+    event: ReplicaEvent,
+}
+
+/// A cache shared by every replica through a Redis-protocol server.
+///
+/// Entries live in Redis with the configured TTLs. Each replica keeps a short-lived local copy
+/// of the slugs it has resolved, and every change is published so the other replicas drop
+/// their copies. While the subscription is down the local copies are bypassed.
+#[derive(Clone)]
+pub struct RedisCache {
+    conn: ConnectionManager,
+    local: MemoryCache,
+    subscribed: Arc<AtomicBool>,
+    origin: Uuid,
+    ttl: Duration,
+    negative_ttl: Duration,
+    events: broadcast::Sender<ReplicaEvent>,
+}
+
+impl fmt::Debug for RedisCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisCache")
+            .field("local", &self.local)
+            .field("subscribed", &self.subscribed.load(Ordering::Relaxed))
+            .field("origin", &self.origin)
+            .finish()
+    }
+}
+
+impl RedisCache {
+    #[tracing::instrument(skip(url))]
+    pub async fn connect(url: &str, config: &CacheConfig) -> Result<Self, CacheError> {
+        let client = Client::open(url)?;
+        let conn = client.get_connection_manager().await?;
+        let pubsub = subscribe(&client).await?;
+        let cache = Self {
+            conn,
+            local: MemoryCache::with_ttl(
+                config,
+                config.local_ttl.min(config.ttl),
+                config.local_ttl.min(config.negative_ttl),
+            ),
+            subscribed: Arc::new(AtomicBool::new(true)),
+            origin: Uuid::new_v4(),
+            ttl: config.ttl,
+            negative_ttl: config.negative_ttl,
+            events: broadcast::channel(REPLICA_EVENT_CAPACITY).0,
+        };
+        tokio::spawn(listen(client, pubsub, cache.clone()));
+        info!("Connected to the shared redirect cache");
+        Ok(cache)
+    }
+
+    fn key(key: &str) -> String {
+        format!("{KEY_PREFIX}{key}")
+    }
+
+    fn subscribed(&self) -> bool {
+        self.subscribed.load(Ordering::Acquire)
+    }
+
+    fn invalidation(&self, keys: &[String]) -> Result<String, CacheError> {
+        Ok(serde_json::to_string(&Invalidation {
+            origin: self.origin,
+            keys: keys.to_vec(),
+        })?)
+    }
+}
+
+async fn subscribe(client: &Client) -> Result<PubSub, CacheError> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub
+        .subscribe(&[INVALIDATION_CHANNEL, EVENT_CHANNEL])
+        .await?;
+    Ok(pubsub)
+}
+
+fn on_invalidation(cache: &RedisCache, payload: &str) -> Result<(), CacheError> {
+    let invalidation: Invalidation = serde_json::from_str(payload)?;
+    if invalidation.origin != cache.origin {
+        cache.local.invalidate_local(&invalidation.keys);
+    }
+    Ok(())
+}
+
+fn on_event(cache: &RedisCache, payload: &str) -> Result<(), CacheError> {
+    let published: Published = serde_json::from_str(payload)?;
+    if published.origin != cache.origin {
+        // Nobody listening isn't an error
+        let _ = cache.events.send(published.event);
+    }
+    Ok(())
+}
+
+/// Applies other replicas' invalidations to the local copies and passes their events on to
+/// this replica's subscribers, resubscribing if the connection drops.
+async fn listen(client: Client, mut pubsub: PubSub, cache: RedisCache) {
+    loop {
+        let mut messages = pubsub.into_on_message();
+        while let Some(msg) = messages.next().await {
+            let handled = msg
+                .get_payload::<String>()
+                .map_err(CacheError::from)
+                .and_then(|payload| match msg.get_channel_name() {
+                    EVENT_CHANNEL => on_event(&cache, &payload),
+                    _ => on_invalidation(&cache, &payload),
+                });
+            if let Err(e) = handled {
+                warn!("Ignoring a malformed message from another replica: {e}");
+            }
+        }
+
+        warn!("Lost the cache invalidation subscription, bypassing local copies until it is back");
+        cache.subscribed.store(false, Ordering::Release);
+        pubsub = loop {
+            sleep(RESUBSCRIBE_DELAY).await;
+            match subscribe(&client).await {
+                Ok(pubsub) => break pubsub,
+                Err(e) => warn!("Unable to resubscribe to cache invalidations: {e}"),
+            }
+        };
+        // Anything cached locally may have missed an invalidation while we were away, and the
+        // domain rules may have changed
+        cache.local.clear();
+        let _ = cache.events.send(ReplicaEvent::DomainRulesChanged);
+        cache.subscribed.store(true, Ordering::Release);
+        info!("Resubscribed to cache invalidations");
+    }
+}
+
+#[async_trait]
+impl CacheBackend for RedisCache {
+    async fn get(&self, key: &str) -> Result<Option<CachedLink>, CacheError> {
+        let subscribed = self.subscribed();
+        if subscribed && let Some(cached) = self.local.get_local(key) {
+            return Ok(Some(cached));
+        }
+        let mut conn = self.conn.clone();
+        let raw: Option<String> = conn.get(Self::key(key)).await?;
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+        let link: CachedLink = serde_json::from_str(&raw)?;
+        if subscribed {
+            self.local.put_local(key, link.clone());
+        }
+        Ok(Some(link))
+    }
+
+    async fn put(&self, key: &str, link: CachedLink) -> Result<(), CacheError> {
+        let ttl = link.time_to_live(self.ttl, self.negative_ttl);
+        let keys = [key.to_owned()];
+        if ttl.is_zero() {
+            self.invalidate(&keys).await?;
+            return Ok(());
+        }
+        let mut conn = self.conn.clone();
+        redis::pipe()
+            .atomic()
+            .pset_ex(
+                Self::key(key),
+                serde_json::to_string(&link)?,
+                ttl.as_millis() as u64,
+            )
+            .ignore()
+            .publish(INVALIDATION_CHANNEL, self.invalidation(&keys)?)
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await?;
+        self.local.put_local(key, link);
+        Ok(())
+    }
+
+    async fn invalidate(&self, keys: &[String]) -> Result<usize, CacheError> {
+        let mut conn = self.conn.clone();
+        let (removed,): (usize,) = redis::pipe()
+            .atomic()
+            .del(keys.iter().map(|key| Self::key(key)).collect::<Vec<_>>())
+            .publish(INVALIDATION_CHANNEL, self.invalidation(keys)?)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+        self.local.invalidate_local(keys);
+        Ok(removed)
+    }
+
+    async fn publish(&self, event: &ReplicaEvent) -> Result<(), CacheError> {
+        let mut conn = self.conn.clone();
+        let published = serde_json::to_string(&Published {
+            origin: self.origin,
+            event: event.clone(),
+        })?;
+        conn.publish::<_, _, ()>(EVENT_CHANNEL, published).await?;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Option<broadcast::Receiver<ReplicaEvent>> {
+        Some(self.events.subscribe())
+    }
+}
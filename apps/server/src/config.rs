@@ -1,6 +1,4 @@
-use std::env;
-#[cfg(feature = "ips")]
-use std::str::FromStr;
+use std::{env, fmt, str::FromStr, time::Duration};
 
 #[cfg(feature = "ips")]
 use axum_client_ip::ClientIpSource;
@@ -8,7 +6,10 @@ use openidconnect::{Scope, core::CoreClaimName};
 use serde::{Deserialize, Serialize};
 use shuttle_runtime::SecretStore;
 
-use crate::actor::ActorPoolConfig;
+use crate::{
+    actor::{ActorPoolConfig, DEFAULT_HEADER_ALLOWLIST, IpStorage, JobSchedule, ViewRetention},
+    utils::{parse_duration, parse_time_delta},
+};
 
 #[cfg(feature = "ips")]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +25,14 @@ pub struct ServerConfig {
     pub oidc: OidcConfig,
     pub ip_source: ClientIpSource,
     pub actors: ActorPoolConfig,
+    #[serde(default)]
+    pub links: LinkConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub geoip: GeoIpConfig,
 }
 
 #[cfg(not(feature = "ips"))]
@@ -39,6 +48,14 @@ pub struct ServerConfig {
     pub assets_path: String,
     pub oidc: OidcConfig,
     pub actors: ActorPoolConfig,
+    #[serde(default)]
+    pub links: LinkConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub geoip: GeoIpConfig,
 }
 
 #[cfg(not(feature = "ips"))]
@@ -54,6 +71,10 @@ impl Default for ServerConfig {
             scheme: "http".to_string(),
             assets_path: "../../js/frontend/dist".to_string(),
             actors: ActorPoolConfig::default(),
+            links: LinkConfig::default(),
+            admin: AdminConfig::default(),
+            cache: CacheConfig::default(),
+            geoip: GeoIpConfig::default(),
         }
     }
 }
@@ -72,6 +93,10 @@ impl Default for ServerConfig {
             assets_path: "../../js/frontend/dist".to_string(),
             ip_source: ClientIpSource::RightmostXForwardedFor,
             actors: ActorPoolConfig::default(),
+            links: LinkConfig::default(),
+            admin: AdminConfig::default(),
+            cache: CacheConfig::default(),
+            geoip: GeoIpConfig::default(),
         }
     }
 }
@@ -86,6 +111,182 @@ pub struct ServerDatabaseConfig {
     pub schema: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkConfig {
+    pub allowed_schemes: Vec<String>,
+    pub domain_rules_file: Option<String>,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            allowed_schemes: vec!["http".to_string(), "https".to_string()],
+            domain_rules_file: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct AdminConfig {
+    pub operators: Vec<String>,
+}
+
+impl AdminConfig {
+    pub fn is_operator(&self, email: &str) -> bool {
+        self.operators
+            .iter()
+            .any(|operator| operator.eq_ignore_ascii_case(email))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Maximum number of cached slugs across all shards.
+    pub capacity: u64,
+    pub shards: usize,
+    /// How long a destination stays cached, capped by the link's own expiry.
+    pub ttl: Duration,
+    /// How long an unknown slug is remembered as missing.
+    pub negative_ttl: Duration,
+    /// Shares the cache between replicas through this Redis-protocol server when set.
+    pub redis_url: Option<String>,
+    /// How long a replica keeps its own copy of an entry from the shared cache.
+    pub local_ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            shards: 16,
+            ttl: Duration::from_secs(60 * 60),
+            negative_ttl: Duration::from_secs(30),
+            redis_url: None,
+            local_ttl: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeoIpConfig {
+    /// MaxMind-format `.mmdb` file with country, region and city data, such as GeoLite2-City.
+    pub database: Option<String>,
+    /// MaxMind-format `.mmdb` file with ASN data, such as GeoLite2-ASN.
+    pub asn_database: Option<String>,
+    /// How often the database files are checked for changes and reloaded.
+    pub reload_interval: Duration,
+}
+
+impl Default for GeoIpConfig {
+    fn default() -> Self {
+        Self {
+            database: None,
+            asn_database: None,
+            reload_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// When the maintenance jobs run, and how replicas share them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    pub clean_sessions: JobSchedule,
+    pub clean_links: JobSchedule,
+    pub parse_user_agents: JobSchedule,
+    pub rollup_views: JobSchedule,
+    pub expire_views: JobSchedule,
+    pub prune_webhook_deliveries: JobSchedule,
+    /// How often each replica looks for due jobs.
+    #[serde(rename = "job_poll_interval")]
+    pub poll_interval: Duration,
+    /// How long a claimed job is held without its lease being renewed.
+    #[serde(rename = "job_lease")]
+    pub lease: Duration,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            clean_sessions: JobSchedule::every(Duration::from_secs(10)),
+            clean_links: JobSchedule::every(Duration::from_secs(30 * 60)),
+            parse_user_agents: JobSchedule::every(Duration::from_secs(5 * 60)),
+            rollup_views: JobSchedule::every(Duration::from_secs(60)),
+            expire_views: JobSchedule::every(Duration::from_secs(60 * 60)),
+            prune_webhook_deliveries: JobSchedule::every(Duration::from_secs(60 * 60)),
+            poll_interval: Duration::from_secs(5),
+            lease: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+/// What is kept about the visitor behind each view, and for how long.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    #[serde(rename = "view_ip_storage")]
+    pub ip_storage: IpStorage,
+    #[serde(rename = "view_ipv4_prefix")]
+    pub ipv4_prefix: u8,
+    #[serde(rename = "view_ipv6_prefix")]
+    pub ipv6_prefix: u8,
+    #[serde(rename = "view_ip_salt")]
+    pub ip_salt: Option<String>,
+    /// The request headers a view keeps. Leaving out `referer` or `user-agent` also leaves the
+    /// referrer or user agent column empty, though the browser, OS and device parsed from the
+    /// user agent are still kept.
+    #[serde(rename = "view_header_allowlist")]
+    pub header_allowlist: Vec<String>,
+    pub honor_do_not_track: bool,
+    /// How long views keep their per-visitor detail; forever when unset.
+    #[serde(rename = "view_retention")]
+    pub retention: Option<Duration>,
+    #[serde(rename = "view_retention_action")]
+    pub retention_action: ViewRetention,
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            ip_storage: IpStorage::default(),
+            ipv4_prefix: 24,
+            ipv6_prefix: 48,
+            ip_salt: None,
+            header_allowlist: DEFAULT_HEADER_ALLOWLIST.map(str::to_string).to_vec(),
+            honor_do_not_track: true,
+            retention: None,
+            retention_action: ViewRetention::default(),
+        }
+    }
+}
+
+/// How webhook deliveries are made and how long they are logged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    #[serde(rename = "webhook_timeout")]
+    pub timeout: Duration,
+    /// Lets webhooks point at private and loopback addresses.
+    #[serde(rename = "webhook_allow_private")]
+    pub allow_private: bool,
+    #[serde(rename = "webhook_delivery_retention")]
+    pub delivery_retention: Duration,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            allow_private: false,
+            delivery_retention: Duration::from_secs(30 * 24 * 60 * 60),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct OidcConfig {
     pub name: String,
@@ -116,13 +317,17 @@ impl GetConfig for ServerConfig {
         let scheme = env::var("SCHEME").unwrap_or("http".to_string());
         let internal_url = env::var("INTERNAL_URL").unwrap_or(format!("{addr}:{port}"));
         let external_url =
-            env::var("EXTERNAL_URL").unwrap_or(format!("{}://{}", &scheme, &internal_url));
+            env::var("EXTERNAL_URL").unwrap_or(format!("{}://{}", scheme, internal_url));
         let assets_path = env::var("ASSETS_PATH").unwrap_or("../../js/frontend/dist".to_string());
         let oidc = OidcConfig::from_env();
         let ip_source: ClientIpSource = env::var("IP_SOURCE_HEADER")
             .map(|v| ClientIpSource::from_str(&v).expect("Unable to parse the IP_SOURCE_HEADER"))
             .unwrap_or(ClientIpSource::RightmostXForwardedFor);
         let actors = ActorPoolConfig::from_env();
+        let links = LinkConfig::from_env();
+        let admin = AdminConfig::from_env();
+        let cache = CacheConfig::from_env();
+        let geoip = GeoIpConfig::from_env();
         Self {
             db,
             internal_url,
@@ -134,6 +339,10 @@ impl GetConfig for ServerConfig {
             oidc,
             ip_source,
             actors,
+            links,
+            admin,
+            cache,
+            geoip,
         }
     }
 
@@ -150,6 +359,10 @@ impl GetConfig for ServerConfig {
             .get("IP_SOURCE_HEADER")
             .map(|v| ClientIpSource::from_str(&v).expect("Unable to parse the IP_SOURCE_HEADER"))
             .unwrap_or(ClientIpSource::RightmostXForwardedFor);
+        let links = LinkConfig::from_secret(secrets.clone());
+        let admin = AdminConfig::from_secret(secrets.clone());
+        let cache = CacheConfig::from_secret(secrets.clone());
+        let geoip = GeoIpConfig::from_secret(secrets.clone());
         let actors = ActorPoolConfig::from_secret(secrets);
         Self {
             oidc,
@@ -157,15 +370,19 @@ impl GetConfig for ServerConfig {
             assets_path,
             ip_source,
             actors,
+            links,
+            admin,
+            cache,
+            geoip,
             ..Self::default()
         }
     }
 }
 
 #[cfg(not(feature = "ips"))]
-impl ServerConfig {
+impl GetConfig for ServerConfig {
     #[tracing::instrument]
-    pub fn from_env() -> Self {
+    fn from_env() -> Self {
         dotenvy::dotenv().ok();
         let db = ServerDatabaseConfig::from_env();
         let addr = env::var("ADDR").unwrap_or("127.0.0.1".to_string());
@@ -174,12 +391,16 @@ impl ServerConfig {
             .parse()
             .expect("PORT must be a number");
         let scheme = env::var("SCHEME").unwrap_or("http".to_string());
-        let internal_url = env::var("INTERNAL_URL").unwrap_or(|| format!("{}:{}", addr, port));
+        let internal_url = env::var("INTERNAL_URL").unwrap_or_else(|_| format!("{addr}:{port}"));
         let external_url =
-            env::var("EXTERNAL_URL").unwrap_or(format!("{}://{}", &scheme, &internal_url));
+            env::var("EXTERNAL_URL").unwrap_or(format!("{}://{}", scheme, internal_url));
         let assets_path = env::var("ASSETS_PATH").unwrap_or("../../js/frontend/dist".to_string());
         let oidc = OidcConfig::from_env();
         let actors = ActorPoolConfig::from_env();
+        let links = LinkConfig::from_env();
+        let admin = AdminConfig::from_env();
+        let cache = CacheConfig::from_env();
+        let geoip = GeoIpConfig::from_env();
         Self {
             db,
             internal_url,
@@ -190,11 +411,15 @@ impl ServerConfig {
             assets_path,
             oidc,
             actors,
+            links,
+            admin,
+            cache,
+            geoip,
         }
     }
 
     #[tracing::instrument(skip(secrets))]
-    pub fn from_secret(secrets: SecretStore) -> Self {
+    fn from_secret(secrets: SecretStore) -> Self {
         let oidc = OidcConfig::from_secret(secrets.clone());
         let external_url = secrets
             .get("EXTERNAL_URL")
@@ -202,17 +427,235 @@ impl ServerConfig {
         let assets_path = secrets
             .get("ASSETS_PATH")
             .unwrap_or_else(|| "../../js/frontend/dist".to_string());
+        let links = LinkConfig::from_secret(secrets.clone());
+        let admin = AdminConfig::from_secret(secrets.clone());
+        let cache = CacheConfig::from_secret(secrets.clone());
+        let geoip = GeoIpConfig::from_secret(secrets.clone());
         let actors = ActorPoolConfig::from_secret(secrets);
         Self {
             oidc,
             external_url,
             assets_path,
             actors,
+            links,
+            admin,
+            cache,
+            geoip,
             ..Self::default()
         }
     }
 }
 
+/// Splits a comma or whitespace separated list, so an empty string means none.
+fn parse_list(list: &str) -> Vec<String> {
+    list.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_ascii_lowercase())
+        .collect()
+}
+
+/// Where settings are read from: the environment, or Shuttle's secret store. A setting that is
+/// present but doesn't parse stops the server with its name.
+pub enum ConfigSource {
+    Env,
+    Secrets(SecretStore),
+}
+
+impl ConfigSource {
+    pub fn get(&self, name: &str) -> Option<String> {
+        match self {
+            Self::Env => env::var(name).ok(),
+            Self::Secrets(secrets) => secrets.get(name),
+        }
+    }
+
+    fn parse_with<T, E: fmt::Display>(
+        &self,
+        name: &str,
+        parse: impl FnOnce(&str) -> Result<T, E>,
+    ) -> Option<T> {
+        self.get(name).map(|value| {
+            parse(&value).unwrap_or_else(|e| panic!("Unable to parse {name} {value:?}: {e}"))
+        })
+    }
+
+    pub fn parse<T>(&self, name: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.parse_with(name, str::parse)
+    }
+
+    /// A duration such as `500ms` or `10m`.
+    pub fn duration(&self, name: &str) -> Option<Duration> {
+        self.parse_with(name, parse_duration)
+    }
+
+    /// A duration that may run to days or years, such as `30d`.
+    pub fn long_duration(&self, name: &str) -> Option<Duration> {
+        self.parse_with(name, |s| {
+            parse_time_delta(s)
+                .map_err(|e| e.to_string())?
+                .to_std()
+                .map_err(|_| "the duration is too large".to_string())
+        })
+    }
+
+    pub fn list(&self, name: &str) -> Option<Vec<String>> {
+        self.get(name).map(|s| parse_list(&s))
+    }
+}
+
+/// A config read setting by setting from a [`ConfigSource`], so it is parsed the same way from
+/// the environment and from secrets.
+pub trait LoadConfig {
+    fn load(source: &ConfigSource) -> Self;
+}
+
+impl<T: LoadConfig> GetConfig for T {
+    fn from_env() -> Self {
+        dotenvy::dotenv().ok();
+        Self::load(&ConfigSource::Env)
+    }
+
+    fn from_secret(secrets: SecretStore) -> Self {
+        Self::load(&ConfigSource::Secrets(secrets))
+    }
+}
+
+impl LoadConfig for LinkConfig {
+    fn load(source: &ConfigSource) -> Self {
+        let defaults = Self::default();
+        Self {
+            allowed_schemes: source
+                .list("ALLOWED_URL_SCHEMES")
+                .unwrap_or(defaults.allowed_schemes),
+            domain_rules_file: source.get("DOMAIN_RULES_FILE"),
+        }
+    }
+}
+
+impl LoadConfig for AdminConfig {
+    fn load(source: &ConfigSource) -> Self {
+        Self {
+            operators: source.list("OPERATOR_EMAILS").unwrap_or_default(),
+        }
+    }
+}
+
+impl LoadConfig for CacheConfig {
+    fn load(source: &ConfigSource) -> Self {
+        let defaults = Self::default();
+        Self {
+            capacity: source
+                .parse("REDIRECT_CACHE_CAPACITY")
+                .unwrap_or(defaults.capacity),
+            shards: source
+                .parse("REDIRECT_CACHE_SHARDS")
+                .unwrap_or(defaults.shards),
+            ttl: source
+                .duration("REDIRECT_CACHE_TTL")
+                .unwrap_or(defaults.ttl),
+            negative_ttl: source
+                .duration("REDIRECT_CACHE_NEGATIVE_TTL")
+                .unwrap_or(defaults.negative_ttl),
+            redis_url: source.get("REDIS_URL"),
+            local_ttl: source
+                .duration("REDIRECT_CACHE_LOCAL_TTL")
+                .unwrap_or(defaults.local_ttl),
+        }
+    }
+}
+
+impl LoadConfig for GeoIpConfig {
+    fn load(source: &ConfigSource) -> Self {
+        let defaults = Self::default();
+        Self {
+            database: source.get("GEOIP_DATABASE"),
+            asn_database: source.get("GEOIP_ASN_DATABASE"),
+            reload_interval: source
+                .duration("GEOIP_RELOAD_INTERVAL")
+                .unwrap_or(defaults.reload_interval),
+        }
+    }
+}
+
+impl LoadConfig for SchedulerConfig {
+    fn load(source: &ConfigSource) -> Self {
+        let defaults = Self::default();
+        Self {
+            clean_sessions: source
+                .parse("SESSION_CLEAN_INTERVAL")
+                .unwrap_or(defaults.clean_sessions),
+            clean_links: source
+                .parse("SHORT_LINKS_CLEAN_INTERVAL")
+                .unwrap_or(defaults.clean_links),
+            parse_user_agents: source
+                .parse("USER_AGENT_BACKFILL_INTERVAL")
+                .unwrap_or(defaults.parse_user_agents),
+            rollup_views: source
+                .parse("VIEW_ROLLUP_INTERVAL")
+                .unwrap_or(defaults.rollup_views),
+            expire_views: source
+                .parse("VIEW_RETENTION_INTERVAL")
+                .unwrap_or(defaults.expire_views),
+            prune_webhook_deliveries: source
+                .parse("WEBHOOK_DELIVERY_PRUNE_INTERVAL")
+                .unwrap_or(defaults.prune_webhook_deliveries),
+            poll_interval: source
+                .duration("JOB_POLL_INTERVAL")
+                .unwrap_or(defaults.poll_interval),
+            lease: source.duration("JOB_LEASE").unwrap_or(defaults.lease),
+        }
+    }
+}
+
+impl LoadConfig for PrivacyConfig {
+    fn load(source: &ConfigSource) -> Self {
+        let defaults = Self::default();
+        Self {
+            ip_storage: source
+                .parse("VIEW_IP_STORAGE")
+                .unwrap_or(defaults.ip_storage),
+            ipv4_prefix: source
+                .parse("VIEW_IPV4_PREFIX")
+                .unwrap_or(defaults.ipv4_prefix),
+            ipv6_prefix: source
+                .parse("VIEW_IPV6_PREFIX")
+                .unwrap_or(defaults.ipv6_prefix),
+            ip_salt: source.get("VIEW_IP_SALT"),
+            header_allowlist: source
+                .list("VIEW_HEADER_ALLOWLIST")
+                .unwrap_or(defaults.header_allowlist),
+            honor_do_not_track: source
+                .parse("VIEW_HONOR_DNT")
+                .unwrap_or(defaults.honor_do_not_track),
+            retention: source.long_duration("VIEW_RETENTION"),
+            retention_action: source
+                .parse("VIEW_RETENTION_ACTION")
+                .unwrap_or(defaults.retention_action),
+        }
+    }
+}
+
+impl LoadConfig for WebhookConfig {
+    fn load(source: &ConfigSource) -> Self {
+        let defaults = Self::default();
+        Self {
+            timeout: source
+                .duration("WEBHOOK_TIMEOUT")
+                .unwrap_or(defaults.timeout),
+            allow_private: source
+                .parse("WEBHOOK_ALLOW_PRIVATE")
+                .unwrap_or(defaults.allow_private),
+            delivery_retention: source
+                .long_duration("WEBHOOK_DELIVERY_RETENTION")
+                .unwrap_or(defaults.delivery_retention),
+        }
+    }
+}
+
 impl GetConfig for OidcConfig {
     #[tracing::instrument]
     fn from_env() -> Self {
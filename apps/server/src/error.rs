@@ -65,6 +65,10 @@ pub enum ServerError {
     ParseIntError(#[from] std::num::ParseIntError),
     #[error("Mutation error: {0}")]
     ArcMutexError(#[from] ArcMutexError),
+    #[error("Refusing to connect to a non-public address: {0}")]
+    NonPublicAddress(String),
+    #[error("Cache error: {0}")]
+    CacheError(#[from] crate::cache::CacheError),
 }
 
 impl IntoResponse for ServerError {
@@ -89,10 +93,7 @@ impl IntoResponse for ServerError {
                     "Attempted to get a nonexistent value".to_string(),
                 )
             }
-            Self::FromRequestPartsError(e) => {
-                error!("Infallible error from request parts: {}", e);
-                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
-            }
+            Self::FromRequestPartsError(e) => match e {},
             Self::UrlParseError(e) => {
                 error!("URL parse error: {}", e);
                 (StatusCode::BAD_REQUEST, e.to_string())
@@ -164,6 +165,17 @@ impl IntoResponse for ServerError {
                 error!("Mutation error: {}", e.to_string());
                 (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
             }
+            Self::NonPublicAddress(e) => {
+                warn!("Refusing to connect to a non-public address: {}", e);
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("Refusing to connect to a non-public address: {e}"),
+                )
+            }
+            Self::CacheError(e) => {
+                error!("Cache error: {}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            }
         };
         response.into_response()
     }
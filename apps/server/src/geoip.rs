@@ -0,0 +1,333 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    net::IpAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant, SystemTime},
+};
+
+use maxminddb::{MaxMindDBError, Reader};
+use serde::Deserialize;
+use tracing::{info, instrument, warn};
+
+use crate::config::GeoIpConfig;
+
+/// Where an IP address is, as far as the GeoIP databases know.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeoLocation {
+    /// ISO 3166-1 country code
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub asn: Option<u32>,
+    pub as_org: Option<String>,
+}
+
+/// The fields read from a record. City, country and ASN databases each fill in some of them,
+/// so one type reads all three.
+#[derive(Debug, Deserialize)]
+struct Record<'a> {
+    #[serde(borrow)]
+    country: Option<Country<'a>>,
+    #[serde(borrow)]
+    subdivisions: Option<Vec<Named<'a>>>,
+    #[serde(borrow)]
+    city: Option<Named<'a>>,
+    autonomous_system_number: Option<u32>,
+    autonomous_system_organization: Option<&'a str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Country<'a> {
+    iso_code: Option<&'a str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Named<'a> {
+    #[serde(borrow)]
+    names: Option<BTreeMap<&'a str, &'a str>>,
+}
+
+impl Named<'_> {
+    fn english(&self) -> Option<String> {
+        self.names
+            .as_ref()
+            .and_then(|names| names.get("en"))
+            .map(|name| name.to_string())
+    }
+}
+
+/// One `.mmdb` file, swapped out for a fresh copy whenever the file on disk changes.
+struct Database {
+    path: PathBuf,
+    reader: RwLock<Arc<Reader<Vec<u8>>>>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl fmt::Debug for Database {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Database")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Database {
+    fn open(path: &str) -> Result<Self, MaxMindDBError> {
+        let path = PathBuf::from(path);
+        let modified = std::fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let reader = Reader::open_readfile(&path)?;
+        info!(
+            "Loaded the {} GeoIP database from {}",
+            reader.metadata.database_type,
+            path.display()
+        );
+        Ok(Self {
+            path,
+            reader: RwLock::new(Arc::new(reader)),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    fn reader(&self) -> Option<Arc<Reader<Vec<u8>>>> {
+        self.reader.read().ok().map(|reader| reader.clone())
+    }
+
+    /// Reloads the file if its modification time changed. A file that fails to load, say
+    /// because it is still being copied into place, keeps the old copy in use and is tried
+    /// again on the next check.
+    async fn reload_if_changed(&self) {
+        let modified = tokio::fs::metadata(&self.path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_none() || self.modified.lock().is_ok_and(|seen| *seen == modified) {
+            return;
+        }
+        let path = self.path.clone();
+        let loaded = tokio::task::spawn_blocking(move || Reader::open_readfile(path)).await;
+        match loaded {
+            Ok(Ok(reader)) => {
+                if let Ok(mut current) = self.reader.write() {
+                    *current = Arc::new(reader);
+                }
+                if let Ok(mut seen) = self.modified.lock() {
+                    *seen = modified;
+                }
+                info!("Reloaded the GeoIP database from {}", self.path.display());
+            }
+            Ok(Err(e)) => warn!(
+                "Unable to reload the GeoIP database from {}: {e}",
+                self.path.display()
+            ),
+            Err(e) => warn!(
+                "Unable to reload the GeoIP database from {}: {e}",
+                self.path.display()
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct GeoIpInner {
+    databases: Vec<Database>,
+    reload_interval: Duration,
+    checked_at: Mutex<Instant>,
+}
+
+/// Offline IP geolocation from MaxMind-format databases. Cheap to clone; every clone sees
+/// reloads. Without any configured database every lookup comes back empty.
+#[derive(Debug, Clone, Default)]
+pub struct GeoIp {
+    inner: Option<Arc<GeoIpInner>>,
+}
+
+impl GeoIp {
+    /// Opens the configured databases, panicking if one can't be read so a bad path is caught
+    /// at startup.
+    #[instrument]
+    pub fn from_config(config: &GeoIpConfig) -> Self {
+        let databases: Vec<Database> = [&config.database, &config.asn_database]
+            .into_iter()
+            .flatten()
+            .map(|path| Database::open(path).expect("Unable to open the GeoIP database"))
+            .collect();
+        if databases.is_empty() {
+            return Self::default();
+        }
+        Self {
+            inner: Some(Arc::new(GeoIpInner {
+                databases,
+                reload_interval: config.reload_interval,
+                checked_at: Mutex::new(Instant::now()),
+            })),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Looks `ip` up in every database, earlier databases winning where they overlap.
+    pub fn lookup(&self, ip: IpAddr) -> GeoLocation {
+        let mut location = GeoLocation::default();
+        let Some(inner) = &self.inner else {
+            return location;
+        };
+        for database in &inner.databases {
+            let Some(reader) = database.reader() else {
+                continue;
+            };
+            let record: Record = match reader.lookup(ip) {
+                Ok(record) => record,
+                Err(MaxMindDBError::AddressNotFoundError(_)) => continue,
+                Err(e) => {
+                    warn!("Unable to look up {ip} in {}: {e}", database.path.display());
+                    continue;
+                }
+            };
+            location.country = location.country.or_else(|| {
+                record
+                    .country
+                    .and_then(|country| country.iso_code)
+                    .map(str::to_string)
+            });
+            location.region = location.region.or_else(|| {
+                record
+                    .subdivisions
+                    .as_ref()
+                    .and_then(|subdivisions| subdivisions.first())
+                    .and_then(Named::english)
+            });
+            location.city = location
+                .city
+                .or_else(|| record.city.as_ref().and_then(Named::english));
+            location.asn = location.asn.or(record.autonomous_system_number);
+            location.as_org = location
+                .as_org
+                .or_else(|| record.autonomous_system_organization.map(str::to_string));
+        }
+        location
+    }
+
+    /// Reloads any database file that changed on disk, checking at most once per
+    /// `reload_interval`.
+    pub async fn reload_if_changed(&self) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        {
+            let Ok(mut checked_at) = inner.checked_at.lock() else {
+                return;
+            };
+            if checked_at.elapsed() < inner.reload_interval {
+                return;
+            }
+            *checked_at = Instant::now();
+        }
+        for database in &inner.databases {
+            database.reload_if_changed().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::UNIX_EPOCH};
+
+    use super::*;
+
+    const CITY: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/testdata/GeoIP2-City-Test.mmdb"
+    );
+    const ASN: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/testdata/GeoLite2-ASN-Test.mmdb"
+    );
+
+    fn geoip(database: Option<&str>, asn_database: Option<&str>) -> GeoIp {
+        GeoIp::from_config(&GeoIpConfig {
+            database: database.map(str::to_string),
+            asn_database: asn_database.map(str::to_string),
+            reload_interval: Duration::ZERO,
+        })
+    }
+
+    #[test]
+    fn lookups_merge_city_and_asn_data() {
+        let geoip = geoip(Some(CITY), Some(ASN));
+        assert_eq!(
+            geoip.lookup("81.2.69.160".parse().unwrap()),
+            GeoLocation {
+                country: Some("GB".to_string()),
+                region: Some("England".to_string()),
+                city: Some("London".to_string()),
+                asn: Some(20712),
+                as_org: Some("Andrews & Arnold".to_string()),
+            }
+        );
+        assert_eq!(
+            geoip.lookup("2.125.160.216".parse().unwrap()),
+            GeoLocation {
+                country: Some("GB".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            geoip.lookup("1.128.0.1".parse().unwrap()),
+            GeoLocation {
+                asn: Some(1221),
+                as_org: Some("Telstra".to_string()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_addresses_come_back_empty() {
+        let geoip = geoip(Some(CITY), Some(ASN));
+        assert_eq!(
+            geoip.lookup("10.0.0.1".parse().unwrap()),
+            GeoLocation::default()
+        );
+        assert_eq!(
+            geoip.lookup("2001:db8::1".parse().unwrap()),
+            GeoLocation::default()
+        );
+        assert!(!GeoIp::default().is_enabled());
+        assert_eq!(
+            GeoIp::default().lookup("81.2.69.160".parse().unwrap()),
+            GeoLocation::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn changed_files_are_reloaded() {
+        let path = std::env::temp_dir().join(format!("geoip-{}.mmdb", uuid::Uuid::new_v4()));
+        fs::copy(CITY, &path).unwrap();
+        let geoip = geoip(path.to_str(), None);
+        let ip = "81.2.69.160".parse().unwrap();
+        assert_eq!(geoip.lookup(ip).city.as_deref(), Some("London"));
+
+        // Nothing changed on disk, so the reader stays as it is
+        geoip.reload_if_changed().await;
+        assert_eq!(geoip.lookup(ip).city.as_deref(), Some("London"));
+
+        fs::copy(ASN, &path).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(1_000_000))
+            .unwrap();
+        geoip.reload_if_changed().await;
+        let location = geoip.lookup(ip);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(location.city, None);
+        assert_eq!(location.asn, Some(20712));
+    }
+}
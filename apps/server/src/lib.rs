@@ -1,15 +1,24 @@
 pub mod actor;
+pub mod admin;
 pub mod api;
+pub mod cache;
 pub mod config;
 pub mod error;
+pub mod geoip;
 pub mod logger;
 pub mod state;
+#[cfg(test)]
+mod testing;
 pub mod urls;
 pub mod user;
+pub mod user_agent;
 pub mod utils;
+pub mod visitors;
+pub mod webhooks;
 
 use std::{
     env::current_dir,
+    future::{IntoFuture, pending},
     net::SocketAddr,
     str::FromStr,
     sync::{
@@ -29,6 +38,7 @@ use axum_client_ip::ClientIpSource;
 pub use config::{GetConfig, ServerConfig};
 use logger::{init_subscriber, telemetry};
 use state::ServerState;
+use tokio::{net::TcpListener, signal, sync::oneshot, time::sleep};
 use tower::{
     ServiceBuilder,
     layer::util::{Identity, Stack},
@@ -40,7 +50,7 @@ use tower_http::{
     services::fs::ServeDir,
     trace::TraceLayer,
 };
-use tracing::info;
+use tracing::{info, warn};
 
 use self::logger::MicroUrlMakeSpan;
 
@@ -65,17 +75,73 @@ impl MakeRequestId for MicroUrlMakeRequestId {
 #[tracing::instrument]
 pub async fn run(config: ServerConfig) {
     init_subscriber();
-    let app = init_router(config.clone(), None).await;
+    let state = ServerState::new(config.clone()).await;
+    let app = init_router(config.clone(), Some(state.clone())).await;
     let addr = SocketAddr::from_str(config.internal_url.as_str())
-        .unwrap_or_else(|_| panic!("Unable to parse socket {}", &config.internal_url.as_str()));
+        .unwrap_or_else(|_| panic!("Unable to parse socket {}", config.internal_url.as_str()));
     info!("Listening on {}", addr);
-    let listen = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(
-        listen,
+    let listen = TcpListener::bind(&addr).await.unwrap();
+    serve(listen, app, state).await.unwrap();
+}
+
+/// Serves `app` until SIGINT or SIGTERM, then gives open connections and the actor pool up to
+/// `SHUTDOWN_DRAIN_TIMEOUT` each to finish.
+#[tracing::instrument(skip(app))]
+pub async fn serve(listener: TcpListener, app: Router, state: ServerState) -> std::io::Result<()> {
+    let drain_timeout = state.config.actors.drain_timeout;
+    let (signalled, on_signal) = oneshot::channel();
+    let pool = state.pool.clone();
+    let server = axum::serve(
+        listener,
         app.into_make_service_with_connect_info::<SocketAddr>(),
     )
-    .await
-    .unwrap();
+    .with_graceful_shutdown(async move {
+        shutdown_signal().await;
+        // Live click streams never finish on their own
+        pool.close_live_streams();
+        let _ = signalled.send(());
+    });
+    tokio::select! {
+        served = server.into_future() => served?,
+        _ = async {
+            match on_signal.await {
+                Ok(()) => sleep(drain_timeout).await,
+                Err(_) => pending().await,
+            }
+        } => warn!("Timed out waiting for open connections to close"),
+    }
+
+    let report = state.pool.drain(drain_timeout).await;
+    if report.timed_out {
+        warn!("Timed out draining the actor pool");
+    }
+    info!(
+        "Shut down after flushing {} views; {} clicks were dropped and {} background messages \
+         were left unprocessed",
+        report.flushed, report.dropped, report.unprocessed
+    );
+    Ok(())
+}
+
+/// Resolves once the process receives SIGINT (Ctrl-C) or SIGTERM.
+pub async fn shutdown_signal() {
+    let interrupt = async {
+        signal::ctrl_c().await.expect("Unable to listen for SIGINT");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Unable to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => info!("Received SIGINT, shutting down"),
+        _ = terminate => info!("Received SIGTERM, shutting down"),
+    }
 }
 
 #[tracing::instrument]
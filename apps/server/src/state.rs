@@ -1,12 +1,8 @@
-use std::{
-    num::NonZeroUsize,
-    sync::{Arc, Mutex, MutexGuard},
-};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
 
 use axum::extract::FromRef;
 use axum_extra::extract::cookie::Key;
-use entity::short_link::Entity as ShortLink;
-use lru::LruCache;
+use entity::short_link::{self, Entity as ShortLink};
 use migration::{Migrator, MigratorTrait};
 use openidconnect::{
     ClientId, ClientSecret, IssuerUrl, RedirectUrl,
@@ -14,10 +10,19 @@ use openidconnect::{
 };
 use reqwest::{ClientBuilder, redirect::Policy, tls::Certificate};
 use sea_orm::{Database, DatabaseConnection, entity::*, query::*};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
 use url::Url;
 
 use super::{config::ServerConfig, utils::OidcClient};
-use crate::{actor::ActorPool, error::ArcMutexError};
+use crate::{
+    actor::ActorPool,
+    cache::{CacheError, CachedLink, LinkCache, ReplicaEvent},
+    error::{ArcMutexError, ServerError},
+    geoip::GeoIp,
+    urls::domains::DomainRules,
+    utils::public_client,
+};
 
 pub const CHARS: [char; 64] = [
     '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I',
@@ -29,14 +34,19 @@ pub const CHARS: [char; 64] = [
 #[derive(FromRef, Debug, Clone)]
 pub struct ServerState {
     pub conn: DatabaseConnection,
-    cache: Arc<Mutex<LruCache<String, String>>>,
+    cache: LinkCache,
     pub url: Url,
     counter: Arc<Mutex<usize>>,
     pub config: ServerConfig,
     pub oidc_client: OidcClient,
     pub client: reqwest::Client,
+    /// The client for requests made on behalf of users, which only reaches public addresses
+    #[from_ref(skip)]
+    pub public_client: reqwest::Client,
     pub key: Key,
     pub pool: ActorPool,
+    file_domain_rules: Arc<DomainRules>,
+    domain_rules: Arc<RwLock<DomainRules>>,
 }
 
 impl ServerState {
@@ -67,7 +77,9 @@ impl ServerState {
 
         let counter = Arc::new(Mutex::new(counter));
 
-        let cache = Arc::new(Mutex::new(LruCache::new(NonZeroUsize::new(1000).unwrap())));
+        let cache = LinkCache::from_config(&config.cache)
+            .await
+            .expect("Unable to set up the redirect cache");
 
         let url = Url::parse(&config.external_url).unwrap();
 
@@ -114,53 +126,158 @@ impl ServerState {
 
         let key = Key::generate();
 
-        let pool = ActorPool::new(&config.actors, conn.clone());
+        let geoip = GeoIp::from_config(&config.geoip);
+        let pool = ActorPool::new(&config.actors, conn.clone(), cache.clone(), geoip);
 
-        Self {
+        let file_domain_rules = DomainRules::from_config(&config.links);
+        let domain_rules = Arc::new(RwLock::new(
+            file_domain_rules
+                .with_stored(&conn)
+                .await
+                .expect("Unable to load the domain rules"),
+        ));
+        let file_domain_rules = Arc::new(file_domain_rules);
+
+        let state = Self {
             conn,
             cache,
             url,
             counter,
             oidc_client,
             client,
+            public_client: public_client(),
             key,
             pool,
             config,
+            file_domain_rules,
+            domain_rules,
+        };
+        if let Some(events) = state.cache.subscribe() {
+            tokio::spawn(follow_replicas(state.clone(), events));
+        }
+        state
+    }
+
+    /// A state for tests, using `cache` and an OIDC client that is never called.
+    #[cfg(test)]
+    pub(crate) async fn for_tests(conn: DatabaseConnection, cache: LinkCache) -> Self {
+        use openidconnect::{
+            AuthUrl, EmptyAdditionalProviderMetadata, JsonWebKeySet, JsonWebKeySetUrl,
+            core::{CoreJwsSigningAlgorithm, CoreResponseType, CoreSubjectIdentifierType},
+        };
+
+        let config = ServerConfig::default();
+        let url = Url::parse(&config.external_url).unwrap();
+        let provider_metadata = CoreProviderMetadata::new(
+            IssuerUrl::from_url(url.clone()),
+            AuthUrl::from_url(url.join("/authorize").unwrap()),
+            JsonWebKeySetUrl::from_url(url.join("/jwks").unwrap()),
+            vec![openidconnect::ResponseTypes::new(vec![
+                CoreResponseType::Code,
+            ])],
+            vec![CoreSubjectIdentifierType::Public],
+            vec![CoreJwsSigningAlgorithm::RsaSsaPkcs1V15Sha256],
+            EmptyAdditionalProviderMetadata {},
+        )
+        .set_jwks(JsonWebKeySet::default());
+        let oidc_client = CoreClient::from_provider_metadata(
+            provider_metadata,
+            ClientId::new("test".to_string()),
+            None,
+        );
+        let pool = ActorPool::new(
+            &config.actors,
+            conn.clone(),
+            cache.clone(),
+            GeoIp::from_config(&config.geoip),
+        );
+        let file_domain_rules = DomainRules::from_config(&config.links);
+        let domain_rules = Arc::new(RwLock::new(
+            file_domain_rules.with_stored(&conn).await.unwrap(),
+        ));
+        Self {
+            conn,
+            cache,
+            url,
+            counter: Arc::new(Mutex::new(0)),
+            oidc_client,
+            client: reqwest::Client::new(),
+            public_client: public_client(),
+            key: Key::generate(),
+            pool,
+            config,
+            file_domain_rules: Arc::new(file_domain_rules),
+            domain_rules,
+        }
+    }
+
+    #[tracing::instrument]
+    pub fn domain_allowed(&self, host: &str) -> Result<bool, ArcMutexError> {
+        let rules = self.domain_rules.read().map_err(|e| ArcMutexError {
+            error: format!("Unable to acquire a read lock on the domain rules. Got error: {e}"),
+        })?;
+        Ok(rules.allows(host))
+    }
+
+    /// Re-checks a stored destination against the current domain rules at redirect time. A
+    /// destination that no longer parses isn't redirected to.
+    #[tracing::instrument]
+    pub fn redirect_allowed(&self, target: &str) -> Result<bool, ArcMutexError> {
+        match Url::parse(target) {
+            Ok(url) => match url.host_str() {
+                Some(host) => self.domain_allowed(host),
+                None => Ok(false),
+            },
+            Err(_) => Ok(false),
         }
     }
 
     #[tracing::instrument]
-    pub fn put(&self, key: String, val: String) -> Result<Option<String>, ArcMutexError> {
-        let mut cache: MutexGuard<LruCache<String, String>> =
-            self.cache.lock().map_err(|e| ArcMutexError {
-                error: format!(
-                    "Unable to acquire lock on the mutex with key {key} and value {val}. Got \
-                     error: {e}",
-                ),
-            })?;
-        Ok((*cache).put(key, val))
+    pub fn domain_rules_summary(&self) -> Result<(bool, usize), ArcMutexError> {
+        let rules = self.domain_rules.read().map_err(|e| ArcMutexError {
+            error: format!("Unable to acquire a read lock on the domain rules. Got error: {e}"),
+        })?;
+        Ok((rules.allow_only(), rules.static_rules()))
+    }
+
+    /// Reloads the domain rules after they were changed here, and has every other replica
+    /// reload them too.
+    #[tracing::instrument]
+    pub async fn reload_domain_rules(&self) -> Result<(), ServerError> {
+        self.load_domain_rules().await?;
+        self.cache
+            .publish(&ReplicaEvent::DomainRulesChanged)
+            .await?;
+        Ok(())
+    }
+
+    async fn load_domain_rules(&self) -> Result<(), ServerError> {
+        let reloaded = self.file_domain_rules.with_stored(&self.conn).await?;
+        let mut rules = self.domain_rules.write().map_err(|e| ArcMutexError {
+            error: format!("Unable to acquire a write lock on the domain rules. Got error: {e}"),
+        })?;
+        *rules = reloaded;
+        Ok(())
     }
 
     #[tracing::instrument]
-    pub fn get(&self, key: &str) -> Result<Option<String>, ArcMutexError> {
-        let mut cache: MutexGuard<LruCache<String, String>> =
-            self.cache.lock().map_err(|e| ArcMutexError {
-                error: format!(
-                    "Unable to acquire lock on the mutex with key {key}. Got error: {e}",
-                ),
-            })?;
-        Ok((*cache).get(key).cloned())
+    pub async fn put(&self, link: &short_link::Model) -> Result<(), CacheError> {
+        self.cache.put(link).await
     }
 
     #[tracing::instrument]
-    pub fn pop(&self, key: &str) -> Result<Option<(String, String)>, ArcMutexError> {
-        let mut cache: MutexGuard<LruCache<String, String>> =
-            self.cache.lock().map_err(|e| ArcMutexError {
-                error: format!(
-                    "Unable to acquire lock on the mutex with key {key}. Got error: {e}",
-                ),
-            })?;
-        Ok((*cache).pop_entry(key))
+    pub async fn put_missing(&self, key: &str) -> Result<(), CacheError> {
+        self.cache.put_missing(key).await
+    }
+
+    #[tracing::instrument]
+    pub async fn get(&self, key: &str) -> Result<Option<CachedLink>, CacheError> {
+        self.cache.get(key).await
+    }
+
+    #[tracing::instrument]
+    pub async fn invalidate(&self, keys: &[&str]) -> Result<usize, CacheError> {
+        self.cache.invalidate(keys).await
     }
 
     #[tracing::instrument]
@@ -183,3 +300,20 @@ impl ServerState {
         Ok(b64)
     }
 }
+
+/// Acts on the changes other replicas make, for as long as the cache is shared.
+async fn follow_replicas(state: ServerState, mut events: broadcast::Receiver<ReplicaEvent>) {
+    loop {
+        match events.recv().await {
+            // Having missed some events, the rules may have changed in one of them
+            Ok(ReplicaEvent::DomainRulesChanged) | Err(RecvError::Lagged(_)) => {
+                if let Err(e) = state.load_domain_rules().await {
+                    warn!("Unable to reload the domain rules: {e}");
+                }
+            }
+            // Clicks are passed on to live subscribers by the actor pool
+            Ok(ReplicaEvent::Clicks { .. }) => {}
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Fixtures for the tests that run against a migrated Postgres database named by
+//! `TEST_DATABASE_URL`. Those tests are ignored by default, run them with `--include-ignored`.
+
+use chrono::Utc;
+use entity::{short_link, user};
+use sea_orm::{DatabaseConnection, entity::*};
+use uuid::Uuid;
+
+pub(crate) async fn connect() -> DatabaseConnection {
+    let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL isn't set");
+    sea_orm::Database::connect(url).await.unwrap()
+}
+
+pub(crate) async fn create_user(conn: &DatabaseConnection) -> Uuid {
+    let id = Uuid::new_v4();
+    let now = Utc::now().naive_utc();
+    user::ActiveModel {
+        user_id: ActiveValue::Set(id),
+        name: ActiveValue::Set("Test user".to_string()),
+        email: ActiveValue::Set(format!("{id}@example.com")),
+        created_at: ActiveValue::Set(now),
+        updated_at: ActiveValue::Set(now),
+    }
+    .insert(conn)
+    .await
+    .unwrap();
+    id
+}
+
+/// An enabled link to `https://example.com` with a slug of its own, for the test to adjust
+/// before saving it with [`create_link`].
+pub(crate) fn link(user: Option<Uuid>) -> short_link::Model {
+    let id = Uuid::new_v4().simple().to_string();
+    let now = Utc::now().naive_utc();
+    short_link::Model {
+        short_url: format!("https://sho.rt/{id}"),
+        id,
+        original_url: "https://example.com/".to_string(),
+        user_id: user,
+        expiry_date: None,
+        created_at: now,
+        updated_at: now,
+        enabled: true,
+        disabled_reason: None,
+        deleted_at: None,
+        title: None,
+        notes: None,
+        description: None,
+        favicon: None,
+        og_image: None,
+        metadata_fetched_at: None,
+        max_clicks: None,
+        clicks_remaining: None,
+    }
+}
+
+pub(crate) async fn create_link(
+    conn: &DatabaseConnection,
+    link: short_link::Model,
+) -> short_link::Model {
+    link.into_active_model().insert(conn).await.unwrap()
+}
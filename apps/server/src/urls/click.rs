@@ -0,0 +1,78 @@
+use std::{convert::Infallible, net::IpAddr};
+
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderMap, HeaderName, Method, header, request::Parts},
+};
+#[cfg(feature = "ips")]
+use axum_client_ip::ClientIp;
+
+use crate::user_agent::parse_user_agent;
+
+/// What is known about the visitor behind a redirect.
+///
+/// Every field is optional; the `ips` and `headers` features decide what gets captured, while
+/// the redirect handler and view recording stay the same either way.
+#[derive(Debug, Clone, Default)]
+pub struct ClickContext {
+    pub ip: Option<IpAddr>,
+    pub headers: Option<HeaderMap>,
+    pub referrer: Option<String>,
+    pub user_agent: Option<String>,
+    /// The request was a `HEAD`, which link checkers and unfurlers send but browsers don't
+    pub head: bool,
+    /// The browser sent `DNT: 1` or `Sec-GPC: 1`
+    pub do_not_track: bool,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ClickContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        #[cfg(feature = "ips")]
+        let ip = match ClientIp::from_request_parts(parts, state).await {
+            Ok(ClientIp(ip)) => Some(ip),
+            Err(e) => {
+                tracing::warn!("Unable to determine the client IP: {e}");
+                None
+            }
+        };
+        #[cfg(not(feature = "ips"))]
+        let ip = {
+            let _ = state;
+            None
+        };
+
+        #[cfg(feature = "headers")]
+        let headers = Some(parts.headers.clone());
+        #[cfg(not(feature = "headers"))]
+        let headers = None;
+
+        let header = |name: header::HeaderName| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        };
+
+        Ok(Self {
+            ip,
+            headers,
+            referrer: header(header::REFERER),
+            user_agent: header(header::USER_AGENT),
+            head: parts.method == Method::HEAD,
+            do_not_track: ["dnt", "sec-gpc"]
+                .into_iter()
+                .any(|name| header(HeaderName::from_static(name)).as_deref() == Some("1")),
+        })
+    }
+}
+
+impl ClickContext {
+    /// Whether the request came from a bot rather than a person following the link. Browsers
+    /// always follow a redirect with a `GET`.
+    pub fn is_bot(&self) -> bool {
+        self.head || parse_user_agent(self.user_agent.as_deref()).bot
+    }
+}
@@ -0,0 +1,279 @@
+use std::{collections::HashSet, fmt, fs::read_to_string, net::IpAddr};
+
+use entity::{domain_rule, sea_orm_active_enums::DomainAction};
+use sea_orm::{ConnectionTrait, DbErr, EntityTrait};
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument, warn};
+use url::Host;
+
+use crate::config::LinkConfig;
+
+/// The on-disk domain rules file, pointed at by `DOMAIN_RULES_FILE`.
+///
+/// ```toml
+/// allow_only = false
+/// allow = ["*.corp.example.com"]
+/// deny = ["phish.example", "*.evil.example"]
+/// blocklists = ["/etc/micro-url/hosts.txt"]
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DomainRulesFile {
+    pub allow_only: bool,
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    pub blocklists: Vec<String>,
+}
+
+/// Destination domain rules, merged from the rules file and the `domain_rule` table.
+///
+/// A pattern is either an exact host (`example.com`) or a wildcard (`*.example.com`) that
+/// matches every subdomain but not the apex. Deny rules always win; in allow-only mode a host
+/// must also match an allow rule.
+#[derive(Clone, Default)]
+pub struct DomainRules {
+    allow_only: bool,
+    allow: HashSet<String>,
+    deny: HashSet<String>,
+    static_rules: usize,
+}
+
+// Blocklists can hold hundreds of thousands of hosts, so keep them out of traces
+impl fmt::Debug for DomainRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DomainRules")
+            .field("allow_only", &self.allow_only)
+            .field("allow", &self.allow.len())
+            .field("deny", &self.deny.len())
+            .field("static_rules", &self.static_rules)
+            .finish()
+    }
+}
+
+impl DomainRules {
+    /// Loads the rules file and any blocklists it references.
+    #[instrument]
+    pub fn from_config(config: &LinkConfig) -> Self {
+        let mut rules = Self::default();
+        let Some(path) = config.domain_rules_file.as_deref() else {
+            return rules;
+        };
+        let content = read_to_string(path).expect("Unable to read the domain rules file");
+        let file: DomainRulesFile =
+            toml::from_str(&content).expect("Unable to parse the domain rules file");
+        rules.allow_only = file.allow_only;
+        for pattern in &file.allow {
+            rules.insert(pattern, DomainAction::Allow);
+        }
+        for pattern in &file.deny {
+            rules.insert(pattern, DomainAction::Deny);
+        }
+        for blocklist in &file.blocklists {
+            let content = read_to_string(blocklist).expect("Unable to read the blocklist file");
+            for host in parse_hosts(&content) {
+                rules.insert(&host, DomainAction::Deny);
+            }
+        }
+        rules.static_rules = rules.allow.len() + rules.deny.len();
+        info!(
+            "Loaded {} domain rules from {path} (allow only: {})",
+            rules.static_rules, rules.allow_only
+        );
+        rules
+    }
+
+    /// Returns a copy of the file rules with the rules stored in the database added.
+    #[instrument(skip(conn))]
+    pub async fn with_stored<C: ConnectionTrait>(&self, conn: &C) -> Result<Self, DbErr> {
+        let mut rules = self.clone();
+        for rule in domain_rule::Entity::find().all(conn).await? {
+            rules.insert(&rule.pattern, rule.action);
+        }
+        Ok(rules)
+    }
+
+    fn insert(&mut self, pattern: &str, action: DomainAction) {
+        let Some(pattern) = normalize_pattern(pattern) else {
+            warn!("Ignoring invalid domain rule {pattern:?}");
+            return;
+        };
+        match action {
+            DomainAction::Allow => self.allow.insert(pattern),
+            DomainAction::Deny => self.deny.insert(pattern),
+        };
+    }
+
+    fn matches(set: &HashSet<String>, host: &str) -> bool {
+        if set.contains(host) {
+            return true;
+        }
+        let mut rest = host;
+        while let Some((_, parent)) = rest.split_once('.') {
+            if set.contains(&format!("*.{parent}")) {
+                return true;
+            }
+            rest = parent;
+        }
+        false
+    }
+
+    /// Returns true when links may point at `host`.
+    pub fn allows(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if Self::matches(&self.deny, &host) {
+            return false;
+        }
+        !self.allow_only || Self::matches(&self.allow, &host)
+    }
+
+    pub fn allow_only(&self) -> bool {
+        self.allow_only
+    }
+
+    pub fn static_rules(&self) -> usize {
+        self.static_rules
+    }
+}
+
+/// Lower-cases a rule and checks it is a host name, IP address or `*.` wildcard.
+pub fn normalize_pattern(pattern: &str) -> Option<String> {
+    let pattern = pattern.trim().trim_end_matches('.').to_ascii_lowercase();
+    let (wildcard, host) = match pattern.strip_prefix("*.") {
+        Some(host) => (true, host),
+        None => (false, pattern.as_str()),
+    };
+    match Host::parse(host).ok()? {
+        Host::Domain(domain) if domain.contains('.') || !wildcard => Some(if wildcard {
+            format!("*.{domain}")
+        } else {
+            domain
+        }),
+        Host::Ipv4(_) | Host::Ipv6(_) if !wildcard => Some(host.to_string()),
+        _ => None,
+    }
+}
+
+/// Extracts host names from a hosts-format blocklist (`0.0.0.0 evil.example`), also accepting
+/// files with one bare domain per line.
+pub fn parse_hosts(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.split('#').next())
+        .flat_map(|line| {
+            let mut fields = line.split_whitespace().peekable();
+            if fields
+                .peek()
+                .is_some_and(|field| field.parse::<IpAddr>().is_ok())
+            {
+                fields.next();
+            }
+            fields
+        })
+        .filter(|host| {
+            !matches!(
+                *host,
+                "localhost" | "localhost.localdomain" | "local" | "broadcasthost" | "0.0.0.0"
+            )
+        })
+        .filter_map(normalize_pattern)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(allow_only: bool, allow: &[&str], deny: &[&str]) -> DomainRules {
+        let mut rules = DomainRules {
+            allow_only,
+            ..Default::default()
+        };
+        for pattern in allow {
+            rules.insert(pattern, DomainAction::Allow);
+        }
+        for pattern in deny {
+            rules.insert(pattern, DomainAction::Deny);
+        }
+        rules
+    }
+
+    #[test]
+    fn wildcards_match_subdomains_but_not_the_apex() {
+        let rules = rules(false, &[], &["*.evil.example", "phish.example"]);
+        assert!(!rules.allows("a.evil.example"));
+        assert!(!rules.allows("a.b.evil.example"));
+        assert!(rules.allows("evil.example"));
+        assert!(!rules.allows("phish.example"));
+        assert!(rules.allows("www.phish.example"));
+        assert!(rules.allows("notevil.example"));
+    }
+
+    #[test]
+    fn hosts_are_matched_case_insensitively_without_the_trailing_dot() {
+        let rules = rules(false, &[], &["Phish.Example."]);
+        assert!(!rules.allows("PHISH.example"));
+        assert!(!rules.allows("phish.example."));
+    }
+
+    #[test]
+    fn deny_rules_win_and_allow_only_needs_an_allow_rule() {
+        let rules = rules(
+            true,
+            &["*.corp.example", "partner.example"],
+            &["legacy.corp.example"],
+        );
+        assert!(rules.allows("wiki.corp.example"));
+        assert!(rules.allows("partner.example"));
+        assert!(!rules.allows("legacy.corp.example"));
+        assert!(!rules.allows("corp.example"));
+        assert!(!rules.allows("example.org"));
+
+        let open = DomainRules::default();
+        assert!(open.allows("example.org"));
+    }
+
+    #[test]
+    fn invalid_patterns_are_ignored() {
+        assert_eq!(
+            normalize_pattern(" Example.COM. "),
+            Some("example.com".into())
+        );
+        assert_eq!(
+            normalize_pattern("*.Example.com"),
+            Some("*.example.com".into())
+        );
+        assert_eq!(normalize_pattern("192.0.2.1"), Some("192.0.2.1".into()));
+        assert_eq!(normalize_pattern("*.com"), None);
+        assert_eq!(normalize_pattern("*.192.0.2.1"), None);
+        assert_eq!(normalize_pattern("exa mple.com"), None);
+        assert_eq!(normalize_pattern(""), None);
+
+        let rules = rules(false, &[], &["*.com", "bad host"]);
+        assert!(rules.allows("example.com"));
+    }
+
+    #[test]
+    fn blocklists_read_hosts_files_and_bare_domains() {
+        let content = "\
+# A hosts file
+127.0.0.1 localhost
+::1 localhost
+0.0.0.0 0.0.0.0
+0.0.0.0 tracker.example ads.example # two on one line
+0.0.0.0\tMalware.Example.
+
+bare.example
+   # indented comment
+0.0.0.0 bad/host
+";
+        assert_eq!(
+            parse_hosts(content),
+            [
+                "tracker.example",
+                "ads.example",
+                "malware.example",
+                "bare.example",
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+
+use axum::response::{
+    IntoResponse, Response,
+    sse::{Event, KeepAlive, Sse},
+};
+use futures_util::{
+    StreamExt,
+    stream::{self, BoxStream},
+};
+use utoipa::{
+    IntoResponses,
+    openapi::{ContentBuilder, Ref, RefOr, ResponseBuilder},
+};
+use uuid::Uuid;
+
+use crate::actor::{ClickEvent, ClickSubscription};
+
+/// A response streaming recorded clicks as server-sent events.
+pub struct LiveClicks(Sse<BoxStream<'static, Result<Event, axum::Error>>>);
+
+impl IntoResponse for LiveClicks {
+    fn into_response(self) -> Response {
+        self.0.into_response()
+    }
+}
+
+impl IntoResponses for LiveClicks {
+    fn responses() -> BTreeMap<String, RefOr<utoipa::openapi::response::Response>> {
+        let response = ResponseBuilder::new()
+            .description(
+                "A `click` event carrying a `ClickEvent` for every matching click any replica \
+                 records, and a `lagged` event with how many were skipped when the client falls \
+                 behind",
+            )
+            .content(
+                "text/event-stream",
+                ContentBuilder::new()
+                    .schema(Some(Ref::from_schema_name("ClickEvent")))
+                    .build(),
+            )
+            .build();
+        BTreeMap::from([("200".to_string(), response.into())])
+    }
+}
+
+/// Which recorded clicks a live stream passes on.
+#[derive(Debug, Clone)]
+pub enum ClickFilter {
+    /// Clicks on one short link
+    Link(String),
+    /// Clicks on any link the user owns
+    Owner(Uuid),
+}
+
+impl ClickFilter {
+    fn matches(&self, click: &ClickEvent) -> bool {
+        match self {
+            ClickFilter::Link(id) => click.id == *id,
+            ClickFilter::Owner(user) => click.owner == Some(*user),
+        }
+    }
+}
+
+/// Streams the clicks `filter` lets through as `click` events. A subscriber that falls too far
+/// behind gets a `lagged` event carrying how many clicks it missed, and the stream ends when
+/// the server shuts down.
+pub fn live_clicks(clicks: ClickSubscription, filter: ClickFilter) -> LiveClicks {
+    let events = stream::unfold((clicks, filter), |(mut clicks, filter)| async move {
+        loop {
+            let event = match clicks.next().await? {
+                Ok(click) if filter.matches(&click) => {
+                    Event::default().event("click").json_data(&click)
+                }
+                Ok(_) => continue,
+                Err(skipped) => Ok(Event::default().event("lagged").data(skipped.to_string())),
+            };
+            return Some((event, (clicks, filter)));
+        }
+    });
+    LiveClicks(Sse::new(events.boxed()).keep_alive(KeepAlive::default()))
+}
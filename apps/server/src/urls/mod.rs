@@ -1,5 +1,11 @@
+pub mod click;
+pub mod domains;
+pub mod live;
+pub mod resolve;
 pub mod routes;
+pub mod stats;
 pub mod structs;
+pub mod validation;
 
 use utoipa_axum::{router::OpenApiRouter, routes};
 
@@ -12,8 +18,13 @@ pub fn url_router(state: ServerState) -> OpenApiRouter {
     OpenApiRouter::new()
         .routes(routes!(routes::new_url))
         .routes(routes!(routes::delete_url))
+        .routes(routes!(routes::restore_url))
+        .routes(routes!(routes::disable_url))
+        .routes(routes!(routes::enable_url))
         .routes(routes!(routes::update_url))
         .routes(routes!(routes::url_info))
+        .routes(routes!(routes::url_stats))
+        .routes(routes!(routes::url_live))
         .routes(routes!(routes::get_url))
         .routes(routes!(routes::qr_code))
         .with_state(state)
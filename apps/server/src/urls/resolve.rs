@@ -0,0 +1,303 @@
+use chrono::Utc;
+use entity::short_link;
+use sea_orm::{DbErr, entity::*, query::*, sea_query::Expr};
+use thiserror::Error;
+
+use crate::{
+    cache::{CacheError, CachedLink},
+    error::ArcMutexError,
+    state::ServerState,
+};
+
+/// Why a slug cannot be redirected.
+#[derive(Error, Debug)]
+pub enum RedirectError {
+    #[error("URL not found")]
+    NotFound,
+    #[error("{}", .0.as_deref().unwrap_or("This link has been disabled"))]
+    Disabled(Option<String>),
+    #[error("This link has expired")]
+    Expired,
+    #[error("This link has reached its click limit")]
+    ClickLimitReached,
+    #[error("This link points to a blocked domain")]
+    Blocked,
+    #[error("Database error: {0}")]
+    DbErr(#[from] DbErr),
+    #[error("Cache error: {0}")]
+    CacheError(#[from] CacheError),
+    #[error(transparent)]
+    LockError(#[from] ArcMutexError),
+}
+
+/// A destination that may be redirected to.
+#[derive(Debug, Clone)]
+pub struct ResolvedRedirect {
+    pub url: String,
+    /// Whether the destination came from the redirect cache.
+    pub cached: bool,
+}
+
+/// Resolves a slug to its destination, enforcing deletion, disabled state, expiry, click
+/// limits and domain rules the same way whether or not the slug is cached. A `counted` redirect
+/// uses up one of the link's clicks; bots and `HEAD` requests are only turned away once they
+/// have all been used.
+#[tracing::instrument(skip(state))]
+pub async fn resolve_redirect(
+    state: &ServerState,
+    id: &str,
+    counted: bool,
+) -> Result<ResolvedRedirect, RedirectError> {
+    // Deleted and disabled links are evicted when they change and expired entries are never
+    // returned, so a hit only has to check the rules that can change without touching the link
+    if let Some(cached) = state.get(id).await? {
+        let CachedLink::Redirect {
+            url, max_clicks, ..
+        } = cached
+        else {
+            return Err(RedirectError::NotFound);
+        };
+        check_destination(state, id, &url, max_clicks, counted).await?;
+        return Ok(ResolvedRedirect { url, cached: true });
+    }
+
+    let Some(short) = short_link::Entity::find_by_id(id)
+        .filter(short_link::Column::DeletedAt.is_null())
+        .one(&state.conn)
+        .await?
+    else {
+        state.put_missing(id).await?;
+        return Err(RedirectError::NotFound);
+    };
+    if !short.enabled {
+        return Err(RedirectError::Disabled(short.disabled_reason));
+    }
+    if short
+        .expiry_date
+        .is_some_and(|expiry| expiry <= Utc::now().naive_utc())
+    {
+        return Err(RedirectError::Expired);
+    }
+    check_destination(state, id, &short.original_url, short.max_clicks, counted).await?;
+    state.put(&short).await?;
+    Ok(ResolvedRedirect {
+        url: short.original_url,
+        cached: false,
+    })
+}
+
+async fn check_destination(
+    state: &ServerState,
+    id: &str,
+    url: &str,
+    max_clicks: Option<i64>,
+    counted: bool,
+) -> Result<(), RedirectError> {
+    if !state.redirect_allowed(url)? {
+        return Err(RedirectError::Blocked);
+    }
+    if max_clicks.is_some() && !take_click(state, id, counted).await? {
+        return Err(RedirectError::ClickLimitReached);
+    }
+    Ok(())
+}
+
+/// Takes one of a limited link's remaining clicks, or only checks one is left when the
+/// redirect isn't `counted`. The count lives on the link's row and is decremented in a single
+/// conditional update, so concurrent redirects on any replica can't let more clicks through
+/// than the limit, however long their views wait to be written.
+async fn take_click(state: &ServerState, id: &str, counted: bool) -> Result<bool, DbErr> {
+    let remaining = short_link::Column::ClicksRemaining.gt(0);
+    if !counted {
+        return Ok(short_link::Entity::find_by_id(id)
+            .filter(remaining)
+            .count(&state.conn)
+            .await?
+            > 0);
+    }
+    let taken = short_link::Entity::update_many()
+        .col_expr(
+            short_link::Column::ClicksRemaining,
+            Expr::col(short_link::Column::ClicksRemaining).sub(1),
+        )
+        .filter(short_link::Column::Id.eq(id))
+        .filter(remaining)
+        .exec(&state.conn)
+        .await?;
+    Ok(taken.rows_affected > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::{
+        cache::{LinkCache, SharedMemoryCache},
+        config::CacheConfig,
+        testing::{connect, create_link, link},
+    };
+
+    async fn state() -> ServerState {
+        let cache = LinkCache::new(SharedMemoryCache::new(&CacheConfig::default()));
+        ServerState::for_tests(connect().await, cache).await
+    }
+
+    /// Changes the link's row without telling the cache, as only a stale entry would show.
+    async fn point_at(state: &ServerState, id: &str, url: &str) {
+        short_link::Entity::update_many()
+            .col_expr(short_link::Column::OriginalUrl, Expr::value(url))
+            .filter(short_link::Column::Id.eq(id))
+            .exec(&state.conn)
+            .await
+            .unwrap();
+    }
+
+    async fn clicks_remaining(state: &ServerState, id: &str) -> Option<i64> {
+        short_link::Entity::find_by_id(id)
+            .one(&state.conn)
+            .await
+            .unwrap()
+            .unwrap()
+            .clicks_remaining
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn misses_are_cached_and_hits_skip_the_database() {
+        let state = state().await;
+        let link = create_link(&state.conn, link(None)).await;
+
+        let miss = resolve_redirect(&state, &link.id, true).await.unwrap();
+        assert_eq!(miss.url, link.original_url);
+        assert!(!miss.cached);
+
+        point_at(&state, &link.id, "https://example.org/").await;
+        let hit = resolve_redirect(&state, &link.id, true).await.unwrap();
+        assert_eq!(hit.url, link.original_url);
+        assert!(hit.cached);
+
+        state.invalidate(&[&link.id]).await.unwrap();
+        let miss = resolve_redirect(&state, &link.id, true).await.unwrap();
+        assert_eq!(miss.url, "https://example.org/");
+        assert!(!miss.cached);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn missing_links_are_cached_as_missing() {
+        let state = state().await;
+        let missing = link(None);
+
+        assert!(matches!(
+            resolve_redirect(&state, &missing.id, true).await,
+            Err(RedirectError::NotFound)
+        ));
+        assert!(matches!(
+            state.get(&missing.id).await.unwrap(),
+            Some(CachedLink::Missing)
+        ));
+
+        // Until the entry is invalidated the database isn't asked again
+        let missing = create_link(&state.conn, missing).await;
+        assert!(matches!(
+            resolve_redirect(&state, &missing.id, true).await,
+            Err(RedirectError::NotFound)
+        ));
+        state.invalidate(&[&missing.id]).await.unwrap();
+        assert!(resolve_redirect(&state, &missing.id, true).await.is_ok());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn expired_disabled_and_deleted_links_are_turned_away_uncached() {
+        let state = state().await;
+        let now = Utc::now().naive_utc();
+        let expired = create_link(
+            &state.conn,
+            short_link::Model {
+                expiry_date: Some(now - TimeDelta::minutes(1)),
+                ..link(None)
+            },
+        )
+        .await;
+        let disabled = create_link(
+            &state.conn,
+            short_link::Model {
+                enabled: false,
+                disabled_reason: Some("Under review".to_string()),
+                ..link(None)
+            },
+        )
+        .await;
+        let deleted = create_link(
+            &state.conn,
+            short_link::Model {
+                deleted_at: Some(now),
+                ..link(None)
+            },
+        )
+        .await;
+
+        assert!(matches!(
+            resolve_redirect(&state, &expired.id, true).await,
+            Err(RedirectError::Expired)
+        ));
+        assert!(matches!(
+            resolve_redirect(&state, &disabled.id, true).await,
+            Err(RedirectError::Disabled(Some(reason))) if reason == "Under review"
+        ));
+        assert!(matches!(
+            resolve_redirect(&state, &deleted.id, true).await,
+            Err(RedirectError::NotFound)
+        ));
+        // Expired and disabled links can change back, so only the deleted one is remembered
+        assert!(state.get(&expired.id).await.unwrap().is_none());
+        assert!(state.get(&disabled.id).await.unwrap().is_none());
+        assert!(matches!(
+            state.get(&deleted.id).await.unwrap(),
+            Some(CachedLink::Missing)
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn concurrent_redirects_never_take_more_clicks_than_the_limit() {
+        let state = state().await;
+        let link = create_link(
+            &state.conn,
+            short_link::Model {
+                max_clicks: Some(5),
+                clicks_remaining: Some(5),
+                ..link(None)
+            },
+        )
+        .await;
+
+        // Uncounted redirects only check a click is left
+        assert!(resolve_redirect(&state, &link.id, false).await.is_ok());
+        assert_eq!(clicks_remaining(&state, &link.id).await, Some(5));
+
+        let redirects: Vec<_> = (0..20)
+            .map(|_| {
+                let state = state.clone();
+                let id = link.id.clone();
+                tokio::spawn(async move { resolve_redirect(&state, &id, true).await })
+            })
+            .collect();
+        let mut allowed = 0;
+        for redirect in redirects {
+            match redirect.await.unwrap() {
+                Ok(_) => allowed += 1,
+                Err(RedirectError::ClickLimitReached) => {}
+                Err(e) => panic!("Unexpected error {e}"),
+            }
+        }
+        assert_eq!(allowed, 5);
+        assert_eq!(clicks_remaining(&state, &link.id).await, Some(0));
+        assert!(matches!(
+            resolve_redirect(&state, &link.id, false).await,
+            Err(RedirectError::ClickLimitReached)
+        ));
+    }
+}
@@ -1,26 +1,38 @@
 use std::io::Cursor;
 
-#[cfg(feature = "headers")]
-use axum::http::HeaderMap;
 use axum::{
     Json, debug_handler,
     extract::{Path, Query, State},
 };
-#[cfg(feature = "ips")]
-use axum_client_ip::ClientIp;
-use entity::short_link;
+use axum_extra::extract::cookie::PrivateCookieJar;
+use chrono::TimeDelta;
+use entity::{sea_orm_active_enums::WebhookEvent, short_link, short_link_tag, tag};
 use image::{ImageFormat, Rgba};
 use qrcode::{EcLevel, QrCode, Version, render::Renderer};
-use sea_orm::entity::*;
-use tracing::instrument;
+use sea_orm::{
+    entity::*,
+    query::*,
+    sea_query::{Expr, Func},
+};
+use tracing::{instrument, warn};
+use uuid::Uuid;
 
-use super::structs::{
-    ImageFormats, NewUrlRequest, NewUrlResponse, QrCodeResponse, UpdateUrlResponse,
+use super::{
+    click::ClickContext,
+    live::{ClickFilter, LiveClicks, live_clicks},
+    resolve::resolve_redirect,
+    stats::{StatsRange, link_stats, link_views},
+    structs::{
+        DisableUrlRequest, ImageFormats, LiveClicksResponse, NewUrlRequest, NewUrlResponse,
+        QrCodeResponse, RestoreUrlResponse, StatsParams, UpdateUrlResponse, UrlStatsResponse,
+    },
+    validation::{InvalidUrl, validate_target},
 };
 use crate::{
-    actor::{ActorInputMessage, ViewInput},
+    actor::{ActorInputMessage, MetadataInput, ViewInput},
     state::ServerState,
     urls::structs::{DeleteUrlResponse, GetUrlInfoResponse, GetUrlResponse, QrCodeParams},
+    user::session_user,
 };
 
 #[instrument]
@@ -56,11 +68,17 @@ pub async fn qr_code(
         ));
     }
 
-    let Some(short) = short_link::Entity::find_by_id(&id).one(&state.conn).await? else {
+    let Some(short) = short_link::Entity::find_by_id(&id)
+        .filter(short_link::Column::DeletedAt.is_null())
+        .one(&state.conn)
+        .await?
+    else {
         return Err(QrCodeResponse::UrlNotFound);
     };
 
-    state.put(id, short.original_url.clone())?;
+    if short.enabled {
+        state.put(&short).await?;
+    }
 
     let qr = QrCode::with_version(
         short.short_url.into_bytes(),
@@ -123,6 +141,12 @@ pub async fn new_url(
     State(state): State<ServerState>,
     Json(payload): Json<NewUrlRequest>,
 ) -> Result<NewUrlResponse, NewUrlResponse> {
+    let url = validate_target(&payload.url, &state.config.links, &state.url)?;
+    let host = url.host_str().unwrap_or_default();
+    if !state.domain_allowed(host)? {
+        return Err(InvalidUrl::Blocked(host.to_string()).into());
+    }
+
     let short: String = match payload.short {
         Some(short) => short,
         None => state.increment()?,
@@ -135,218 +159,294 @@ pub async fn new_url(
         short_url: ActiveValue::set(short_url.as_str().to_string().to_owned()),
         user_id: ActiveValue::set(payload.user),
         expiry_date: ActiveValue::set(payload.expiry),
-        original_url: ActiveValue::set(payload.url.clone()),
+        original_url: ActiveValue::set(url.to_string()),
         created_at: ActiveValue::set(chrono::Utc::now().naive_utc()),
         updated_at: ActiveValue::set(chrono::Utc::now().naive_utc()),
+        enabled: ActiveValue::set(true),
+        disabled_reason: ActiveValue::set(None),
+        deleted_at: ActiveValue::set(None),
+        title: ActiveValue::set(payload.title),
+        notes: ActiveValue::set(payload.notes),
+        description: ActiveValue::set(None),
+        favicon: ActiveValue::set(None),
+        og_image: ActiveValue::set(None),
+        metadata_fetched_at: ActiveValue::set(None),
+        max_clicks: ActiveValue::set(payload.max_clicks),
+        clicks_remaining: ActiveValue::set(payload.max_clicks.map(|max| max.max(0))),
     };
 
-    let new = new_url.insert(&state.conn).await?;
+    let txn = state.conn.begin().await?;
+    let new = new_url.insert(&txn).await?;
+    if let Some(tags) = payload.tags {
+        replace_tags(&txn, &new.id, new.user_id, tags).await?;
+    }
+    txn.commit().await?;
 
-    state.put(short, payload.url)?;
+    if let Err(e) = state
+        .pool
+        .try_send(ActorInputMessage::FetchMetadata(MetadataInput {
+            id: new.id.clone(),
+            url: new.original_url.clone(),
+            client: state.public_client.clone(),
+            timeout: state.config.actors.metadata_timeout,
+            max_bytes: state.config.actors.metadata_max_bytes,
+            conn: state.conn.clone(),
+        }))
+    {
+        warn!("Unable to queue a metadata fetch for url {}: {e}", new.id);
+    }
+
+    state.put(&new).await?;
+    state
+        .pool
+        .notify_webhooks(new.user_id, WebhookEvent::LinkCreated, &new)
+        .await;
 
-    Ok(NewUrlResponse::UrlCreated(new))
+    Ok(NewUrlResponse::UrlCreated(Box::new(new)))
 }
 
 // /{id}
-#[cfg(all(not(feature = "ips"), not(feature = "headers")))]
 #[instrument]
 #[debug_handler]
 #[utoipa::path(get, path = "/{id}", params(("id", description = "The short url ID")), responses(GetUrlResponse), tag = super::URL_TAG)]
 pub async fn get_url(
     Path(id): Path<String>,
-    State(mut state): State<ServerState>,
+    State(state): State<ServerState>,
+    click: ClickContext,
 ) -> Result<GetUrlResponse, GetUrlResponse> {
     if id.starts_with("/api") || id.starts_with("/ui") || id.starts_with("/auth") {
-        Ok(GetUrlResponse::Redirect(id))
-    } else if let Some(url) = state.get(&id)? {
-        state
-            .pool
-            .send(ActorInputMessage::UpdateViews(ViewInput {
-                id,
-                cached: true,
-                conn: state.conn.clone(),
-            }))
-            .await?;
-        Ok(GetUrlResponse::Redirect(url.to_owned()))
-    } else {
-        state
-            .pool
-            .send(ActorInputMessage::UpdateViews(ViewInput {
-                id: id.clone(),
-                cached: false,
-                conn: state.conn.clone(),
-            }))
-            .await?;
-        let Some(short) = short_link::Entity::find_by_id(&id).one(&state.conn).await? else {
-            return Err(GetUrlResponse::UrlNotFound);
-        };
-        state.put(id, short.original_url.clone())?;
-        Ok(GetUrlResponse::Redirect(short.original_url))
+        return Ok(GetUrlResponse::Redirect(id));
     }
+    let resolved = resolve_redirect(&state, &id, !click.is_bot()).await?;
+    state.pool.record_click(ViewInput {
+        id,
+        cached: resolved.cached,
+        click,
+        created_at: chrono::Utc::now().naive_utc(),
+    });
+    Ok(GetUrlResponse::Redirect(resolved.url))
 }
 
-#[cfg(all(feature = "ips", not(feature = "headers")))]
+// /api/url/delete/{id}
 #[instrument]
 #[debug_handler]
-#[utoipa::path(get, path = "/{id}", params(("id", description = "The short url ID")), responses(GetUrlResponse), tag = super::URL_TAG)]
-pub async fn get_url(
+#[utoipa::path(delete, path = "/delete/{id}", params(("id", description = "The short url ID")), context_path = super::URL_PREFIX, responses(DeleteUrlResponse), tag = super::URL_TAG, security(("session_id" = [])))]
+pub async fn delete_url(
     Path(id): Path<String>,
-    State(mut state): State<ServerState>,
-    ClientIp(ip): ClientIp,
-) -> Result<GetUrlResponse, GetUrlResponse> {
-    if id.starts_with("/api") || id.starts_with("/ui") || id.starts_with("/auth") {
-        Ok(GetUrlResponse::Redirect(id))
-    } else if let Some(url) = state.get(&id)? {
-        state
-            .pool
-            .send(ActorInputMessage::UpdateViews(ViewInput {
-                id,
-                cached: true,
-                ip,
-                conn: state.conn.clone(),
-            }))
-            .await?;
-        Ok(GetUrlResponse::Redirect(url.to_owned()))
-    } else {
-        state
-            .pool
-            .send(ActorInputMessage::UpdateViews(ViewInput {
-                id: id.clone(),
-                cached: false,
-                ip,
-                conn: state.conn.clone(),
-            }))
-            .await?;
-        let Some(short) = short_link::Entity::find_by_id(&id).one(&state.conn).await? else {
-            return Err(GetUrlResponse::UrlNotFound);
-        };
-        state.put(id, short.original_url.clone())?;
-        Ok(GetUrlResponse::Redirect(short.original_url))
-    }
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<DeleteUrlResponse, DeleteUrlResponse> {
+    let user = session_user(&jar, &state, DeleteUrlResponse::InvalidSession).await?;
+    // Links belonging to someone else look the same as links that don't exist
+    let Some(short) = short_link::Entity::find_by_id(&id)
+        .filter(short_link::Column::UserId.eq(user))
+        .filter(short_link::Column::DeletedAt.is_null())
+        .one(&state.conn)
+        .await?
+    else {
+        return Err(DeleteUrlResponse::UrlNotFound);
+    };
+    let mut trashed = short.into_active_model();
+    trashed.deleted_at = ActiveValue::Set(Some(chrono::Utc::now().naive_utc()));
+    trashed.updated_at = ActiveValue::Set(chrono::Utc::now().naive_utc());
+    let short = trashed.update(&state.conn).await?;
+    state.invalidate(&[&id]).await?;
+    state
+        .pool
+        .notify_webhooks(short.user_id, WebhookEvent::LinkDeleted, &short)
+        .await;
+    Ok(DeleteUrlResponse::UrlDeleted)
 }
 
-#[cfg(all(feature = "headers", not(feature = "ips")))]
+// /api/url/restore/{id}
 #[instrument]
 #[debug_handler]
-#[utoipa::path(get, path = "/{id}", params(("id", description = "The short url ID")), responses(GetUrlResponse), tag = super::URL_TAG)]
-pub async fn get_url(
+#[utoipa::path(put, path = "/restore/{id}", params(("id", description = "The short url ID")), context_path = super::URL_PREFIX, responses(RestoreUrlResponse), tag = super::URL_TAG, security(("session_id" = [])))]
+pub async fn restore_url(
     Path(id): Path<String>,
-    State(mut state): State<ServerState>,
-    headers: HeaderMap,
-) -> Result<GetUrlResponse, GetUrlResponse> {
-    if id.starts_with("/api") || id.starts_with("/ui") || id.starts_with("/auth") {
-        Ok(GetUrlResponse::Redirect(id))
-    } else if let Some(url) = state.get(&id)? {
-        state
-            .pool
-            .send(ActorInputMessage::UpdateViews(ViewInput {
-                id,
-                cached: true,
-                headers,
-                conn: state.conn.clone(),
-            }))
-            .await?;
-        Ok(GetUrlResponse::Redirect(url.to_owned()))
-    } else {
-        state
-            .pool
-            .send(ActorInputMessage::UpdateViews(ViewInput {
-                id: id.clone(),
-                cached: false,
-                headers,
-                conn: state.conn.clone(),
-            }))
-            .await?;
-        let Some(short) = short_link::Entity::find_by_id(&id).one(&state.conn).await? else {
-            return Err(GetUrlResponse::UrlNotFound);
-        };
-        state.put(id, short.original_url.clone())?;
-        Ok(GetUrlResponse::Redirect(short.original_url))
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<RestoreUrlResponse, RestoreUrlResponse> {
+    let user = session_user(&jar, &state, RestoreUrlResponse::InvalidSession).await?;
+    // Links belonging to someone else look the same as links that don't exist
+    let Some(short) = short_link::Entity::find_by_id(&id)
+        .filter(short_link::Column::UserId.eq(user))
+        .filter(short_link::Column::DeletedAt.is_not_null())
+        .one(&state.conn)
+        .await?
+    else {
+        return Err(RestoreUrlResponse::UrlNotFound);
+    };
+    let now = chrono::Utc::now().naive_utc();
+    let restorable_until = short.deleted_at.and_then(|deleted_at| {
+        TimeDelta::from_std(state.config.actors.trash_retention)
+            .ok()
+            .and_then(|retention| deleted_at.checked_add_signed(retention))
+    });
+    if restorable_until.is_some_and(|until| until < now) {
+        return Err(RestoreUrlResponse::RestoreWindowExpired(
+            format!("The restore window for {id} has expired").into(),
+        ));
     }
+    let mut restored = short.into_active_model();
+    restored.deleted_at = ActiveValue::Set(None);
+    restored.updated_at = ActiveValue::Set(now);
+    let short = restored.update(&state.conn).await?;
+    state.invalidate(&[&id]).await?;
+    state
+        .pool
+        .notify_webhooks(short.user_id, WebhookEvent::LinkUpdated, &short)
+        .await;
+    Ok(RestoreUrlResponse::UrlRestored(Box::new(short)))
 }
 
-#[cfg(all(feature = "headers", feature = "ips"))]
+// /api/url/disable/{id}
 #[instrument]
 #[debug_handler]
-#[utoipa::path(get, path = "/{id}", params(("id", description = "The short url ID")), responses(GetUrlResponse), tag = super::URL_TAG)]
-pub async fn get_url(
+#[utoipa::path(put, path = "/disable/{id}", params(("id", description = "The short url ID")), context_path = super::URL_PREFIX, request_body = DisableUrlRequest, responses(UpdateUrlResponse), tag = super::URL_TAG, security(("session_id" = [])))]
+pub async fn disable_url(
     Path(id): Path<String>,
+    jar: PrivateCookieJar,
     State(state): State<ServerState>,
-    ClientIp(ip): ClientIp,
-    headers: HeaderMap,
-) -> Result<GetUrlResponse, GetUrlResponse> {
-    if id.starts_with("/api") || id.starts_with("/ui") || id.starts_with("/auth") {
-        Ok(GetUrlResponse::Redirect(id))
-    } else if let Some(url) = state.get(&id)? {
-        state
-            .pool
-            .send(ActorInputMessage::UpdateViews(ViewInput {
-                id,
-                cached: true,
-                ip,
-                headers,
-                conn: state.conn.clone(),
-            }))
-            .await?;
-        Ok(GetUrlResponse::Redirect(url.to_owned()))
-    } else {
-        state
-            .pool
-            .send(ActorInputMessage::UpdateViews(ViewInput {
-                id: id.clone(),
-                cached: false,
-                ip,
-                headers,
-                conn: state.conn.clone(),
-            }))
-            .await?;
-        let Some(short) = short_link::Entity::find_by_id(&id).one(&state.conn).await? else {
-            return Err(GetUrlResponse::UrlNotFound);
-        };
-        state.put(id, short.original_url.clone())?;
-        Ok(GetUrlResponse::Redirect(short.original_url))
-    }
+    Json(payload): Json<DisableUrlRequest>,
+) -> Result<UpdateUrlResponse, UpdateUrlResponse> {
+    let user = session_user(&jar, &state, UpdateUrlResponse::InvalidSession).await?;
+    // Links belonging to someone else look the same as links that don't exist
+    let Some(short) = short_link::Entity::find_by_id(&id)
+        .filter(short_link::Column::UserId.eq(user))
+        .filter(short_link::Column::DeletedAt.is_null())
+        .one(&state.conn)
+        .await?
+    else {
+        return Err(UpdateUrlResponse::UrlNotFound);
+    };
+    let mut disabled = short.into_active_model();
+    disabled.enabled = ActiveValue::Set(false);
+    disabled.disabled_reason = ActiveValue::Set(payload.reason);
+    disabled.updated_at = ActiveValue::Set(chrono::Utc::now().naive_utc());
+    let short = disabled.update(&state.conn).await?;
+    state.invalidate(&[&id]).await?;
+    state
+        .pool
+        .notify_webhooks(short.user_id, WebhookEvent::LinkUpdated, &short)
+        .await;
+    Ok(UpdateUrlResponse::UrlUpdated(Box::new(short)))
 }
 
-// /api/url/delete/{id}
+// /api/url/enable/{id}
 #[instrument]
 #[debug_handler]
-#[utoipa::path(delete, path = "/delete/{id}", params(("id", description = "The short url ID")), context_path = super::URL_PREFIX, responses(DeleteUrlResponse), tag = super::URL_TAG)]
-pub async fn delete_url(
+#[utoipa::path(put, path = "/enable/{id}", params(("id", description = "The short url ID")), context_path = super::URL_PREFIX, responses(UpdateUrlResponse), tag = super::URL_TAG, security(("session_id" = [])))]
+pub async fn enable_url(
     Path(id): Path<String>,
+    jar: PrivateCookieJar,
     State(state): State<ServerState>,
-) -> Result<DeleteUrlResponse, DeleteUrlResponse> {
-    let Some(short) = short_link::Entity::find_by_id(&id).one(&state.conn).await? else {
-        return Err(DeleteUrlResponse::UrlNotFound);
+) -> Result<UpdateUrlResponse, UpdateUrlResponse> {
+    let user = session_user(&jar, &state, UpdateUrlResponse::InvalidSession).await?;
+    // Links belonging to someone else look the same as links that don't exist
+    let Some(short) = short_link::Entity::find_by_id(&id)
+        .filter(short_link::Column::UserId.eq(user))
+        .filter(short_link::Column::DeletedAt.is_null())
+        .one(&state.conn)
+        .await?
+    else {
+        return Err(UpdateUrlResponse::UrlNotFound);
     };
-    short.delete(&state.conn).await?;
-    state.pop(&id)?;
-    Ok(DeleteUrlResponse::UrlDeleted)
+    let mut enabled = short.into_active_model();
+    enabled.enabled = ActiveValue::Set(true);
+    enabled.disabled_reason = ActiveValue::Set(None);
+    enabled.updated_at = ActiveValue::Set(chrono::Utc::now().naive_utc());
+    let short = enabled.update(&state.conn).await?;
+    state.invalidate(&[&id]).await?;
+    state
+        .pool
+        .notify_webhooks(short.user_id, WebhookEvent::LinkUpdated, &short)
+        .await;
+    Ok(UpdateUrlResponse::UrlUpdated(Box::new(short)))
 }
 
 // /api/url/update/{id}
 #[instrument]
 #[debug_handler]
-#[utoipa::path(put, path = "/update/{id}", params(("id", description = "The short url ID")), context_path = super::URL_PREFIX, request_body = NewUrlRequest, responses(UpdateUrlResponse), tag = super::URL_TAG)]
+#[utoipa::path(put, path = "/update/{id}", params(("id", description = "The short url ID")), context_path = super::URL_PREFIX, request_body = NewUrlRequest, responses(UpdateUrlResponse), tag = super::URL_TAG, security(("session_id" = [])))]
 pub async fn update_url(
     Path(id): Path<String>,
+    jar: PrivateCookieJar,
     State(state): State<ServerState>,
     Json(payload): Json<NewUrlRequest>,
 ) -> Result<UpdateUrlResponse, UpdateUrlResponse> {
-    let Some(short) = short_link::Entity::find_by_id(&id).one(&state.conn).await? else {
+    let user = session_user(&jar, &state, UpdateUrlResponse::InvalidSession).await?;
+    // Links belonging to someone else look the same as links that don't exist
+    let Some(short) = short_link::Entity::find_by_id(&id)
+        .filter(short_link::Column::UserId.eq(user))
+        .filter(short_link::Column::DeletedAt.is_null())
+        .one(&state.conn)
+        .await?
+    else {
         return Err(UpdateUrlResponse::UrlNotFound);
     };
+    let url = validate_target(&payload.url, &state.config.links, &state.url)?;
+    let host = url.host_str().unwrap_or_default();
+    if !state.domain_allowed(host)? {
+        return Err(InvalidUrl::Blocked(host.to_string()).into());
+    }
+    let limit_changed = short.max_clicks != payload.max_clicks;
     let mut new_url = short.into_active_model();
     if let Some(short_url) = payload.short {
         new_url.id = ActiveValue::Set(short_url.clone());
         new_url.short_url = ActiveValue::Set(state.url.clone().join(&short_url)?.into());
     }
     new_url.expiry_date = ActiveValue::Set(payload.expiry);
-    new_url.id = ActiveValue::Set(payload.url);
+    new_url.original_url = ActiveValue::Set(url.to_string());
+    new_url.title = ActiveValue::Set(payload.title);
+    new_url.notes = ActiveValue::Set(payload.notes);
+    new_url.max_clicks = ActiveValue::Set(payload.max_clicks);
     new_url.updated_at = ActiveValue::set(chrono::Utc::now().naive_utc());
-    let short = new_url.update(&state.conn).await?;
+    let mut update = short_link::Entity::update_many();
+    match payload.max_clicks {
+        None => new_url.clicks_remaining = ActiveValue::Set(None),
+        Some(max) if limit_changed => {
+            // The clicks already used are the old limit less what was left of it, or the
+            // counted views if the link had no limit
+            let used = Func::coalesce([
+                Expr::col(short_link::Column::MaxClicks)
+                    .sub(Expr::col(short_link::Column::ClicksRemaining)),
+                link_views(false),
+            ]);
+            update = update.col_expr(
+                short_link::Column::ClicksRemaining,
+                Func::greatest([Expr::val(max).sub(used), Expr::val(0).into()]).into(),
+            );
+        }
+        Some(_) => {}
+    }
+    let txn = state.conn.begin().await?;
+    // Filter on the old slug so renaming a link updates the row instead of looking for the new one
+    let Some(short) = update
+        .set(new_url)
+        .filter(short_link::Column::Id.eq(&id))
+        .filter(short_link::Column::UserId.eq(user))
+        .exec_with_returning(&txn)
+        .await?
+        .pop()
+    else {
+        return Err(UpdateUrlResponse::UrlNotFound);
+    };
+    if let Some(tags) = payload.tags {
+        replace_tags(&txn, &short.id, short.user_id, tags).await?;
+    }
+    txn.commit().await?;
 
-    Ok(UpdateUrlResponse::UrlUpdated(short))
+    state.invalidate(&[&id, &short.id]).await?;
+    if short.enabled {
+        state.put(&short).await?;
+    }
+    state
+        .pool
+        .notify_webhooks(short.user_id, WebhookEvent::LinkUpdated, &short)
+        .await;
+
+    Ok(UpdateUrlResponse::UrlUpdated(Box::new(short)))
 }
 
 // /api/url/{id}
@@ -357,8 +457,135 @@ pub async fn url_info(
     Path(id): Path<String>,
     State(state): State<ServerState>,
 ) -> Result<GetUrlInfoResponse, GetUrlInfoResponse> {
-    let Some(short) = short_link::Entity::find_by_id(&id).one(&state.conn).await? else {
+    let Some(short) = short_link::Entity::find_by_id(&id)
+        .filter(short_link::Column::DeletedAt.is_null())
+        .one(&state.conn)
+        .await?
+    else {
         return Err(GetUrlInfoResponse::UrlNotFound);
     };
-    Ok(GetUrlInfoResponse::Url(short))
+    Ok(GetUrlInfoResponse::Url(Box::new(short.into())))
+}
+
+// /api/url/{id}/stats
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    get,
+    path = "/{id}/stats",
+    context_path = super::URL_PREFIX,
+    params(("id", description = "The short url ID"), StatsParams),
+    responses(UrlStatsResponse),
+    tag = super::URL_TAG,
+    security(("session_id" = [])),
+)]
+pub async fn url_stats(
+    Path(id): Path<String>,
+    Query(params): Query<StatsParams>,
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<UrlStatsResponse, UrlStatsResponse> {
+    let user = session_user(&jar, &state, UrlStatsResponse::InvalidSession).await?;
+
+    let range = StatsRange::new(params, chrono::Utc::now().naive_utc())
+        .map_err(|e| UrlStatsResponse::InvalidRange(e.into()))?;
+
+    // Links belonging to someone else look the same as links that don't exist
+    let Some(short) = short_link::Entity::find_by_id(&id)
+        .filter(short_link::Column::UserId.eq(user))
+        .one(&state.conn)
+        .await?
+    else {
+        return Err(UrlStatsResponse::UrlNotFound);
+    };
+
+    let stats = link_stats(&state.conn, &short.id, range).await?;
+    Ok(UrlStatsResponse::Stats(Box::new(stats)))
+}
+
+// /api/url/{id}/live
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    get,
+    path = "/{id}/live",
+    context_path = super::URL_PREFIX,
+    params(("id", description = "The short url ID")),
+    responses(LiveClicksResponse),
+    tag = super::URL_TAG,
+    security(("session_id" = [])),
+)]
+pub async fn url_live(
+    Path(id): Path<String>,
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<LiveClicks, LiveClicksResponse> {
+    let user = session_user(&jar, &state, LiveClicksResponse::InvalidSession).await?;
+
+    // Links belonging to someone else look the same as links that don't exist
+    let Some(short) = short_link::Entity::find_by_id(&id)
+        .filter(short_link::Column::UserId.eq(user))
+        .one(&state.conn)
+        .await?
+    else {
+        return Err(LiveClicksResponse::UrlNotFound);
+    };
+
+    Ok(live_clicks(
+        state.pool.subscribe_clicks(),
+        ClickFilter::Link(short.id),
+    ))
+}
+
+/// Replaces the tags on a link, creating any of the owner's tags that don't exist yet.
+async fn replace_tags<C: ConnectionTrait>(
+    conn: &C,
+    link: &str,
+    user: Option<Uuid>,
+    names: Vec<String>,
+) -> Result<(), sea_orm::DbErr> {
+    short_link_tag::Entity::delete_many()
+        .filter(short_link_tag::Column::ShortLink.eq(link))
+        .exec(conn)
+        .await?;
+
+    let mut names: Vec<String> = names
+        .into_iter()
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+        .collect();
+    names.sort();
+    names.dedup();
+
+    for name in names {
+        let owner = match user {
+            Some(user) => tag::Column::UserId.eq(user),
+            None => tag::Column::UserId.is_null(),
+        };
+        let tag = match tag::Entity::find()
+            .filter(owner)
+            .filter(tag::Column::Name.eq(&name))
+            .one(conn)
+            .await?
+        {
+            Some(tag) => tag,
+            None => {
+                tag::ActiveModel {
+                    user_id: ActiveValue::set(user),
+                    name: ActiveValue::set(name),
+                    created_at: ActiveValue::set(chrono::Utc::now().naive_utc()),
+                    ..Default::default()
+                }
+                .insert(conn)
+                .await?
+            }
+        };
+        short_link_tag::ActiveModel {
+            short_link: ActiveValue::set(link.to_owned()),
+            tag_id: ActiveValue::set(tag.id),
+        }
+        .insert(conn)
+        .await?;
+    }
+    Ok(())
 }
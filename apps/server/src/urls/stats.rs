@@ -0,0 +1,725 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, TimeDelta, Timelike};
+use entity::{daily_views, hourly_views, views};
+use sea_orm::{
+    ConnectionTrait, DbErr, FromQueryResult, Order,
+    entity::*,
+    query::*,
+    sea_query::{Alias, Expr, Query, SelectStatement, SimpleExpr, UnionType},
+};
+
+use super::structs::{StatsBreakdown, StatsBucket, StatsParams, StatsPoint, UrlStats};
+use crate::visitors::{HyperLogLog, daily_sketches};
+
+/// The longest time series a stats request may ask for.
+const MAX_BUCKETS: i64 = 5000;
+const DEFAULT_RANGE_DAYS: i64 = 30;
+const DEFAULT_BREAKDOWN_LIMIT: u64 = 10;
+const MAX_BREAKDOWN_LIMIT: u64 = 100;
+
+/// Who counts as one visitor: the same IP address, or IP hash, with the same user agent. Views
+/// recorded before the referrer and user agent had their own columns only have them in
+/// `headers`.
+const VISITOR: &str = r#"COALESCE("views"."ip_hash", host("views"."ip"), '') || '|' || COALESCE("views"."user_agent", "views"."headers"->'user-agent'->>0, '')"#;
+pub(crate) const USER_AGENT: &str =
+    r#"COALESCE("views"."user_agent", "views"."headers"->'user-agent'->>0)"#;
+const BROWSER: &str = r#""views"."browser""#;
+const OS: &str = r#""views"."os""#;
+const DEVICE: &str = r#""views"."device""#;
+const COUNTRY: &str = r#""views"."country""#;
+/// The referring host, lowercased, or the raw referrer when it isn't a URL.
+pub(crate) const REFERRER: &str = r#"COALESCE(lower(substring(COALESCE("views"."referrer", "views"."headers"->'referer'->>0) from '^[A-Za-z][A-Za-z0-9+.-]*://([^/:?#@]+)')), COALESCE("views"."referrer", "views"."headers"->'referer'->>0))"#;
+
+impl StatsBucket {
+    fn unit(self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+            Self::Week => "week",
+        }
+    }
+
+    fn step(self) -> TimeDelta {
+        match self {
+            Self::Hour => TimeDelta::hours(1),
+            Self::Day => TimeDelta::days(1),
+            Self::Week => TimeDelta::weeks(1),
+        }
+    }
+
+    /// The rollup a stats request reads: hourly for hour buckets, daily otherwise.
+    fn rollup(self) -> Self {
+        match self {
+            Self::Hour => Self::Hour,
+            Self::Day | Self::Week => Self::Day,
+        }
+    }
+
+    /// The start of the bucket holding `at`, matching Postgres' `date_trunc`, whose weeks
+    /// start on Monday.
+    fn truncate(self, at: NaiveDateTime) -> NaiveDateTime {
+        let hour = at
+            .with_nanosecond(0)
+            .and_then(|at| at.with_second(0))
+            .and_then(|at| at.with_minute(0))
+            .unwrap_or(at);
+        match self {
+            Self::Hour => hour,
+            Self::Day => at.date().and_hms_opt(0, 0, 0).unwrap_or(hour),
+            Self::Week => {
+                let monday =
+                    at.date() - TimeDelta::days(at.weekday().num_days_from_monday().into());
+                monday.and_hms_opt(0, 0, 0).unwrap_or(hour)
+            }
+        }
+    }
+}
+
+/// A validated stats request.
+#[derive(Debug, Clone, Copy)]
+pub struct StatsRange {
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+    pub bucket: StatsBucket,
+    pub limit: u64,
+    pub include_bots: bool,
+}
+
+impl StatsRange {
+    /// Fills in the defaults, relative to `now`, and rejects empty ranges and ones that would
+    /// need more than [`MAX_BUCKETS`] buckets. The range is widened to whole hours for hour
+    /// buckets and whole days otherwise, as that is what the rollups count.
+    pub fn new(params: StatsParams, now: NaiveDateTime) -> Result<Self, String> {
+        let to = params.to.unwrap_or(now);
+        let from = params
+            .from
+            .unwrap_or_else(|| to - TimeDelta::days(DEFAULT_RANGE_DAYS));
+        if from >= to {
+            return Err("The start of the range must be before its end".to_string());
+        }
+        let bucket = params.bucket.unwrap_or_default();
+        let rollup = bucket.rollup();
+        let from = rollup.truncate(from);
+        let to = match rollup.truncate(to) {
+            start if start < to => start + rollup.step(),
+            start => start,
+        };
+        let buckets = (to - bucket.truncate(from)).num_seconds() / bucket.step().num_seconds();
+        if buckets >= MAX_BUCKETS {
+            return Err(format!(
+                "The range needs more than {MAX_BUCKETS} {} buckets; use a larger bucket or a \
+                 shorter range",
+                bucket.unit()
+            ));
+        }
+        Ok(Self {
+            from,
+            to,
+            bucket,
+            limit: params
+                .limit
+                .unwrap_or(DEFAULT_BREAKDOWN_LIMIT)
+                .clamp(1, MAX_BREAKDOWN_LIMIT),
+            include_bots: params.include_bots.unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct Totals {
+    views: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct Bucketed {
+    start: NaiveDateTime,
+    views: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct BucketVisitors {
+    start: NaiveDateTime,
+    unique_visitors: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct Referrer {
+    value: Option<String>,
+    views: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct Grouped {
+    value: Option<String>,
+    views: i64,
+    unique_visitors: i64,
+}
+
+/// Every view of the link in the surrounding query, leaving out bots unless `include_bots` is
+/// set: the daily rollups plus the views the rollup job hasn't reached yet.
+pub(crate) fn link_views(include_bots: bool) -> SimpleExpr {
+    let (rolled_up, pending) = if include_bots {
+        (r#""daily_views"."views" + "daily_views"."bot_views""#, "")
+    } else {
+        (r#""daily_views"."views""#, r#" AND NOT "views"."bot""#)
+    };
+    Expr::cust(format!(
+        r#"((SELECT COALESCE(SUM({rolled_up}), 0) FROM "daily_views" WHERE "daily_views"."short_link" = "short_link"."id") + (SELECT COUNT(*) FROM "views" WHERE "views"."short_link" = "short_link"."id" AND NOT "views"."rolled_up"{pending}))::bigint"#
+    ))
+}
+
+/// The views of link `id` over `range` as rows of `at`, `referrer_host` and `views`: the
+/// rollup rows matching the range's rollup, plus the views the rollup job hasn't reached yet
+/// grouped the same way.
+fn rollup_source(id: &str, range: StatsRange) -> SelectStatement {
+    let rollup = range.bucket.rollup();
+    let mut source = match rollup {
+        StatsBucket::Hour => {
+            let views = if range.include_bots {
+                r#""hourly_views"."views" + "hourly_views"."bot_views""#
+            } else {
+                r#""hourly_views"."views""#
+            };
+            hourly_views::Entity::find()
+                .select_only()
+                .column_as(hourly_views::Column::Hour, "at")
+                .column(hourly_views::Column::ReferrerHost)
+                .column_as(Expr::cust(views), "views")
+                .filter(hourly_views::Column::ShortLink.eq(id))
+                .filter(hourly_views::Column::Hour.gte(range.from))
+                .filter(hourly_views::Column::Hour.lt(range.to))
+                .into_query()
+        }
+        StatsBucket::Day | StatsBucket::Week => {
+            let views = if range.include_bots {
+                r#""daily_views"."views" + "daily_views"."bot_views""#
+            } else {
+                r#""daily_views"."views""#
+            };
+            daily_views::Entity::find()
+                .select_only()
+                .column_as(Expr::cust(r#""daily_views"."day"::timestamp"#), "at")
+                .column(daily_views::Column::ReferrerHost)
+                .column_as(Expr::cust(views), "views")
+                .filter(daily_views::Column::ShortLink.eq(id))
+                .filter(daily_views::Column::Day.gte(range.from.date()))
+                .filter(daily_views::Column::Day.lt(range.to.date()))
+                .into_query()
+        }
+    };
+
+    let at = Expr::cust(format!(
+        r#"date_trunc('{}', "views"."created_at")"#,
+        rollup.unit()
+    ));
+    let referrer = Expr::cust(format!("COALESCE({REFERRER}, '')"));
+    let mut pending = views::Entity::find()
+        .select_only()
+        .column_as(at.clone(), "at")
+        .column_as(referrer.clone(), "referrer_host")
+        .column_as(views::Column::Id.count(), "views")
+        .filter(views::Column::ShortLink.eq(id))
+        .filter(views::Column::RolledUp.eq(false))
+        .filter(views::Column::CreatedAt.gte(range.from))
+        .filter(views::Column::CreatedAt.lt(range.to));
+    if !range.include_bots {
+        pending = pending.filter(views::Column::Bot.eq(false));
+    }
+    source.union(
+        UnionType::All,
+        pending.group_by(at).group_by(referrer).into_query(),
+    );
+    source
+}
+
+/// Aggregates the views of link `id` over `range`, leaving out bots unless the range includes
+/// them. Every figure is computed by Postgres, so only the aggregates leave the database.
+///
+/// View counts, the time series and referrers come from the rollup tables, plus whatever the
+/// rollup job hasn't reached yet, so they cover the whole history however short view
+/// retention is. Unique visitors in the totals and in day and week buckets come from the
+/// daily visitor sketches, which only count humans. Hour buckets and the other breakdowns are
+/// counted from the views still kept.
+pub async fn link_stats<C: ConnectionTrait>(
+    conn: &C,
+    id: &str,
+    range: StatsRange,
+) -> Result<UrlStats, DbErr> {
+    let backend = conn.get_database_backend();
+    let source = || {
+        Query::select()
+            .from_subquery(rollup_source(id, range), Alias::new("source"))
+            .to_owned()
+    };
+    let sum = Expr::cust(r#"COALESCE(SUM("source"."views"), 0)::bigint"#);
+    let in_range = || {
+        let mut select = views::Entity::find()
+            .select_only()
+            .filter(views::Column::ShortLink.eq(id))
+            .filter(views::Column::CreatedAt.gte(range.from))
+            .filter(views::Column::CreatedAt.lt(range.to));
+        if !range.include_bots {
+            select = select.filter(views::Column::Bot.eq(false));
+        }
+        select
+    };
+    let counted_visitors = Expr::cust(format!("COUNT(DISTINCT {VISITOR})"));
+
+    let first_day = range.from.date();
+    let last_day = (range.to - TimeDelta::microseconds(1)).date();
+    let sketches = daily_sketches(conn, id, first_day, last_day).await?;
+    let sketched_visitors = |from: NaiveDate, to: NaiveDate| {
+        let mut visitors = HyperLogLog::default();
+        for (_, sketch) in sketches.range(from.max(first_day)..=to.min(last_day)) {
+            visitors.merge(sketch);
+        }
+        visitors.estimate()
+    };
+
+    let totals = Totals::find_by_statement(
+        backend.build(source().expr_as(sum.clone(), Alias::new("views"))),
+    )
+    .one(conn)
+    .await?
+    .unwrap_or(Totals { views: 0 });
+
+    let start = Expr::cust(format!(
+        r#"date_trunc('{}', "source"."at")"#,
+        range.bucket.unit()
+    ));
+    let mut buckets: HashMap<NaiveDateTime, i64> = Bucketed::find_by_statement(
+        backend.build(
+            source()
+                .expr_as(start.clone(), Alias::new("start"))
+                .expr_as(sum.clone(), Alias::new("views"))
+                .add_group_by([start]),
+        ),
+    )
+    .all(conn)
+    .await?
+    .into_iter()
+    .map(|bucket| (bucket.start, bucket.views))
+    .collect();
+    // Hours are too short for the daily sketches, so hourly visitors are counted directly
+    let mut hourly_visitors: HashMap<NaiveDateTime, i64> = HashMap::new();
+    if range.bucket == StatsBucket::Hour {
+        let hour = Expr::cust(r#"date_trunc('hour', "views"."created_at")"#);
+        hourly_visitors = in_range()
+            .column_as(hour.clone(), "start")
+            .column_as(counted_visitors.clone(), "unique_visitors")
+            .group_by(hour)
+            .into_model::<BucketVisitors>()
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|bucket| (bucket.start, bucket.unique_visitors))
+            .collect();
+    }
+    let mut series = Vec::new();
+    let mut at = range.bucket.truncate(range.from);
+    while at < range.to {
+        let unique_visitors = match range.bucket {
+            StatsBucket::Hour => hourly_visitors.remove(&at).unwrap_or_default(),
+            StatsBucket::Day | StatsBucket::Week => sketched_visitors(
+                at.date(),
+                (at + range.bucket.step() - TimeDelta::days(1)).date(),
+            ),
+        };
+        series.push(StatsPoint {
+            start: at,
+            views: buckets.remove(&at).unwrap_or_default(),
+            unique_visitors,
+        });
+        at += range.bucket.step();
+    }
+
+    let value = Expr::cust(r#"NULLIF("source"."referrer_host", '')"#);
+    let referrers = Referrer::find_by_statement(
+        backend.build(
+            source()
+                .expr_as(value.clone(), Alias::new("value"))
+                .expr_as(sum, Alias::new("views"))
+                .add_group_by([value])
+                .and_having(Expr::cust(r#"SUM("source"."views") > 0"#))
+                .order_by_expr(Expr::cust("views"), Order::Desc)
+                .order_by_expr(Expr::cust("value"), Order::Asc)
+                .limit(range.limit),
+        ),
+    )
+    .all(conn)
+    .await?;
+
+    let breakdown = |expr: &'static str| {
+        in_range()
+            .column_as(views::Column::Id.count(), "views")
+            .column_as(counted_visitors.clone(), "unique_visitors")
+            .column_as(Expr::cust(expr), "value")
+            .group_by(Expr::cust(expr))
+            .order_by(Expr::cust("views"), Order::Desc)
+            .order_by(Expr::cust("value"), Order::Asc)
+            .limit(range.limit)
+            .into_model::<Grouped>()
+    };
+    let user_agents = breakdown(USER_AGENT).all(conn).await?;
+    let browsers = breakdown(BROWSER).all(conn).await?;
+    let operating_systems = breakdown(OS).all(conn).await?;
+    let devices = breakdown(DEVICE).all(conn).await?;
+    let countries = breakdown(COUNTRY).all(conn).await?;
+
+    Ok(UrlStats {
+        id: id.to_string(),
+        from: range.from,
+        to: range.to,
+        bucket: range.bucket,
+        include_bots: range.include_bots,
+        views: totals.views,
+        unique_visitors: sketched_visitors(first_day, last_day),
+        series,
+        referrers: referrers
+            .into_iter()
+            .map(|referrer| StatsBreakdown {
+                value: referrer.value,
+                views: referrer.views,
+                unique_visitors: None,
+            })
+            .collect(),
+        user_agents: user_agents.into_iter().map(StatsBreakdown::from).collect(),
+        browsers: browsers.into_iter().map(StatsBreakdown::from).collect(),
+        operating_systems: operating_systems
+            .into_iter()
+            .map(StatsBreakdown::from)
+            .collect(),
+        devices: devices.into_iter().map(StatsBreakdown::from).collect(),
+        countries: countries.into_iter().map(StatsBreakdown::from).collect(),
+    })
+}
+
+impl From<Grouped> for StatsBreakdown {
+    fn from(group: Grouped) -> Self {
+        Self {
+            value: group.value,
+            views: group.views,
+            unique_visitors: Some(group.unique_visitors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::short_link;
+    use sea_orm::DatabaseConnection;
+
+    use super::*;
+    use crate::testing::{connect, create_link, link};
+
+    /// 9am on Monday the 5th of January 2026.
+    fn monday() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 1, 5)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+    }
+
+    fn params(from: NaiveDateTime, to: NaiveDateTime, bucket: StatsBucket) -> StatsParams {
+        StatsParams {
+            from: Some(from),
+            to: Some(to),
+            bucket: Some(bucket),
+            limit: None,
+            include_bots: None,
+        }
+    }
+
+    #[test]
+    fn ranges_are_widened_to_whole_rollup_buckets() {
+        let from = monday() + TimeDelta::minutes(30);
+        let to = from + TimeDelta::hours(49);
+
+        let hours = StatsRange::new(params(from, to, StatsBucket::Hour), to).unwrap();
+        assert_eq!(hours.from, monday());
+        assert_eq!(hours.to, monday() + TimeDelta::hours(50));
+
+        let days = StatsRange::new(params(from, to, StatsBucket::Day), to).unwrap();
+        assert_eq!(days.from, monday().date().and_hms_opt(0, 0, 0).unwrap());
+        assert_eq!(days.to, days.from + TimeDelta::days(3));
+
+        // Weeks are read from the daily rollup, so they only widen to whole days
+        let weeks = StatsRange::new(params(from, to, StatsBucket::Week), to).unwrap();
+        assert_eq!((weeks.from, weeks.to), (days.from, days.to));
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        let week = monday().date().and_hms_opt(0, 0, 0).unwrap();
+        for day in 0..7 {
+            let at = monday() + TimeDelta::days(day) + TimeDelta::minutes(5);
+            assert_eq!(StatsBucket::Week.truncate(at), week, "{at}");
+        }
+        assert_eq!(
+            StatsBucket::Week.truncate(monday() + TimeDelta::days(7)),
+            week + TimeDelta::weeks(1)
+        );
+    }
+
+    #[test]
+    fn ranges_needing_too_many_buckets_are_rejected() {
+        let longest = monday() + TimeDelta::hours(MAX_BUCKETS - 1);
+        let too_long = monday() + TimeDelta::hours(MAX_BUCKETS);
+        assert!(StatsRange::new(params(monday(), longest, StatsBucket::Hour), longest).is_ok());
+        assert!(StatsRange::new(params(monday(), too_long, StatsBucket::Hour), too_long).is_err());
+        // The same range fits easily in day buckets
+        assert!(StatsRange::new(params(monday(), too_long, StatsBucket::Day), too_long).is_ok());
+
+        assert!(StatsRange::new(params(monday(), monday(), StatsBucket::Day), monday()).is_err());
+    }
+
+    #[test]
+    fn defaults_fill_in_and_limits_are_clamped() {
+        let range = StatsRange::new(
+            StatsParams {
+                from: None,
+                to: None,
+                bucket: None,
+                limit: Some(1000),
+                include_bots: None,
+            },
+            monday(),
+        )
+        .unwrap();
+        assert_eq!(range.bucket, StatsBucket::Day);
+        assert_eq!(
+            range.to,
+            monday().date().and_hms_opt(0, 0, 0).unwrap() + TimeDelta::days(1)
+        );
+        assert_eq!(
+            range.from,
+            monday().date().and_hms_opt(0, 0, 0).unwrap() - TimeDelta::days(DEFAULT_RANGE_DAYS)
+        );
+        assert_eq!(range.limit, MAX_BREAKDOWN_LIMIT);
+        assert!(!range.include_bots);
+
+        let params = StatsParams {
+            limit: Some(0),
+            ..params(monday(), monday() + TimeDelta::days(1), StatsBucket::Day)
+        };
+        assert_eq!(StatsRange::new(params, monday()).unwrap().limit, 1);
+    }
+
+    async fn daily(
+        conn: &DatabaseConnection,
+        id: &str,
+        day: NaiveDateTime,
+        host: &str,
+        views: i64,
+        bot_views: i64,
+    ) {
+        daily_views::ActiveModel {
+            short_link: ActiveValue::Set(id.to_string()),
+            day: ActiveValue::Set(day.date()),
+            referrer_host: ActiveValue::Set(host.to_string()),
+            views: ActiveValue::Set(views),
+            bot_views: ActiveValue::Set(bot_views),
+        }
+        .insert(conn)
+        .await
+        .unwrap();
+    }
+
+    async fn hourly(
+        conn: &DatabaseConnection,
+        id: &str,
+        hour: NaiveDateTime,
+        host: &str,
+        views: i64,
+        bot_views: i64,
+    ) {
+        hourly_views::ActiveModel {
+            short_link: ActiveValue::Set(id.to_string()),
+            hour: ActiveValue::Set(hour),
+            referrer_host: ActiveValue::Set(host.to_string()),
+            views: ActiveValue::Set(views),
+            bot_views: ActiveValue::Set(bot_views),
+        }
+        .insert(conn)
+        .await
+        .unwrap();
+    }
+
+    /// A view of `id` at `at` that the rollup job hasn't reached yet.
+    fn view(id: &str, at: NaiveDateTime) -> views::ActiveModel {
+        views::ActiveModel {
+            short_link: ActiveValue::Set(id.to_string()),
+            cache_hit: ActiveValue::Set(false),
+            created_at: ActiveValue::Set(at),
+            ip_hash: ActiveValue::Set(Some("visitor".to_string())),
+            bot: ActiveValue::Set(false),
+            rolled_up: ActiveValue::Set(false),
+            ..Default::default()
+        }
+    }
+
+    async fn total_views(conn: &DatabaseConnection, id: &str, include_bots: bool) -> i64 {
+        short_link::Entity::find_by_id(id)
+            .select_only()
+            .column_as(link_views(include_bots), "views")
+            .into_tuple()
+            .one(conn)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn link_views_adds_pending_views_to_the_rollups() {
+        let conn = connect().await;
+        let viewed = create_link(&conn, link(None)).await;
+        daily(&conn, &viewed.id, monday(), "", 3, 1).await;
+        daily(
+            &conn,
+            &viewed.id,
+            monday() + TimeDelta::days(1),
+            "example.org",
+            2,
+            0,
+        )
+        .await;
+        views::Entity::insert_many([
+            view(&viewed.id, monday()),
+            view(&viewed.id, monday()),
+            views::ActiveModel {
+                bot: ActiveValue::Set(true),
+                ..view(&viewed.id, monday())
+            },
+            // Already counted by the rollups
+            views::ActiveModel {
+                rolled_up: ActiveValue::Set(true),
+                ..view(&viewed.id, monday())
+            },
+        ])
+        .exec(&conn)
+        .await
+        .unwrap();
+
+        assert_eq!(total_views(&conn, &viewed.id, false).await, 7);
+        assert_eq!(total_views(&conn, &viewed.id, true).await, 9);
+        let unviewed = create_link(&conn, link(None)).await;
+        assert_eq!(total_views(&conn, &unviewed.id, true).await, 0);
+    }
+
+    fn views_per_bucket(stats: &UrlStats) -> Vec<(NaiveDateTime, i64)> {
+        stats
+            .series
+            .iter()
+            .map(|point| (point.start, point.views))
+            .collect()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn rollups_and_pending_views_share_day_and_week_buckets() {
+        let conn = connect().await;
+        let viewed = create_link(&conn, link(None)).await;
+        let day = monday().date().and_hms_opt(0, 0, 0).unwrap();
+        daily(&conn, &viewed.id, day, "", 4, 2).await;
+        daily(
+            &conn,
+            &viewed.id,
+            day + TimeDelta::days(1),
+            "example.org",
+            2,
+            0,
+        )
+        .await;
+        daily(&conn, &viewed.id, day + TimeDelta::days(7), "", 5, 0).await;
+        views::Entity::insert_many([
+            views::ActiveModel {
+                referrer: ActiveValue::Set(Some("https://Example.org/post".to_string())),
+                ..view(&viewed.id, monday() + TimeDelta::days(2))
+            },
+            views::ActiveModel {
+                bot: ActiveValue::Set(true),
+                ..view(&viewed.id, monday() + TimeDelta::days(2))
+            },
+        ])
+        .exec(&conn)
+        .await
+        .unwrap();
+
+        let to = day + TimeDelta::days(3);
+        let days = StatsRange::new(params(day, to, StatsBucket::Day), to).unwrap();
+        let stats = link_stats(&conn, &viewed.id, days).await.unwrap();
+        assert_eq!(stats.views, 7);
+        assert_eq!(
+            views_per_bucket(&stats),
+            [
+                (day, 4),
+                (day + TimeDelta::days(1), 2),
+                (day + TimeDelta::days(2), 1)
+            ]
+        );
+        // The pending view's referrer is grouped by host like the rolled up ones
+        let referrers: Vec<_> = stats
+            .referrers
+            .iter()
+            .map(|referrer| (referrer.value.as_deref(), referrer.views))
+            .collect();
+        assert_eq!(referrers, [(None, 4), (Some("example.org"), 3)]);
+
+        let with_bots = StatsRange {
+            include_bots: true,
+            ..days
+        };
+        let stats = link_stats(&conn, &viewed.id, with_bots).await.unwrap();
+        assert_eq!(stats.views, 10);
+
+        let to = day + TimeDelta::weeks(2);
+        let weeks = StatsRange::new(params(day, to, StatsBucket::Week), to).unwrap();
+        let stats = link_stats(&conn, &viewed.id, weeks).await.unwrap();
+        assert_eq!(
+            views_per_bucket(&stats),
+            [(day, 7), (day + TimeDelta::weeks(1), 5)]
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn hour_buckets_read_the_hourly_rollup() {
+        let conn = connect().await;
+        let viewed = create_link(&conn, link(None)).await;
+        hourly(&conn, &viewed.id, monday(), "", 3, 1).await;
+        // Only read by day and week buckets
+        daily(&conn, &viewed.id, monday(), "", 3, 1).await;
+        views::Entity::insert_many([
+            view(&viewed.id, monday() + TimeDelta::minutes(90)),
+            views::ActiveModel {
+                ip_hash: ActiveValue::Set(Some("someone else".to_string())),
+                ..view(&viewed.id, monday() + TimeDelta::minutes(100))
+            },
+        ])
+        .exec(&conn)
+        .await
+        .unwrap();
+
+        let to = monday() + TimeDelta::hours(3);
+        let hours = StatsRange::new(params(monday(), to, StatsBucket::Hour), to).unwrap();
+        let stats = link_stats(&conn, &viewed.id, hours).await.unwrap();
+        assert_eq!(stats.views, 5);
+        assert_eq!(
+            views_per_bucket(&stats),
+            [
+                (monday(), 3),
+                (monday() + TimeDelta::hours(1), 2),
+                (monday() + TimeDelta::hours(2), 0)
+            ]
+        );
+        // Hourly visitors are counted from the views still kept
+        let visitors: Vec<_> = stats
+            .series
+            .iter()
+            .map(|point| point.unique_visitors)
+            .collect();
+        assert_eq!(visitors, [0, 2, 0]);
+    }
+}
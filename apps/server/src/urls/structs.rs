@@ -9,12 +9,13 @@ use axum::{
 use chrono::NaiveDateTime;
 use entity::short_link;
 use serde::{Deserialize, Serialize};
-use tracing::{error, info, instrument};
+use tracing::{error, info, instrument, warn};
 use ts_rs::TS;
 use utoipa::{IntoParams, IntoResponses, ToSchema};
 use uuid::Uuid;
 
-use crate::{error::ArcMutexError, utils::BasicError};
+use super::{resolve::RedirectError, validation::InvalidUrl};
+use crate::{cache::CacheError, error::ArcMutexError, utils::BasicError};
 
 #[derive(Debug, Clone, Serialize, Deserialize, IntoParams, TS)]
 #[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
@@ -101,6 +102,12 @@ impl From<image::error::ImageError> for QrCodeResponse {
     }
 }
 
+impl From<CacheError> for QrCodeResponse {
+    fn from(value: CacheError) -> Self {
+        Self::CacheError(value.to_string().into())
+    }
+}
+
 impl From<ArcMutexError> for QrCodeResponse {
     fn from(value: ArcMutexError) -> Self {
         Self::CacheError(value.to_string().into())
@@ -185,9 +192,28 @@ impl IntoResponse for QrCodeResponse {
 #[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
 pub struct NewUrlRequest {
     pub url: String,
+    #[ts(optional)]
     pub short: Option<String>,
+    #[ts(optional)]
     pub user: Option<Uuid>,
+    #[ts(optional)]
     pub expiry: Option<NaiveDateTime>,
+    #[ts(optional)]
+    pub title: Option<String>,
+    #[ts(optional)]
+    pub notes: Option<String>,
+    #[ts(optional)]
+    pub tags: Option<Vec<String>>,
+    /// Stop redirecting once the link has been clicked this many times.
+    #[ts(optional)]
+    pub max_clicks: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct DisableUrlRequest {
+    #[ts(optional)]
+    pub reason: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, IntoResponses, TS)]
@@ -203,7 +229,7 @@ pub enum NewUrlResponse {
     #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
     CacheError(#[to_schema] BasicError),
     #[response(status = StatusCode::OK)]
-    UrlCreated(#[to_schema] short_link::Model),
+    UrlCreated(#[to_schema] Box<short_link::Model>),
 }
 
 impl IntoResponse for NewUrlResponse {
@@ -240,6 +266,12 @@ impl IntoResponse for NewUrlResponse {
     }
 }
 
+impl From<CacheError> for NewUrlResponse {
+    fn from(value: CacheError) -> Self {
+        Self::CacheError(value.to_string().into())
+    }
+}
+
 impl From<ArcMutexError> for NewUrlResponse {
     fn from(value: ArcMutexError) -> Self {
         Self::CacheError(value.to_string().into())
@@ -254,6 +286,14 @@ impl From<sea_orm::DbErr> for NewUrlResponse {
     }
 }
 
+impl From<InvalidUrl> for NewUrlResponse {
+    fn from(e: InvalidUrl) -> Self {
+        NewUrlResponse::UrlParseError(BasicError {
+            error: e.to_string(),
+        })
+    }
+}
+
 impl From<url::ParseError> for NewUrlResponse {
     fn from(e: url::ParseError) -> Self {
         NewUrlResponse::UrlParseError(BasicError {
@@ -266,6 +306,8 @@ impl From<url::ParseError> for NewUrlResponse {
 #[serde(untagged)]
 #[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
 pub enum DeleteUrlResponse {
+    #[response(status = StatusCode::UNAUTHORIZED)]
+    InvalidSession(#[to_schema] BasicError),
     #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
     DatabaseError(#[to_schema] BasicError),
     #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
@@ -280,6 +322,10 @@ impl IntoResponse for DeleteUrlResponse {
     #[instrument]
     fn into_response(self) -> Response {
         match self {
+            Self::InvalidSession(e) => {
+                error!(%e);
+                (StatusCode::UNAUTHORIZED, Json(e)).into_response()
+            }
             Self::CacheError(e) => {
                 error!(%e);
                 (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
@@ -312,6 +358,12 @@ impl IntoResponse for DeleteUrlResponse {
     }
 }
 
+impl From<CacheError> for DeleteUrlResponse {
+    fn from(value: CacheError) -> Self {
+        Self::CacheError(value.to_string().into())
+    }
+}
+
 impl From<ArcMutexError> for DeleteUrlResponse {
     fn from(value: ArcMutexError) -> Self {
         Self::CacheError(value.to_string().into())
@@ -330,20 +382,28 @@ impl From<sea_orm::DbErr> for DeleteUrlResponse {
 #[serde(untagged)]
 #[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
 pub enum UpdateUrlResponse {
+    #[response(status = StatusCode::UNAUTHORIZED)]
+    InvalidSession(#[to_schema] BasicError),
     #[response(status = StatusCode::BAD_REQUEST)]
     UrlParseError(#[to_schema] BasicError),
     #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
     DatabaseError(#[to_schema] BasicError),
+    #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
+    CacheError(#[to_schema] BasicError),
     #[response(status = StatusCode::BAD_REQUEST)]
     UrlNotFound,
     #[response(status = StatusCode::OK)]
-    UrlUpdated(#[to_schema] short_link::Model),
+    UrlUpdated(#[to_schema] Box<short_link::Model>),
 }
 
 impl IntoResponse for UpdateUrlResponse {
     #[instrument]
     fn into_response(self) -> Response {
         match self {
+            Self::InvalidSession(e) => {
+                error!(%e);
+                (StatusCode::UNAUTHORIZED, Json(e)).into_response()
+            }
             UpdateUrlResponse::UrlUpdated(model) => {
                 info!("{:?}", model);
                 (StatusCode::OK, Json(model)).into_response()
@@ -362,6 +422,10 @@ impl IntoResponse for UpdateUrlResponse {
                 error!(%e);
                 (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
             }
+            Self::CacheError(e) => {
+                error!(%e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
+            }
             UpdateUrlResponse::UrlParseError(e) => {
                 error!(%e);
                 (StatusCode::BAD_REQUEST, Json(e)).into_response()
@@ -370,6 +434,18 @@ impl IntoResponse for UpdateUrlResponse {
     }
 }
 
+impl From<CacheError> for UpdateUrlResponse {
+    fn from(value: CacheError) -> Self {
+        Self::CacheError(value.to_string().into())
+    }
+}
+
+impl From<ArcMutexError> for UpdateUrlResponse {
+    fn from(value: ArcMutexError) -> Self {
+        Self::CacheError(value.to_string().into())
+    }
+}
+
 impl From<sea_orm::DbErr> for UpdateUrlResponse {
     fn from(e: sea_orm::DbErr) -> Self {
         UpdateUrlResponse::DatabaseError(BasicError {
@@ -378,6 +454,14 @@ impl From<sea_orm::DbErr> for UpdateUrlResponse {
     }
 }
 
+impl From<InvalidUrl> for UpdateUrlResponse {
+    fn from(e: InvalidUrl) -> Self {
+        UpdateUrlResponse::UrlParseError(BasicError {
+            error: e.to_string(),
+        })
+    }
+}
+
 impl From<url::ParseError> for UpdateUrlResponse {
     fn from(e: url::ParseError) -> Self {
         UpdateUrlResponse::UrlParseError(BasicError {
@@ -386,6 +470,76 @@ impl From<url::ParseError> for UpdateUrlResponse {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, IntoResponses, TS)]
+#[serde(untagged)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub enum RestoreUrlResponse {
+    #[response(status = StatusCode::UNAUTHORIZED)]
+    InvalidSession(#[to_schema] BasicError),
+    #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
+    DatabaseError(#[to_schema] BasicError),
+    #[response(status = StatusCode::NOT_FOUND)]
+    UrlNotFound,
+    #[response(status = StatusCode::GONE)]
+    RestoreWindowExpired(#[to_schema] BasicError),
+    #[response(status = StatusCode::OK)]
+    UrlRestored(#[to_schema] Box<short_link::Model>),
+    #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
+    CacheError(#[to_schema] BasicError),
+}
+
+impl IntoResponse for RestoreUrlResponse {
+    #[instrument]
+    fn into_response(self) -> Response {
+        match self {
+            Self::InvalidSession(e) => {
+                error!(%e);
+                (StatusCode::UNAUTHORIZED, Json(e)).into_response()
+            }
+            Self::UrlRestored(model) => {
+                info!("{:?}", model);
+                (StatusCode::OK, Json(model)).into_response()
+            }
+            Self::UrlNotFound => {
+                error!("URL not found in trash");
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(BasicError {
+                        error: "URL not found in trash".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+            Self::RestoreWindowExpired(e) => {
+                error!(%e);
+                (StatusCode::GONE, Json(e)).into_response()
+            }
+            Self::DatabaseError(e) => {
+                error!(%e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
+            }
+            Self::CacheError(e) => {
+                error!(%e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
+            }
+        }
+    }
+}
+
+impl From<CacheError> for RestoreUrlResponse {
+    fn from(value: CacheError) -> Self {
+        Self::CacheError(value.to_string().into())
+    }
+}
+
+impl From<sea_orm::DbErr> for RestoreUrlResponse {
+    fn from(e: sea_orm::DbErr) -> Self {
+        Self::DatabaseError(BasicError {
+            error: e.to_string(),
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, IntoResponses, TS)]
 #[serde(untagged)]
 #[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
@@ -396,8 +550,14 @@ pub enum GetUrlResponse {
     UrlNotFound,
     #[response(status = StatusCode::PERMANENT_REDIRECT)]
     Redirect(#[to_schema] String),
-    #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
-    ViewError(#[to_schema] BasicError),
+    #[response(status = StatusCode::GONE)]
+    UrlDisabled(#[to_schema] BasicError),
+    #[response(status = StatusCode::GONE)]
+    UrlExpired(#[to_schema] BasicError),
+    #[response(status = StatusCode::GONE)]
+    ClickLimitReached(#[to_schema] BasicError),
+    #[response(status = StatusCode::FORBIDDEN)]
+    UrlBlocked(#[to_schema] BasicError),
     #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
     CacheError(#[to_schema] BasicError),
 }
@@ -428,14 +588,47 @@ impl IntoResponse for GetUrlResponse {
                 info!(url);
                 Redirect::permanent(&url).into_response()
             }
-            GetUrlResponse::ViewError(e) => {
-                error!(%e);
-                (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
+            GetUrlResponse::UrlDisabled(e) => {
+                warn!(%e);
+                (StatusCode::GONE, Json(e)).into_response()
+            }
+            GetUrlResponse::UrlExpired(e) => {
+                warn!(%e);
+                (StatusCode::GONE, Json(e)).into_response()
+            }
+            GetUrlResponse::ClickLimitReached(e) => {
+                warn!(%e);
+                (StatusCode::GONE, Json(e)).into_response()
+            }
+            GetUrlResponse::UrlBlocked(e) => {
+                warn!(%e);
+                (StatusCode::FORBIDDEN, Json(e)).into_response()
             }
         }
     }
 }
 
+impl From<RedirectError> for GetUrlResponse {
+    fn from(e: RedirectError) -> Self {
+        let message = e.to_string().into();
+        match e {
+            RedirectError::NotFound => Self::UrlNotFound,
+            RedirectError::Disabled(_) => Self::UrlDisabled(message),
+            RedirectError::Expired => Self::UrlExpired(message),
+            RedirectError::ClickLimitReached => Self::ClickLimitReached(message),
+            RedirectError::Blocked => Self::UrlBlocked(message),
+            RedirectError::DbErr(_) => Self::DatabaseError(message),
+            RedirectError::CacheError(_) | RedirectError::LockError(_) => Self::CacheError(message),
+        }
+    }
+}
+
+impl From<CacheError> for GetUrlResponse {
+    fn from(value: CacheError) -> Self {
+        Self::CacheError(value.to_string().into())
+    }
+}
+
 impl From<ArcMutexError> for GetUrlResponse {
     fn from(value: ArcMutexError) -> Self {
         Self::CacheError(value.to_string().into())
@@ -450,11 +643,49 @@ impl From<sea_orm::DbErr> for GetUrlResponse {
     }
 }
 
-impl From<async_channel::SendError<crate::actor::ActorInputMessage>> for GetUrlResponse {
-    fn from(value: async_channel::SendError<crate::actor::ActorInputMessage>) -> Self {
-        Self::ViewError(BasicError {
-            error: value.to_string(),
-        })
+/// What anyone holding a short link may see of it. The owner and their notes are left out.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct PublicUrlInfo {
+    pub id: String,
+    pub short_url: String,
+    pub original_url: String,
+    #[ts(optional)]
+    pub expiry_date: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub enabled: bool,
+    #[ts(optional)]
+    pub disabled_reason: Option<String>,
+    #[ts(optional)]
+    pub title: Option<String>,
+    #[ts(optional)]
+    pub description: Option<String>,
+    #[ts(optional)]
+    pub favicon: Option<String>,
+    #[ts(optional)]
+    pub og_image: Option<String>,
+    #[ts(optional)]
+    pub max_clicks: Option<i64>,
+}
+
+impl From<short_link::Model> for PublicUrlInfo {
+    fn from(link: short_link::Model) -> Self {
+        Self {
+            id: link.id,
+            short_url: link.short_url,
+            original_url: link.original_url,
+            expiry_date: link.expiry_date,
+            created_at: link.created_at,
+            updated_at: link.updated_at,
+            enabled: link.enabled,
+            disabled_reason: link.disabled_reason,
+            title: link.title,
+            description: link.description,
+            favicon: link.favicon,
+            og_image: link.og_image,
+            max_clicks: link.max_clicks,
+        }
     }
 }
 
@@ -467,7 +698,7 @@ pub enum GetUrlInfoResponse {
     #[response(status = StatusCode::NOT_FOUND)]
     UrlNotFound,
     #[response(status = StatusCode::OK)]
-    Url(#[to_schema] short_link::Model),
+    Url(#[to_schema] Box<PublicUrlInfo>),
 }
 
 impl IntoResponse for GetUrlInfoResponse {
@@ -503,3 +734,195 @@ impl From<sea_orm::DbErr> for GetUrlInfoResponse {
         })
     }
 }
+
+/// How finely `/api/url/{id}/stats` buckets its time series.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+#[serde(rename_all = "lowercase")]
+pub enum StatsBucket {
+    Hour,
+    #[default]
+    Day,
+    Week,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, IntoParams, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+#[into_params(parameter_in = Query, style = Form)]
+pub struct StatsParams {
+    /// Start of the range (inclusive, UTC). Defaults to 30 days before `to`
+    #[ts(optional)]
+    pub from: Option<NaiveDateTime>,
+    /// End of the range (exclusive, UTC). Defaults to now
+    #[ts(optional)]
+    pub to: Option<NaiveDateTime>,
+    #[ts(optional)]
+    pub bucket: Option<StatsBucket>,
+    /// How many entries each breakdown returns, at most 100. Defaults to 10
+    #[ts(optional)]
+    pub limit: Option<u64>,
+    /// Count views from bots, crawlers and link previewers too. Defaults to false
+    #[ts(optional)]
+    pub include_bots: Option<bool>,
+}
+
+/// Views in one bucket of the time series.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct StatsPoint {
+    /// Start of the bucket
+    pub start: NaiveDateTime,
+    pub views: i64,
+    /// Distinct IP address and user agent pairs for hour buckets, and the approximate count
+    /// from the daily visitor sketches for day and week buckets. A week adds up its days, so
+    /// someone who comes back on another day counts again
+    pub unique_visitors: i64,
+}
+
+/// Views sharing one referrer host, user agent, browser, OS, device class or country. `value`
+/// is missing for views without one.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct StatsBreakdown {
+    #[ts(optional)]
+    pub value: Option<String>,
+    pub views: i64,
+    /// Distinct IP address and user agent pairs. Missing for referrers, which are counted
+    /// from the rollups
+    #[ts(optional)]
+    pub unique_visitors: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct UrlStats {
+    pub id: String,
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+    pub bucket: StatsBucket,
+    /// Whether views from bots were counted
+    pub include_bots: bool,
+    pub views: i64,
+    /// Approximate distinct human visitors over the whole days the range touches, from the
+    /// daily visitor sketches. Someone who comes back on another day counts again
+    pub unique_visitors: i64,
+    /// One point per bucket in the range, including empty ones
+    pub series: Vec<StatsPoint>,
+    /// The most common referring hosts
+    pub referrers: Vec<StatsBreakdown>,
+    /// The most common user agents
+    pub user_agents: Vec<StatsBreakdown>,
+    /// The most common browsers, with bots under their own name when they are included
+    pub browsers: Vec<StatsBreakdown>,
+    pub operating_systems: Vec<StatsBreakdown>,
+    /// Views by device class: desktop, mobile, tablet, tv, console or unknown
+    pub devices: Vec<StatsBreakdown>,
+    /// Views by ISO country code, when a GeoIP database is configured
+    pub countries: Vec<StatsBreakdown>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, IntoResponses, TS)]
+#[serde(untagged)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub enum UrlStatsResponse {
+    #[response(status = StatusCode::UNAUTHORIZED)]
+    InvalidSession(#[to_schema] BasicError),
+    #[response(status = StatusCode::BAD_REQUEST)]
+    InvalidRange(#[to_schema] BasicError),
+    #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
+    DatabaseError(#[to_schema] BasicError),
+    #[response(status = StatusCode::NOT_FOUND)]
+    UrlNotFound,
+    #[response(status = StatusCode::OK)]
+    Stats(#[to_schema] Box<UrlStats>),
+}
+
+impl IntoResponse for UrlStatsResponse {
+    #[instrument]
+    fn into_response(self) -> Response {
+        match self {
+            UrlStatsResponse::InvalidSession(e) => {
+                error!(%e);
+                (StatusCode::UNAUTHORIZED, Json(e)).into_response()
+            }
+            UrlStatsResponse::InvalidRange(e) => {
+                warn!(%e);
+                (StatusCode::BAD_REQUEST, Json(e)).into_response()
+            }
+            UrlStatsResponse::DatabaseError(e) => {
+                error!(%e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
+            }
+            UrlStatsResponse::UrlNotFound => {
+                error!("URL not found");
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(BasicError {
+                        error: "URL not found".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+            UrlStatsResponse::Stats(stats) => {
+                info!("{} views of {}", stats.views, stats.id);
+                (StatusCode::OK, Json(stats)).into_response()
+            }
+        }
+    }
+}
+
+impl From<sea_orm::DbErr> for UrlStatsResponse {
+    fn from(e: sea_orm::DbErr) -> Self {
+        UrlStatsResponse::DatabaseError(BasicError {
+            error: e.to_string(),
+        })
+    }
+}
+
+/// Why a live click stream couldn't be opened. Once open, the stream itself answers with
+/// `text/event-stream`.
+#[derive(Debug, Clone, Serialize, Deserialize, IntoResponses, TS)]
+#[serde(untagged)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub enum LiveClicksResponse {
+    #[response(status = StatusCode::UNAUTHORIZED)]
+    InvalidSession(#[to_schema] BasicError),
+    #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
+    DatabaseError(#[to_schema] BasicError),
+    #[response(status = StatusCode::NOT_FOUND)]
+    UrlNotFound,
+}
+
+impl IntoResponse for LiveClicksResponse {
+    #[instrument]
+    fn into_response(self) -> Response {
+        match self {
+            LiveClicksResponse::InvalidSession(e) => {
+                error!(%e);
+                (StatusCode::UNAUTHORIZED, Json(e)).into_response()
+            }
+            LiveClicksResponse::DatabaseError(e) => {
+                error!(%e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
+            }
+            LiveClicksResponse::UrlNotFound => {
+                error!("URL not found");
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(BasicError {
+                        error: "URL not found".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+impl From<sea_orm::DbErr> for LiveClicksResponse {
+    fn from(e: sea_orm::DbErr) -> Self {
+        LiveClicksResponse::DatabaseError(BasicError {
+            error: e.to_string(),
+        })
+    }
+}
@@ -0,0 +1,133 @@
+use thiserror::Error;
+use url::Url;
+
+use crate::config::LinkConfig;
+
+#[derive(Error, Debug)]
+pub enum InvalidUrl {
+    #[error("Unable to parse {url:?} as a URL: {source}")]
+    Parse {
+        url: String,
+        source: url::ParseError,
+    },
+    #[error("The {scheme:?} scheme is not allowed, expected one of: {allowed}")]
+    Scheme { scheme: String, allowed: String },
+    #[error("{0} has no host to redirect to")]
+    NoHost(Url),
+    #[error("{0} points back at this shortener")]
+    SelfReferential(Url),
+    #[error("Links to {0} are blocked by a domain rule")]
+    Blocked(String),
+}
+
+/// Parses and checks a link destination, returning the normalized URL to store.
+#[tracing::instrument]
+pub fn validate_target(raw: &str, config: &LinkConfig, base: &Url) -> Result<Url, InvalidUrl> {
+    let url = Url::parse(raw.trim()).map_err(|source| InvalidUrl::Parse {
+        url: raw.to_string(),
+        source,
+    })?;
+
+    if !config
+        .allowed_schemes
+        .iter()
+        .any(|scheme| scheme.eq_ignore_ascii_case(url.scheme()))
+    {
+        return Err(InvalidUrl::Scheme {
+            scheme: url.scheme().to_string(),
+            allowed: config.allowed_schemes.join(", "),
+        });
+    }
+
+    let Some(host) = url.host_str() else {
+        return Err(InvalidUrl::NoHost(url));
+    };
+
+    if base
+        .host_str()
+        .is_some_and(|base_host| base_host.eq_ignore_ascii_case(host))
+        && (base.port().is_none() || base.port_or_known_default() == url.port_or_known_default())
+    {
+        return Err(InvalidUrl::SelfReferential(url));
+    }
+
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(raw: &str) -> Result<Url, InvalidUrl> {
+        let base = Url::parse("https://sho.rt").unwrap();
+        validate_target(raw, &LinkConfig::default(), &base)
+    }
+
+    #[test]
+    fn accepts_and_normalizes_web_urls() {
+        assert_eq!(
+            validate("  HTTPS://Example.COM/a b?q=1  ")
+                .unwrap()
+                .as_str(),
+            "https://example.com/a%20b?q=1"
+        );
+        assert!(validate("http://192.0.2.1:8080/").is_ok());
+    }
+
+    #[test]
+    fn rejects_unparsable_urls() {
+        assert!(matches!(
+            validate("example.com"),
+            Err(InvalidUrl::Parse { .. })
+        ));
+        assert!(matches!(
+            validate("https://"),
+            Err(InvalidUrl::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_other_schemes() {
+        for raw in [
+            "javascript:alert(1)",
+            "ftp://example.com/",
+            "data:text/html,hi",
+        ] {
+            assert!(
+                matches!(validate(raw), Err(InvalidUrl::Scheme { .. })),
+                "{raw} was allowed"
+            );
+        }
+        let config = LinkConfig {
+            allowed_schemes: vec!["HTTPS".to_string(), "mailto".to_string()],
+            ..Default::default()
+        };
+        let base = Url::parse("https://sho.rt").unwrap();
+        assert!(validate_target("https://example.com", &config, &base).is_ok());
+        assert!(matches!(
+            validate_target("mailto:someone@example.com", &config, &base),
+            Err(InvalidUrl::NoHost(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_links_back_to_the_shortener() {
+        assert!(matches!(
+            validate("https://SHO.RT/abc"),
+            Err(InvalidUrl::SelfReferential(_))
+        ));
+        assert!(matches!(
+            validate("http://sho.rt/abc"),
+            Err(InvalidUrl::SelfReferential(_))
+        ));
+        assert!(validate("https://www.sho.rt/abc").is_ok());
+
+        let base = Url::parse("http://localhost:3000").unwrap();
+        let config = LinkConfig::default();
+        assert!(matches!(
+            validate_target("http://localhost:3000/abc", &config, &base),
+            Err(InvalidUrl::SelfReferential(_))
+        ));
+        assert!(validate_target("http://localhost:8080/abc", &config, &base).is_ok());
+    }
+}
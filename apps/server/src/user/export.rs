@@ -0,0 +1,550 @@
+use std::{collections::BTreeMap, io, sync::Arc};
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Int32Array, RecordBatch, StringArray, TimestampMicrosecondArray,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef, TimeUnit};
+use axum::{
+    body::{Body, Bytes},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use entity::views;
+use futures_util::stream;
+use parquet::{
+    arrow::ArrowWriter, basic::Compression, errors::ParquetError,
+    file::properties::WriterProperties,
+};
+use sea_orm::{Condition, DatabaseConnection, DbErr, entity::*, query::*};
+use serde::Serialize;
+use thiserror::Error;
+use tracing::error;
+use utoipa::{
+    IntoResponses,
+    openapi::{ContentBuilder, RefOr, ResponseBuilder},
+};
+
+use super::structs::{ExportFormat, UserView};
+
+/// How many views are read from the database, and encoded, at a time.
+const EXPORT_BATCH: u64 = 10_000;
+
+/// The exported columns, in order: the short link a view belongs to, then `UserView`'s.
+const COLUMNS: [&str; 10] = [
+    "short_link",
+    "id",
+    "headers",
+    "ip",
+    "ip_hash",
+    "referrer",
+    "user_agent",
+    "bot",
+    "cache_hit",
+    "created_at",
+];
+
+#[derive(Debug, Error)]
+enum ExportError {
+    #[error("Database error: {0}")]
+    Db(#[from] DbErr),
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Arrow error: {0}")]
+    Arrow(#[from] ArrowError),
+    #[error("Parquet error: {0}")]
+    Parquet(#[from] ParquetError),
+}
+
+/// A response streaming exported views as a file download.
+pub struct ClickExport(Response);
+
+impl IntoResponse for ClickExport {
+    fn into_response(self) -> Response {
+        self.0
+    }
+}
+
+impl IntoResponses for ClickExport {
+    fn responses() -> BTreeMap<String, RefOr<utoipa::openapi::response::Response>> {
+        let response = ResponseBuilder::new()
+            .description(
+                "The views as an attachment, one row per view with the link's ID followed by the \
+                 columns of `UserView`",
+            )
+            .content(
+                ExportFormat::Csv.content_type(),
+                ContentBuilder::new().build(),
+            )
+            .content(
+                ExportFormat::Ndjson.content_type(),
+                ContentBuilder::new().build(),
+            )
+            .content(
+                ExportFormat::Parquet.content_type(),
+                ContentBuilder::new().build(),
+            )
+            .build();
+        BTreeMap::from([("200".to_string(), response.into())])
+    }
+}
+
+/// One exported view.
+#[derive(Debug, Serialize)]
+struct ExportRow {
+    short_link: String,
+    #[serde(flatten)]
+    view: UserView,
+}
+
+impl From<views::Model> for ExportRow {
+    fn from(view: views::Model) -> Self {
+        Self {
+            short_link: view.short_link.clone(),
+            view: view.into(),
+        }
+    }
+}
+
+impl ExportRow {
+    /// The headers as a JSON object, since neither CSV nor the Parquet schema has a map column.
+    fn headers_json(&self) -> Result<Option<String>, serde_json::Error> {
+        self.view
+            .headers
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+    }
+
+    fn csv_record(&self) -> Result<[String; COLUMNS.len()], serde_json::Error> {
+        let view = &self.view;
+        Ok([
+            self.short_link.clone(),
+            view.id.to_string(),
+            self.headers_json()?.unwrap_or_default(),
+            view.ip.clone().unwrap_or_default(),
+            view.ip_hash.clone().unwrap_or_default(),
+            view.referrer.clone().unwrap_or_default(),
+            view.user_agent.clone().unwrap_or_default(),
+            view.bot.to_string(),
+            view.cache_hit.to_string(),
+            view.created_at.format("%Y-%m-%dT%H:%M:%S%.f").to_string(),
+        ])
+    }
+}
+
+fn parquet_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new(COLUMNS[0], DataType::Utf8, false),
+        Field::new(COLUMNS[1], DataType::Int32, false),
+        Field::new(COLUMNS[2], DataType::Utf8, true),
+        Field::new(COLUMNS[3], DataType::Utf8, true),
+        Field::new(COLUMNS[4], DataType::Utf8, true),
+        Field::new(COLUMNS[5], DataType::Utf8, true),
+        Field::new(COLUMNS[6], DataType::Utf8, true),
+        Field::new(COLUMNS[7], DataType::Boolean, false),
+        Field::new(COLUMNS[8], DataType::Boolean, false),
+        Field::new(
+            COLUMNS[9],
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            false,
+        ),
+    ]))
+}
+
+fn record_batch(schema: &SchemaRef, rows: &[ExportRow]) -> Result<RecordBatch, ExportError> {
+    let headers = rows
+        .iter()
+        .map(ExportRow::headers_json)
+        .collect::<Result<Vec<_>, _>>()?;
+    let text = |f: fn(&UserView) -> Option<&str>| -> ArrayRef {
+        Arc::new(StringArray::from_iter(rows.iter().map(|row| f(&row.view))))
+    };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|row| row.short_link.as_str()),
+        )),
+        Arc::new(Int32Array::from_iter_values(
+            rows.iter().map(|row| row.view.id),
+        )),
+        Arc::new(StringArray::from(headers)),
+        text(|view| view.ip.as_deref()),
+        text(|view| view.ip_hash.as_deref()),
+        text(|view| view.referrer.as_deref()),
+        text(|view| view.user_agent.as_deref()),
+        Arc::new(BooleanArray::from_iter(
+            rows.iter().map(|row| Some(row.view.bot)),
+        )),
+        Arc::new(BooleanArray::from_iter(
+            rows.iter().map(|row| Some(row.view.cache_hit)),
+        )),
+        Arc::new(
+            TimestampMicrosecondArray::from_iter_values(
+                rows.iter()
+                    .map(|row| row.view.created_at.and_utc().timestamp_micros()),
+            )
+            .with_timezone("UTC"),
+        ),
+    ];
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+/// Encodes batches of rows into the chunks of the exported file.
+enum Encoder {
+    Csv { header_written: bool },
+    Ndjson,
+    Parquet(Box<ArrowWriter<Vec<u8>>>),
+}
+
+impl Encoder {
+    fn new(format: ExportFormat) -> Self {
+        match format {
+            ExportFormat::Csv => Self::Csv {
+                header_written: false,
+            },
+            ExportFormat::Ndjson => Self::Ndjson,
+            ExportFormat::Parquet => {
+                let props = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                Self::Parquet(Box::new(
+                    ArrowWriter::try_new(Vec::new(), parquet_schema(), Some(props))
+                        .expect("The export schema converts to Parquet"),
+                ))
+            }
+        }
+    }
+
+    fn csv_header(buf: &mut Vec<u8>, header_written: &mut bool) -> Result<(), ExportError> {
+        if !*header_written {
+            let mut writer = csv::Writer::from_writer(buf);
+            writer.write_record(COLUMNS)?;
+            writer.flush().map_err(csv::Error::from)?;
+            *header_written = true;
+        }
+        Ok(())
+    }
+
+    fn encode(&mut self, rows: &[ExportRow]) -> Result<Vec<u8>, ExportError> {
+        let mut buf = Vec::new();
+        match self {
+            Self::Csv { header_written } => {
+                Self::csv_header(&mut buf, header_written)?;
+                let mut writer = csv::Writer::from_writer(&mut buf);
+                for row in rows {
+                    writer.write_record(row.csv_record()?)?;
+                }
+                writer.flush().map_err(csv::Error::from)?;
+            }
+            Self::Ndjson => {
+                for row in rows {
+                    serde_json::to_writer(&mut buf, row)?;
+                    buf.push(b'\n');
+                }
+            }
+            Self::Parquet(writer) => {
+                writer.write(&record_batch(&parquet_schema(), rows)?)?;
+                // Each batch becomes its own row group, so the encoded bytes can be sent on
+                // rather than held until the file is closed
+                writer.flush()?;
+                buf = std::mem::take(writer.inner_mut());
+            }
+        }
+        Ok(buf)
+    }
+
+    /// The end of the file: the header of an empty CSV file or the Parquet footer.
+    fn finish(self) -> Result<Vec<u8>, ExportError> {
+        let mut buf = Vec::new();
+        match self {
+            Self::Csv { mut header_written } => Self::csv_header(&mut buf, &mut header_written)?,
+            Self::Ndjson => {}
+            Self::Parquet(writer) => buf = writer.into_inner()?,
+        }
+        Ok(buf)
+    }
+}
+
+struct ExportState {
+    conn: DatabaseConnection,
+    cond: Condition,
+    /// How many views are read at a time.
+    batch: u64,
+    last: Option<i32>,
+    encoder: Option<Encoder>,
+}
+
+impl ExportState {
+    /// The next chunk of the file, or `None` once it has all been sent.
+    async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, ExportError> {
+        loop {
+            let Some(encoder) = self.encoder.as_mut() else {
+                return Ok(None);
+            };
+            let mut select = views::Entity::find().filter(self.cond.clone());
+            if let Some(last) = self.last {
+                select = select.filter(views::Column::Id.gt(last));
+            }
+            let batch = select
+                .order_by_asc(views::Column::Id)
+                .limit(self.batch)
+                .all(&self.conn)
+                .await?;
+            let Some(last) = batch.last() else {
+                let encoder = self.encoder.take().expect("The encoder was just borrowed");
+                return Ok(Some(encoder.finish()?));
+            };
+            self.last = Some(last.id);
+            let rows: Vec<ExportRow> = batch.into_iter().map(ExportRow::from).collect();
+            let chunk = encoder.encode(&rows)?;
+            if !chunk.is_empty() {
+                return Ok(Some(chunk));
+            }
+        }
+    }
+}
+
+/// Streams the views matching `cond` as an attachment in `format`. Views are read in batches
+/// of their IDs, so only one batch is held in memory at a time. An error part way through is
+/// logged and aborts the response, rather than ending it as if the file were complete.
+pub fn click_export(
+    conn: DatabaseConnection,
+    cond: Condition,
+    format: ExportFormat,
+) -> ClickExport {
+    let state = ExportState {
+        conn,
+        cond,
+        batch: EXPORT_BATCH,
+        last: None,
+        encoder: Some(Encoder::new(format)),
+    };
+    let chunks = stream::unfold(Some(state), |state| async move {
+        let mut state = state?;
+        match state.next_chunk().await {
+            Ok(Some(chunk)) => Some((Ok(Bytes::from(chunk)), Some(state))),
+            Ok(None) => None,
+            Err(e) => {
+                error!("Export failed: {e}");
+                Some((Err(io::Error::other(e)), None))
+            }
+        }
+    });
+    let response = (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"clicks.{}\"", format.extension()),
+            ),
+        ],
+        Body::from_stream(chunks),
+    )
+        .into_response();
+    ClickExport(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveDateTime};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+    use crate::testing::{connect, create_link, link};
+
+    fn created_at() -> NaiveDateTime {
+        DateTime::from_timestamp(1_767_600_000, 123_000)
+            .unwrap()
+            .naive_utc()
+    }
+
+    fn row(id: i32) -> ExportRow {
+        ExportRow {
+            short_link: "abc".to_string(),
+            view: UserView {
+                id,
+                headers: Some(BTreeMap::from([(
+                    "accept-language".to_string(),
+                    vec!["en".to_string()],
+                )])),
+                ip: Some("192.0.2.0/24".to_string()),
+                ip_hash: None,
+                referrer: Some("https://example.org/, with a comma".to_string()),
+                user_agent: Some("Mozilla/5.0".to_string()),
+                bot: false,
+                cache_hit: true,
+                created_at: created_at(),
+            },
+        }
+    }
+
+    /// Encodes `batches` the way an export sends them, returning the whole file.
+    fn export(format: ExportFormat, batches: &[&[ExportRow]]) -> Vec<u8> {
+        let mut encoder = Encoder::new(format);
+        let mut file = Vec::new();
+        for rows in batches {
+            file.extend(encoder.encode(rows).unwrap());
+        }
+        file.extend(encoder.finish().unwrap());
+        file
+    }
+
+    #[test]
+    fn columns_match_the_serialized_row() {
+        let json = serde_json::to_string(&row(1)).unwrap();
+        let keys = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&json)
+            .unwrap()
+            .len();
+        assert_eq!(keys, COLUMNS.len());
+        // Fields are serialized in order, so the columns must appear in the same order
+        let positions: Vec<_> = COLUMNS
+            .iter()
+            .map(|column| {
+                json.find(&format!("\"{column}\":"))
+                    .unwrap_or_else(|| panic!("{column} isn't serialized"))
+            })
+            .collect();
+        assert!(positions.is_sorted(), "{json}");
+
+        let schema = parquet_schema();
+        let fields: Vec<_> = schema.fields().iter().map(|field| field.name()).collect();
+        assert_eq!(fields, COLUMNS);
+    }
+
+    #[test]
+    fn csv_has_one_header_and_a_record_per_view() {
+        let file = export(ExportFormat::Csv, &[&[row(1), row(2)], &[row(3)]]);
+        let mut reader = csv::Reader::from_reader(file.as_slice());
+        assert_eq!(reader.headers().unwrap(), COLUMNS.as_slice());
+        let records: Vec<_> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0].iter().collect::<Vec<_>>(),
+            [
+                "abc",
+                "1",
+                r#"{"accept-language":["en"]}"#,
+                "192.0.2.0/24",
+                "",
+                "https://example.org/, with a comma",
+                "Mozilla/5.0",
+                "false",
+                "true",
+                "2026-01-05T08:00:00.000123",
+            ]
+        );
+        assert_eq!(&records[2][1], "3");
+
+        // An export without views is still a valid file with its header
+        let empty = export(ExportFormat::Csv, &[]);
+        let mut reader = csv::Reader::from_reader(empty.as_slice());
+        assert_eq!(reader.headers().unwrap(), COLUMNS.as_slice());
+        assert_eq!(reader.records().count(), 0);
+    }
+
+    #[test]
+    fn ndjson_has_a_line_per_view() {
+        let file = export(ExportFormat::Ndjson, &[&[row(1)], &[row(2)]]);
+        let lines: Vec<serde_json::Value> = String::from_utf8(file)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["short_link"], "abc");
+        assert_eq!(lines[0]["headers"]["accept-language"][0], "en");
+        assert_eq!(lines[0]["ip_hash"], serde_json::Value::Null);
+        assert_eq!(lines[1]["id"], 2);
+
+        assert!(export(ExportFormat::Ndjson, &[]).is_empty());
+    }
+
+    #[test]
+    fn parquet_has_a_row_group_per_batch() {
+        let file = export(ExportFormat::Parquet, &[&[row(1), row(2)], &[row(3)]]);
+        let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(file)).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+        let batches: Vec<_> = reader.build().unwrap().map(Result::unwrap).collect();
+        let column = |index: usize| batches.iter().map(move |batch| batch.column(index));
+        let ids: Vec<_> = column(1)
+            .flat_map(|ids| {
+                ids.as_any()
+                    .downcast_ref::<Int32Array>()
+                    .unwrap()
+                    .values()
+                    .to_vec()
+            })
+            .collect();
+        assert_eq!(ids, [1, 2, 3]);
+        let created = column(9).next().unwrap();
+        let created = created
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        assert_eq!(created.value(0), created_at().and_utc().timestamp_micros());
+        let hashes = column(4).next().unwrap();
+        assert!(hashes.is_null(0));
+
+        // Without views the file is just the footer, which still describes the columns
+        let empty = export(ExportFormat::Parquet, &[]);
+        let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(empty)).unwrap();
+        assert_eq!(reader.schema().fields().len(), COLUMNS.len());
+        assert_eq!(reader.metadata().num_row_groups(), 0);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated Postgres database in TEST_DATABASE_URL"]
+    async fn views_are_read_a_batch_at_a_time_in_id_order() {
+        let conn = connect().await;
+        let exported = create_link(&conn, link(None)).await;
+        let other = create_link(&conn, link(None)).await;
+        let view = |short_link: &str| views::ActiveModel {
+            short_link: ActiveValue::Set(short_link.to_string()),
+            cache_hit: ActiveValue::Set(false),
+            created_at: ActiveValue::Set(created_at()),
+            bot: ActiveValue::Set(false),
+            rolled_up: ActiveValue::Set(false),
+            ..Default::default()
+        };
+        let mut ids = Vec::new();
+        for short_link in [
+            &exported.id,
+            &other.id,
+            &exported.id,
+            &exported.id,
+            &exported.id,
+            &exported.id,
+        ] {
+            let inserted = view(short_link).insert(&conn).await.unwrap();
+            if *short_link == exported.id {
+                ids.push(i64::from(inserted.id));
+            }
+        }
+
+        let mut state = ExportState {
+            conn,
+            cond: Condition::all().add(views::Column::ShortLink.eq(&exported.id)),
+            batch: 2,
+            last: None,
+            encoder: Some(Encoder::new(ExportFormat::Ndjson)),
+        };
+        let mut chunks = Vec::new();
+        while let Some(chunk) = state.next_chunk().await.unwrap() {
+            chunks.push(chunk);
+        }
+        // Three full batches, then the empty end of the file
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks[3].is_empty());
+        let exported: Vec<i64> = String::from_utf8(chunks.concat())
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["id"]
+                    .as_i64()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(exported, ids);
+    }
+}
@@ -1,11 +1,16 @@
+pub mod export;
 pub mod local;
 pub mod oidc;
 pub mod routes;
 pub mod structs;
 
+use axum_extra::extract::cookie::PrivateCookieJar;
+use entity::sessions;
+use sea_orm::{DbErr, entity::*, query::*};
 use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
 
-use crate::state::ServerState;
+use crate::{state::ServerState, utils::BasicError};
 
 pub const OIDC_TAG: &str = "oidc-users";
 pub const USER_TAG: &str = "user";
@@ -14,6 +19,26 @@ pub const OIDC_PREFIX: &str = "/api/user/oidc";
 pub const USER_PREFIX: &str = "/api/user";
 pub const LOCAL_PREFIX: &str = "/api/user/local";
 
+/// Resolves the session cookie to the signed in user's id, reporting a missing or unknown
+/// session with `invalid`.
+pub(crate) async fn session_user<E: From<DbErr>>(
+    jar: &PrivateCookieJar,
+    state: &ServerState,
+    invalid: fn(BasicError) -> E,
+) -> Result<Uuid, E> {
+    let Some(cookie) = jar.get("sid").map(|cookie| cookie.value().to_owned()) else {
+        return Err(invalid("User not logged in".to_string().into()));
+    };
+    let Some(session) = sessions::Entity::find()
+        .filter(sessions::Column::SessionId.eq(cookie))
+        .one(&state.conn)
+        .await?
+    else {
+        return Err(invalid("User not found".to_string().into()));
+    };
+    Ok(session.user_id)
+}
+
 pub fn oidc_router(state: ServerState) -> OpenApiRouter {
     OpenApiRouter::new()
         .routes(routes!(oidc::get_oidc_provider))
@@ -35,5 +60,8 @@ pub fn user_router(state: ServerState) -> OpenApiRouter {
         .routes(routes!(routes::logout))
         .routes(routes!(routes::get_user_urls))
         .routes(routes!(routes::get_user_url_page))
+        .routes(routes!(routes::get_user_trash))
+        .routes(routes!(routes::get_user_live))
+        .routes(routes!(routes::export_views))
         .with_state(state)
 }
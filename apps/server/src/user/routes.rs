@@ -3,12 +3,102 @@ use axum::{
     extract::{Query, State},
 };
 use axum_extra::extract::cookie::PrivateCookieJar;
-use entity::{sessions, short_link, user, views};
-use sea_orm::{entity::*, query::*};
+use chrono::Utc;
+use entity::{sessions, short_link, short_link_tag, tag, user, views};
+use sea_orm::{
+    entity::*,
+    query::*,
+    sea_query::{Expr, SimpleExpr},
+};
 use tracing::instrument;
 
-use super::structs::{LogoutResponse, Paginate, UserLink, UserLinksResponse, UserProfileResponse};
-use crate::state::ServerState;
+use super::{
+    export::{ClickExport, click_export},
+    session_user,
+    structs::{
+        ExportParams, ExportResponse, LinkQuery, LinkSort, LogoutResponse, Paginate, SortOrder,
+        UserLink, UserLinksAndViews, UserLinksResponse, UserProfileResponse, ViewFilter,
+    },
+};
+use crate::{
+    state::ServerState,
+    urls::{
+        live::{ClickFilter, LiveClicks, live_clicks},
+        stats::link_views,
+        structs::LiveClicksResponse,
+    },
+    visitors::link_unique_visitors,
+};
+
+/// The search as a `tsquery`, matching words from titles once stemmed as English and words from
+/// URLs and slugs as they were typed.
+const SEARCH_QUERY: &str =
+    "(websearch_to_tsquery('english', $1) || websearch_to_tsquery('simple', $1))";
+
+fn search_terms(query: &LinkQuery) -> Option<&str> {
+    query
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+fn filter_links(
+    select: Select<short_link::Entity>,
+    query: &LinkQuery,
+) -> Select<short_link::Entity> {
+    let mut select = select;
+    if let Some(name) = query.tag.as_deref() {
+        select = select.filter(
+            short_link::Column::Id.in_subquery(
+                short_link_tag::Entity::find()
+                    .select_only()
+                    .column(short_link_tag::Column::ShortLink)
+                    .inner_join(tag::Entity)
+                    .filter(tag::Column::Name.eq(name))
+                    .into_query(),
+            ),
+        );
+    }
+    if let Some(search) = search_terms(query) {
+        select = select.filter(Expr::cust_with_values(
+            format!(r#""short_link"."search" @@ {SEARCH_QUERY}"#),
+            [search],
+        ));
+    }
+    select
+}
+
+/// How many views each link has, leaving bots out unless `filter` asks for them.
+fn view_count(filter: ViewFilter) -> SimpleExpr {
+    link_views(filter.include_bots.unwrap_or_default())
+}
+
+fn sort_links(
+    select: Select<short_link::Entity>,
+    query: &LinkQuery,
+    clicks: SimpleExpr,
+) -> Select<short_link::Entity> {
+    let order = match query.order.unwrap_or_default() {
+        SortOrder::Asc => Order::Asc,
+        SortOrder::Desc => Order::Desc,
+    };
+    // Without an explicit sort, search results come best match first
+    if let (None, Some(search)) = (query.sort, search_terms(query)) {
+        return select
+            .order_by_desc(Expr::cust_with_values(
+                format!(r#"ts_rank("short_link"."search", {SEARCH_QUERY})"#),
+                [search],
+            ))
+            .order_by_asc(short_link::Column::Id);
+    }
+    match query.sort.unwrap_or_default() {
+        LinkSort::Created => select.order_by(short_link::Column::CreatedAt, order),
+        LinkSort::Updated => select.order_by(short_link::Column::UpdatedAt, order),
+        LinkSort::Clicks => select.order_by(clicks, order),
+    }
+    .order_by_asc(short_link::Column::Id)
+}
 
 // /auth/logout
 #[instrument]
@@ -103,11 +193,14 @@ pub async fn get_user(
     get,
     path = "/urls",
     context_path = super::USER_PREFIX,
+    params(LinkQuery, ViewFilter),
     responses(UserLinksResponse),
     tag = super::USER_TAG,
     security(("session_id" = [])),
 )]
 pub async fn get_user_urls(
+    Query(query): Query<LinkQuery>,
+    Query(filter): Query<ViewFilter>,
     jar: PrivateCookieJar,
     State(state): State<ServerState>,
 ) -> Result<UserLinksResponse, UserLinksResponse> {
@@ -129,14 +222,26 @@ pub async fn get_user_urls(
         ));
     };
 
-    let res: Vec<UserLink> = short_link::Entity::find()
+    let clicks = view_count(filter);
+    let select = short_link::Entity::find()
         .filter(short_link::Column::UserId.eq(res.user_id))
-        .left_join(views::Entity)
-        .column_as(views::Column::Id.count(), "views")
+        .filter(short_link::Column::DeletedAt.is_null());
+    let mut res: Vec<UserLink> = sort_links(filter_links(select, &query), &query, clicks.clone())
+        .left_join(short_link_tag::Entity)
+        .join(JoinType::LeftJoin, short_link_tag::Relation::Tag.def())
+        .column_as(clicks, "views")
+        .column_as(
+            Expr::cust(r#"array_remove(array_agg(DISTINCT "tag"."name"), NULL)"#),
+            "tags",
+        )
         .group_by(short_link::Column::Id)
         .into_model::<UserLink>()
         .all(&state.conn)
         .await?;
+    let unique = link_unique_visitors(&state.conn, res.iter().map(|link| link.id.clone())).await?;
+    for link in &mut res {
+        link.unique_visitors = unique.get(&link.id).copied().unwrap_or_default();
+    }
 
     Ok(UserLinksResponse::UserLinks(res))
 }
@@ -147,13 +252,15 @@ pub async fn get_user_urls(
     get,
     path = "/urls/page",
     context_path = super::USER_PREFIX,
-    params(Paginate),
+    params(Paginate, LinkQuery, ViewFilter),
     responses(UserLinksResponse),
     tag = super::USER_TAG,
     security(("session_id" = [])),
 )]
 pub async fn get_user_url_page(
     Query(paginate): Query<Paginate>,
+    Query(query): Query<LinkQuery>,
+    Query(filter): Query<ViewFilter>,
     jar: PrivateCookieJar,
     State(state): State<ServerState>,
 ) -> Result<UserLinksResponse, UserLinksResponse> {
@@ -179,20 +286,173 @@ pub async fn get_user_url_page(
 
     let mut models = Vec::new();
 
-    let links = short_link::Entity::find()
-        .filter(short_link::Column::UserId.eq(res.user_id))
+    let select = filter_links(
+        short_link::Entity::find()
+            .filter(short_link::Column::UserId.eq(res.user_id))
+            .filter(short_link::Column::DeletedAt.is_null()),
+        &query,
+    );
+    let links = sort_links(select, &query, view_count(filter))
         .paginate(&txn, paginate.size)
         .fetch_page(paginate.page)
         .await?;
     for link in links {
-        let vs = views::Entity::find()
-            .filter(views::Column::ShortLink.eq(link.id.clone()))
+        let mut vs = views::Entity::find().filter(views::Column::ShortLink.eq(link.id.clone()));
+        if !filter.include_bots.unwrap_or_default() {
+            vs = vs.filter(views::Column::Bot.eq(false));
+        }
+        let vs = vs.all(&txn).await?;
+        let tags = link
+            .find_related(tag::Entity)
+            .order_by_asc(tag::Column::Name)
             .all(&txn)
             .await?;
-        models.push((link, vs));
+        models.push((link, vs, tags));
+    }
+    let mut links: UserLinksAndViews = models.into();
+    let unique = link_unique_visitors(&txn, links.urls.iter().map(|link| link.id.clone())).await?;
+    for link in &mut links.urls {
+        link.unique_visitors = unique.get(&link.id).copied().unwrap_or_default();
     }
 
     txn.commit().await?;
 
-    Ok(UserLinksResponse::UserLinksAndViews(models.into()))
+    Ok(UserLinksResponse::UserLinksAndViews(links))
+}
+
+// /api/user/urls/trash
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    get,
+    path = "/urls/trash",
+    context_path = super::USER_PREFIX,
+    params(ViewFilter),
+    responses(UserLinksResponse),
+    tag = super::USER_TAG,
+    security(("session_id" = [])),
+)]
+pub async fn get_user_trash(
+    Query(filter): Query<ViewFilter>,
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<UserLinksResponse, UserLinksResponse> {
+    let user = session_user(&jar, &state, UserLinksResponse::InvalidSession).await?;
+
+    let mut res: Vec<UserLink> = short_link::Entity::find()
+        .filter(short_link::Column::UserId.eq(user))
+        .filter(short_link::Column::DeletedAt.is_not_null())
+        .left_join(short_link_tag::Entity)
+        .join(JoinType::LeftJoin, short_link_tag::Relation::Tag.def())
+        .column_as(view_count(filter), "views")
+        .column_as(
+            Expr::cust(r#"array_remove(array_agg(DISTINCT "tag"."name"), NULL)"#),
+            "tags",
+        )
+        .group_by(short_link::Column::Id)
+        .order_by_desc(short_link::Column::DeletedAt)
+        .into_model::<UserLink>()
+        .all(&state.conn)
+        .await?;
+    let unique = link_unique_visitors(&state.conn, res.iter().map(|link| link.id.clone())).await?;
+    for link in &mut res {
+        link.unique_visitors = unique.get(&link.id).copied().unwrap_or_default();
+    }
+
+    Ok(UserLinksResponse::UserLinks(res))
+}
+
+// /api/user/live
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    get,
+    path = "/live",
+    context_path = super::USER_PREFIX,
+    responses(LiveClicksResponse),
+    tag = super::USER_TAG,
+    security(("session_id" = [])),
+)]
+pub async fn get_user_live(
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<LiveClicks, LiveClicksResponse> {
+    let user = session_user(&jar, &state, LiveClicksResponse::InvalidSession).await?;
+
+    Ok(live_clicks(
+        state.pool.subscribe_clicks(),
+        ClickFilter::Owner(user),
+    ))
+}
+
+// /api/user/export
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    get,
+    path = "/export",
+    context_path = super::USER_PREFIX,
+    params(ExportParams),
+    responses(ClickExport, ExportResponse),
+    tag = super::USER_TAG,
+    security(("session_id" = [])),
+)]
+pub async fn export_views(
+    Query(params): Query<ExportParams>,
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<ClickExport, ExportResponse> {
+    let user = session_user(&jar, &state, ExportResponse::InvalidSession).await?;
+
+    let to = params.to.unwrap_or_else(|| Utc::now().naive_utc());
+    if params.from.is_some_and(|from| from >= to) {
+        return Err(ExportResponse::InvalidRange(
+            "The start of the range must come before its end".into(),
+        ));
+    }
+
+    // Trashed links are included, their views are still the user's
+    let mut links = short_link::Entity::find()
+        .select_only()
+        .column(short_link::Column::Id)
+        .filter(short_link::Column::UserId.eq(user));
+    if let Some(id) = params.link.as_deref() {
+        if short_link::Entity::find_by_id(id)
+            .filter(short_link::Column::UserId.eq(user))
+            .one(&state.conn)
+            .await?
+            .is_none()
+        {
+            return Err(ExportResponse::UrlNotFound);
+        }
+        links = links.filter(short_link::Column::Id.eq(id));
+    }
+    if let Some(name) = params.tag.as_deref() {
+        links = links.filter(
+            short_link::Column::Id.in_subquery(
+                short_link_tag::Entity::find()
+                    .select_only()
+                    .column(short_link_tag::Column::ShortLink)
+                    .inner_join(tag::Entity)
+                    .filter(tag::Column::Name.eq(name))
+                    .into_query(),
+            ),
+        );
+    }
+
+    let mut cond = Condition::all()
+        .add(views::Column::ShortLink.in_subquery(links.into_query()))
+        .add(views::Column::CreatedAt.lt(to));
+    if let Some(from) = params.from {
+        cond = cond.add(views::Column::CreatedAt.gte(from));
+    }
+    if !params.include_bots.unwrap_or(false) {
+        cond = cond.add(views::Column::Bot.eq(false));
+    }
+
+    Ok(click_export(
+        state.conn.clone(),
+        cond,
+        params.format.unwrap_or_default(),
+    ))
 }
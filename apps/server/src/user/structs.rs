@@ -7,7 +7,7 @@ use axum::{
 };
 use axum_extra::extract::cookie::PrivateCookieJar;
 use chrono::NaiveDateTime;
-use entity::{short_link, user, views};
+use entity::{short_link, tag, user, views};
 use openidconnect::{
     ClaimsVerificationError, ConfigurationError, HttpClientError, RequestTokenError,
     SignatureVerificationError, SigningError, StandardErrorResponse, UserInfoError,
@@ -20,9 +20,7 @@ use ts_rs::TS;
 use utoipa::{IntoParams, IntoResponses, ToSchema};
 use uuid::Uuid;
 
-#[cfg(feature = "headers")]
-use crate::utils::HeaderMapDef;
-use crate::utils::{BasicError, BasicResponse};
+use crate::utils::{BasicError, BasicResponse, HeaderMapDef, display_ip};
 
 #[derive(Debug, Clone, Serialize, Deserialize, IntoParams, TS)]
 #[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
@@ -32,6 +30,106 @@ pub struct Paginate {
     pub size: u64,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+#[into_params(parameter_in = Query, style = Form)]
+pub struct LinkQuery {
+    /// Only return links carrying this tag
+    #[ts(optional)]
+    pub tag: Option<String>,
+    /// Full-text search over the title, original URL and slug. Supports `"quoted phrases"`,
+    /// `or` and `-excluded` words
+    #[ts(optional)]
+    pub search: Option<String>,
+    /// Defaults to relevance when searching, and to `created` otherwise
+    #[ts(optional)]
+    pub sort: Option<LinkSort>,
+    #[ts(optional)]
+    pub order: Option<SortOrder>,
+}
+
+/// Which views count towards a link's clicks.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, IntoParams, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+#[into_params(parameter_in = Query, style = Form)]
+pub struct ViewFilter {
+    /// Count views from bots, crawlers and link previewers too. Defaults to false
+    #[ts(optional)]
+    pub include_bots: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+#[serde(rename_all = "lowercase")]
+pub enum LinkSort {
+    #[default]
+    Created,
+    Updated,
+    Clicks,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// The file formats views can be exported as.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    /// One JSON object per line
+    Ndjson,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Ndjson => "application/x-ndjson",
+            Self::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Ndjson => "ndjson",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+#[into_params(parameter_in = Query, style = Form)]
+pub struct ExportParams {
+    /// Only export the views of this link
+    #[ts(optional)]
+    pub link: Option<String>,
+    /// Only export the views of links carrying this tag
+    #[ts(optional)]
+    pub tag: Option<String>,
+    /// Start of the range (inclusive, UTC). Defaults to the first view
+    #[ts(optional)]
+    pub from: Option<NaiveDateTime>,
+    /// End of the range (exclusive, UTC). Defaults to now
+    #[ts(optional)]
+    pub to: Option<NaiveDateTime>,
+    #[ts(optional)]
+    pub format: Option<ExportFormat>,
+    /// Export views from bots, crawlers and link previewers too. Defaults to false
+    #[ts(optional)]
+    pub include_bots: Option<bool>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
 #[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
 pub struct OidcName {
@@ -294,8 +392,8 @@ pub struct UserLinksAndViews {
     pub urls: Vec<UserLinkWithViews>,
 }
 
-impl From<Vec<(short_link::Model, Vec<views::Model>)>> for UserLinksAndViews {
-    fn from(models: Vec<(short_link::Model, Vec<views::Model>)>) -> Self {
+impl From<Vec<(short_link::Model, Vec<views::Model>, Vec<tag::Model>)>> for UserLinksAndViews {
+    fn from(models: Vec<(short_link::Model, Vec<views::Model>, Vec<tag::Model>)>) -> Self {
         Self {
             urls: models
                 .iter()
@@ -316,12 +414,33 @@ pub struct UserLinkWithViews {
     pub expiry_date: Option<NaiveDateTime>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub enabled: bool,
+    #[ts(optional)]
+    pub disabled_reason: Option<String>,
+    #[ts(optional)]
+    pub deleted_at: Option<NaiveDateTime>,
+    #[ts(optional)]
+    pub title: Option<String>,
+    #[ts(optional)]
+    pub notes: Option<String>,
+    #[ts(optional)]
+    pub description: Option<String>,
+    #[ts(optional)]
+    pub favicon: Option<String>,
+    #[ts(optional)]
+    pub og_image: Option<String>,
+    #[ts(optional)]
+    pub max_clicks: Option<i64>,
+    pub tags: Vec<String>,
+    /// Approximate distinct human visitors of each day, added up over the link's lifetime, so
+    /// someone who comes back on another day counts again
+    pub unique_visitors: i64,
     pub views: Vec<UserView>,
 }
 
-impl From<(short_link::Model, Vec<views::Model>)> for UserLinkWithViews {
-    fn from(values: (short_link::Model, Vec<views::Model>)) -> Self {
-        let (sl, vi) = values;
+impl From<(short_link::Model, Vec<views::Model>, Vec<tag::Model>)> for UserLinkWithViews {
+    fn from(values: (short_link::Model, Vec<views::Model>, Vec<tag::Model>)) -> Self {
+        let (sl, vi, tg) = values;
         Self {
             id: sl.id,
             short_url: sl.short_url,
@@ -330,6 +449,17 @@ impl From<(short_link::Model, Vec<views::Model>)> for UserLinkWithViews {
             expiry_date: sl.expiry_date,
             created_at: sl.created_at,
             updated_at: sl.updated_at,
+            enabled: sl.enabled,
+            disabled_reason: sl.disabled_reason,
+            deleted_at: sl.deleted_at,
+            title: sl.title,
+            notes: sl.notes,
+            description: sl.description,
+            favicon: sl.favicon,
+            og_image: sl.og_image,
+            max_clicks: sl.max_clicks,
+            tags: tg.into_iter().map(|t| t.name).collect(),
+            unique_visitors: 0,
             views: vi
                 .iter()
                 .map(|v| v.to_owned().into())
@@ -344,33 +474,32 @@ pub struct UserView {
     pub id: i32,
     #[ts(optional)]
     pub headers: Option<BTreeMap<String, Vec<String>>>,
+    /// The visitor's address, or their network when addresses are truncated
     #[ts(optional)]
     pub ip: Option<String>,
+    /// A salted hash of the visitor's address when addresses are hashed
+    #[ts(optional)]
+    pub ip_hash: Option<String>,
+    #[ts(optional)]
+    pub referrer: Option<String>,
+    #[ts(optional)]
+    pub user_agent: Option<String>,
+    pub bot: bool,
     pub cache_hit: bool,
     pub created_at: NaiveDateTime,
 }
 
-#[cfg(feature = "headers")]
 impl From<views::Model> for UserView {
     fn from(vi: views::Model) -> Self {
         let headers: Option<HeaderMapDef> = vi.headers.map(|val| val.into());
         Self {
             id: vi.id,
             headers: headers.map(|v| v.0),
-            ip: vi.ip.map(|ip| ip.ip().to_string()),
-            cache_hit: vi.cache_hit,
-            created_at: vi.created_at,
-        }
-    }
-}
-
-#[cfg(not(feature = "headers"))]
-impl From<views::Model> for UserView {
-    fn from(vi: views::Model) -> Self {
-        Self {
-            id: vi.id,
-            headers: None,
-            ip: vi.ip.map(|ip| ip.ip().to_string()),
+            ip: vi.ip.map(display_ip),
+            ip_hash: vi.ip_hash,
+            referrer: vi.referrer,
+            user_agent: vi.user_agent,
+            bot: vi.bot,
             cache_hit: vi.cache_hit,
             created_at: vi.created_at,
         }
@@ -388,7 +517,29 @@ pub struct UserLink {
     pub expiry_date: Option<NaiveDateTime>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub enabled: bool,
+    #[ts(optional)]
+    pub disabled_reason: Option<String>,
+    #[ts(optional)]
+    pub deleted_at: Option<NaiveDateTime>,
+    #[ts(optional)]
+    pub title: Option<String>,
+    #[ts(optional)]
+    pub notes: Option<String>,
+    #[ts(optional)]
+    pub description: Option<String>,
+    #[ts(optional)]
+    pub favicon: Option<String>,
+    #[ts(optional)]
+    pub og_image: Option<String>,
+    #[ts(optional)]
+    pub max_clicks: Option<i64>,
+    pub tags: Vec<String>,
     pub views: i64,
+    /// Approximate distinct human visitors of each day, added up over the link's lifetime, so
+    /// someone who comes back on another day counts again
+    #[sea_orm(skip)]
+    pub unique_visitors: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, IntoResponses, TS)]
@@ -441,6 +592,56 @@ impl IntoResponse for UserLinksResponse {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, IntoResponses, TS)]
+#[serde(untagged)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub enum ExportResponse {
+    #[response(status = StatusCode::UNAUTHORIZED)]
+    InvalidSession(#[to_schema] BasicError),
+    #[response(status = StatusCode::BAD_REQUEST)]
+    InvalidRange(#[to_schema] BasicError),
+    #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
+    DatabaseError(#[to_schema] BasicError),
+    #[response(status = StatusCode::NOT_FOUND)]
+    UrlNotFound,
+}
+
+impl From<sea_orm::DbErr> for ExportResponse {
+    fn from(e: sea_orm::DbErr) -> Self {
+        Self::DatabaseError(format!("Database error: {e}").into())
+    }
+}
+
+impl IntoResponse for ExportResponse {
+    #[instrument]
+    fn into_response(self) -> Response {
+        match self {
+            ExportResponse::InvalidSession(e) => {
+                error!(%e);
+                (StatusCode::UNAUTHORIZED, Json(e)).into_response()
+            }
+            ExportResponse::InvalidRange(e) => {
+                error!(%e);
+                (StatusCode::BAD_REQUEST, Json(e)).into_response()
+            }
+            ExportResponse::DatabaseError(e) => {
+                error!(%e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
+            }
+            ExportResponse::UrlNotFound => {
+                error!("URL not found");
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(BasicError {
+                        error: "URL not found".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[serde(untagged)]
 #[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
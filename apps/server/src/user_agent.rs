@@ -0,0 +1,247 @@
+use std::sync::LazyLock;
+
+use entity::sea_orm_active_enums::DeviceClass;
+use regex::RegexSet;
+use serde::Deserialize;
+
+/// The rules bundled with the server, so parsing never needs the network.
+const RULES: &str = include_str!("../user_agents.toml");
+
+static PARSER: LazyLock<UserAgentParser> = LazyLock::new(|| {
+    UserAgentParser::from_toml(RULES).expect("Unable to load the bundled user agent rules")
+});
+
+#[derive(Debug, Deserialize)]
+struct RulesFile {
+    bots: Vec<NamedRule>,
+    browsers: Vec<NamedRule>,
+    operating_systems: Vec<NamedRule>,
+    devices: Vec<DeviceRule>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NamedRule {
+    pattern: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceRule {
+    pattern: String,
+    device: DeviceClass,
+}
+
+/// What a user agent says about the visitor.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParsedUserAgent {
+    /// The browser family, or the bot's name for bots
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub device: DeviceClass,
+    pub bot: bool,
+}
+
+impl Default for ParsedUserAgent {
+    fn default() -> Self {
+        Self {
+            browser: None,
+            os: None,
+            device: DeviceClass::Unknown,
+            bot: false,
+        }
+    }
+}
+
+/// A set of ordered rules per field; the first rule that matches decides the value.
+#[derive(Debug)]
+struct Rules<T> {
+    patterns: RegexSet,
+    values: Vec<T>,
+}
+
+impl<T: Clone> Rules<T> {
+    fn new(rules: impl IntoIterator<Item = (String, T)>) -> Result<Self, regex::Error> {
+        let (patterns, values): (Vec<String>, Vec<T>) = rules.into_iter().unzip();
+        Ok(Self {
+            patterns: RegexSet::new(patterns)?,
+            values,
+        })
+    }
+
+    fn first_match(&self, user_agent: &str) -> Option<T> {
+        self.patterns
+            .matches(user_agent)
+            .iter()
+            .next()
+            .map(|index| self.values[index].clone())
+    }
+}
+
+#[derive(Debug)]
+pub struct UserAgentParser {
+    bots: Rules<String>,
+    browsers: Rules<String>,
+    operating_systems: Rules<String>,
+    devices: Rules<DeviceClass>,
+}
+
+impl UserAgentParser {
+    /// Compiles a rules file in the format of the bundled `user_agents.toml`.
+    pub fn from_toml(rules: &str) -> Result<Self, String> {
+        let file: RulesFile = toml::from_str(rules).map_err(|e| e.to_string())?;
+        let named = |rules: Vec<NamedRule>| {
+            Rules::new(rules.into_iter().map(|rule| (rule.pattern, rule.name)))
+                .map_err(|e| e.to_string())
+        };
+        Ok(Self {
+            bots: named(file.bots)?,
+            browsers: named(file.browsers)?,
+            operating_systems: named(file.operating_systems)?,
+            devices: Rules::new(
+                file.devices
+                    .into_iter()
+                    .map(|rule| (rule.pattern, rule.device)),
+            )
+            .map_err(|e| e.to_string())?,
+        })
+    }
+
+    /// The parser built from the bundled rules.
+    pub fn bundled() -> &'static Self {
+        &PARSER
+    }
+
+    pub fn parse(&self, user_agent: Option<&str>) -> ParsedUserAgent {
+        let Some(user_agent) = user_agent.map(str::trim).filter(|ua| !ua.is_empty()) else {
+            return ParsedUserAgent::default();
+        };
+        let bot = self.bots.first_match(user_agent);
+        ParsedUserAgent {
+            bot: bot.is_some(),
+            browser: bot.or_else(|| self.browsers.first_match(user_agent)),
+            os: self.operating_systems.first_match(user_agent),
+            device: self
+                .devices
+                .first_match(user_agent)
+                .unwrap_or(DeviceClass::Unknown),
+        }
+    }
+}
+
+/// Parses `user_agent` with the bundled rules.
+pub fn parse_user_agent(user_agent: Option<&str>) -> ParsedUserAgent {
+    UserAgentParser::bundled().parse(user_agent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(browser: &str, os: Option<&str>, device: DeviceClass, bot: bool) -> ParsedUserAgent {
+        ParsedUserAgent {
+            browser: Some(browser.to_string()),
+            os: os.map(str::to_string),
+            device,
+            bot,
+        }
+    }
+
+    #[test]
+    fn bundled_rules_compile() {
+        UserAgentParser::from_toml(RULES).unwrap();
+        assert!(UserAgentParser::from_toml("bots = []").is_err());
+    }
+
+    #[test]
+    fn browsers_are_recognised() {
+        let cases = [
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 Chrome/126.0.0.0 Safari/537.36",
+                parsed("Chrome", Some("Windows"), DeviceClass::Desktop, false),
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 Chrome/126.0.0.0 Safari/537.36 Edg/126.0.2592.87",
+                parsed("Edge", Some("Windows"), DeviceClass::Desktop, false),
+            ),
+            (
+                "Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:127.0) Gecko/20100101 Firefox/127.0",
+                parsed("Firefox", Some("Linux"), DeviceClass::Desktop, false),
+            ),
+            (
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, \
+                 like Gecko) Version/17.5 Safari/605.1.15",
+                parsed("Safari", Some("macOS"), DeviceClass::Desktop, false),
+            ),
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 \
+                 (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1",
+                parsed("Safari", Some("iOS"), DeviceClass::Mobile, false),
+            ),
+            (
+                "Mozilla/5.0 (iPad; CPU OS 17_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like \
+                 Gecko) Version/17.5 Mobile/15E148 Safari/604.1",
+                parsed("Safari", Some("iOS"), DeviceClass::Tablet, false),
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 Chrome/126.0.0.0 Mobile Safari/537.36",
+                parsed("Chrome", Some("Android"), DeviceClass::Mobile, false),
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 13; SM-X710) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 SamsungBrowser/25.0 Chrome/121.0.0.0 Safari/537.36",
+                parsed(
+                    "Samsung Internet",
+                    Some("Android"),
+                    DeviceClass::Tablet,
+                    false,
+                ),
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64; Xbox; Xbox One) AppleWebKit/537.36 \
+                 (KHTML, like Gecko) Chrome/70.0.3538.102 Safari/537.36 Edge/18.19041",
+                parsed("Edge", Some("Xbox"), DeviceClass::Console, false),
+            ),
+        ];
+        let parser = UserAgentParser::bundled();
+        for (user_agent, expected) in cases {
+            assert_eq!(parser.parse(Some(user_agent)), expected, "{user_agent}");
+        }
+    }
+
+    #[test]
+    fn bots_are_named_after_themselves() {
+        let cases = [
+            (
+                "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+                "Googlebot",
+            ),
+            (
+                "Slackbot-LinkExpanding 1.0 (+https://api.slack.com/robots)",
+                "Slackbot",
+            ),
+            (
+                "facebookexternalhit/1.1 (+http://www.facebook.com/externalhit_uatext.php)",
+                "Facebook",
+            ),
+            ("curl/8.4.0", "curl"),
+            ("python-requests/2.32.3", "Python Requests"),
+        ];
+        let parser = UserAgentParser::bundled();
+        for (user_agent, name) in cases {
+            let parsed = parser.parse(Some(user_agent));
+            assert!(parsed.bot, "{user_agent} wasn't flagged as a bot");
+            assert_eq!(parsed.browser.as_deref(), Some(name), "{user_agent}");
+        }
+    }
+
+    #[test]
+    fn missing_user_agents_parse_as_unknown() {
+        let parser = UserAgentParser::bundled();
+        assert_eq!(parser.parse(None), ParsedUserAgent::default());
+        assert_eq!(parser.parse(Some("   ")), ParsedUserAgent::default());
+        assert_eq!(parse_user_agent(Some("")), ParsedUserAgent::default());
+    }
+}
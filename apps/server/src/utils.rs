@@ -1,8 +1,11 @@
-#[cfg(feature = "headers")]
-use std::collections::BTreeMap;
-use std::{fmt::Display, sync::LazyLock, time::Duration};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
 
-#[cfg(feature = "headers")]
 use axum::http::header::HeaderMap;
 use chrono::TimeDelta;
 use openidconnect::{
@@ -16,16 +19,15 @@ use openidconnect::{
     },
 };
 use regex::{Regex, RegexBuilder};
-#[cfg(feature = "headers")]
-use sea_orm::query::JsonValue;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use sea_orm::{prelude::IpNetwork, query::JsonValue};
 use serde::{Deserialize, Serialize};
-#[cfg(feature = "headers")]
 use serde_json::value::Value;
 use tracing::instrument;
 use ts_rs::TS;
+use url::{Host, Url};
 use utoipa::ToSchema;
 
-#[cfg(feature = "headers")]
 use crate::error::ServerError;
 
 static DURATION_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
@@ -217,12 +219,10 @@ pub(crate) fn parse_time_delta(s: &str) -> Result<TimeDelta, ServerError> {
     Ok(time)
 }
 
-#[cfg(feature = "headers")]
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema, TS)]
 #[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
 pub struct HeaderMapDef(pub BTreeMap<String, Vec<String>>);
 
-#[cfg(feature = "headers")]
 impl TryFrom<HeaderMap> for HeaderMapDef {
     type Error = ServerError;
 
@@ -243,7 +243,6 @@ impl TryFrom<HeaderMap> for HeaderMapDef {
     }
 }
 
-#[cfg(feature = "headers")]
 impl From<JsonValue> for HeaderMapDef {
     fn from(js: JsonValue) -> Self {
         let mut map = BTreeMap::new();
@@ -322,6 +321,152 @@ impl From<JsonValue> for HeaderMapDef {
     }
 }
 
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // 0.0.0.0/8, 100.64.0.0/10 (CGNAT), 192.0.0.0/24, 198.18.0.0/15 and 240.0.0.0/4
+        || a == 0
+        || (a == 100 && (b & 0xc0) == 64)
+        || (a == 192 && b == 0 && c == 0)
+        || (a == 198 && (b & 0xfe) == 18)
+        || a >= 240)
+}
+
+/// The IPv4 address carried inside an IPv6 one, for the ranges that route to the IPv4 internet:
+/// IPv4-mapped (`::ffff:0:0/96`), IPv4-compatible (`::/96`), NAT64 (`64:ff9b::/96`) and 6to4
+/// (`2002::/16`).
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return Some(v4);
+    }
+    let [a, b, c, d, e, f, g, h] = ip.segments();
+    let low = Ipv4Addr::from((u32::from(g) << 16) | u32::from(h));
+    match (a, b) {
+        // :: and ::1 are left to the unspecified and loopback checks
+        (0, 0) if [c, d, e, f] == [0; 4] && !(ip.is_unspecified() || ip.is_loopback()) => Some(low),
+        (0x0064, 0xff9b) if [c, d, e, f] == [0; 4] => Some(low),
+        (0x2002, _) => Some(Ipv4Addr::from((u32::from(b) << 16) | u32::from(c))),
+        _ => None,
+    }
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    if let Some(v4) = embedded_ipv4(ip) {
+        return is_public_ipv4(v4);
+    }
+    let [a, b, ..] = ip.segments();
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        // 2001:db8::/32 (documentation), and whatever in 64:ff9b::/32 (which includes local-use
+        // NAT64) and ::/32 doesn't carry an IPv4 address
+        || (a == 0x2001 && b == 0x0db8)
+        || (a == 0x0064 && b == 0xff9b)
+        || (a == 0 && b == 0))
+}
+
+/// Returns true when `ip` is routable on the public internet.
+pub(crate) fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => is_public_ipv6(ip),
+    }
+}
+
+/// Formats a stored address, leaving off the prefix when it covers a single host.
+pub(crate) fn display_ip(ip: IpNetwork) -> String {
+    let host = if ip.is_ipv4() { 32 } else { 128 };
+    if ip.prefix() == host {
+        ip.ip().to_string()
+    } else {
+        ip.to_string()
+    }
+}
+
+/// Resolves `host` and refuses it unless every address it resolves to is public.
+async fn resolve_public(host: &str, port: u16) -> Result<Vec<SocketAddr>, ServerError> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| ServerError::NonPublicAddress(format!("{host}: {e}")))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(ServerError::NonPublicAddress(format!(
+            "{host} did not resolve to any address"
+        )));
+    }
+    match addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        Some(addr) => Err(ServerError::NonPublicAddress(format!(
+            "{host} resolves to {}",
+            addr.ip()
+        ))),
+        None => Ok(addrs),
+    }
+}
+
+/// A DNS resolver that refuses hosts resolving to any address that isn't public.
+///
+/// Checking a URL before requesting it isn't enough on its own, since the client resolves the
+/// host again to connect and the answer can change in between. A client resolving through this
+/// only ever connects to addresses that passed the check.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            // The client fills in the port of the URL it connects to
+            let addrs = resolve_public(name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// A client for requests made on behalf of users, which can only reach public addresses.
+/// Redirects aren't followed and proxies aren't used, since either would get around the check.
+pub(crate) fn public_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .no_proxy()
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+        .expect("Unable to build the public client")
+}
+
+/// Refuses `url` unless it is http(s) and its host is, or resolves only to, public addresses.
+///
+/// This is the SSRF guard for any request the server makes on behalf of a user. Requests must
+/// also go through `public_client`, so the addresses connected to are the ones checked here.
+#[instrument]
+pub(crate) async fn ensure_public_url(url: &Url) -> Result<(), ServerError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(ServerError::NonPublicAddress(format!(
+            "unsupported scheme {}",
+            url.scheme()
+        )));
+    }
+    let port = url.port_or_known_default().unwrap_or(80);
+    let ip = match url.host() {
+        Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+        Some(Host::Domain(domain)) => return resolve_public(domain, port).await.map(|_| ()),
+        None => {
+            return Err(ServerError::NonPublicAddress(format!("{url} has no host")));
+        }
+    };
+    if !is_public_ip(ip) {
+        return Err(ServerError::NonPublicAddress(format!("{url} is {ip}")));
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
 #[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
 pub struct BasicError {
@@ -407,3 +552,88 @@ pub type OidcClient = Client<
     EndpointMaybeSet,
     EndpointMaybeSet,
 >;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public(ip: &str) -> bool {
+        is_public_ip(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn public_addresses_pass() {
+        assert!(public("93.184.216.34"));
+        assert!(public("2606:2800:220:1:248:1893:25c8:1946"));
+        assert!(public("::ffff:93.184.216.34"));
+        assert!(public("64:ff9b::93.184.216.34"));
+        assert!(public("2002:5db8:d822::1"));
+    }
+
+    #[test]
+    fn private_addresses_are_refused() {
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fc00::1",
+            "fe80::1",
+            "2001:db8::1",
+        ] {
+            assert!(!public(ip), "{ip} should be refused");
+        }
+    }
+
+    #[test]
+    fn embedded_private_ipv4_is_refused() {
+        for ip in [
+            // IPv4-mapped
+            "::ffff:127.0.0.1",
+            // IPv4-compatible
+            "::10.0.0.1",
+            "::169.254.169.254",
+            // NAT64
+            "64:ff9b::127.0.0.1",
+            "64:ff9b::192.168.1.1",
+            // 6to4 for 127.0.0.1 and 10.1.2.3
+            "2002:7f00:1::1",
+            "2002:a01:203::",
+        ] {
+            assert!(!public(ip), "{ip} should be refused");
+        }
+    }
+
+    #[test]
+    fn ipv6_ranges_without_ipv4_are_refused() {
+        assert!(!public("64:ff9b:1::1"));
+        assert!(!public("::1:0:0:1"));
+    }
+
+    #[tokio::test]
+    async fn literal_hosts_are_checked_without_resolving() {
+        let check = |url: &str| {
+            let url = Url::parse(url).unwrap();
+            async move { ensure_public_url(&url).await }
+        };
+        assert!(check("http://93.184.216.34/").await.is_ok());
+        assert!(check("http://127.0.0.1:8080/").await.is_err());
+        assert!(check("http://[::ffff:10.0.0.1]/").await.is_err());
+        assert!(check("http://[2002:c0a8:101::]/").await.is_err());
+        assert!(check("ftp://93.184.216.34/").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn the_public_client_refuses_private_hosts() {
+        let err = public_client()
+            .get("http://localhost:9/")
+            .send()
+            .await
+            .unwrap_err();
+        assert!(err.is_connect(), "{err:?}");
+        assert!(format!("{err:?}").contains("resolves to"), "{err:?}");
+    }
+}
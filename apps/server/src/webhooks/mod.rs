@@ -0,0 +1,18 @@
+pub mod routes;
+pub mod structs;
+
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::state::ServerState;
+
+pub const WEBHOOK_TAG: &str = "webhooks";
+pub const WEBHOOK_PREFIX: &str = "/api/user";
+
+pub fn webhook_router(state: ServerState) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(routes::list_webhooks, routes::new_webhook))
+        .routes(routes!(routes::update_webhook, routes::delete_webhook))
+        .routes(routes!(routes::webhook_deliveries))
+        .routes(routes!(routes::test_webhook))
+        .with_state(state)
+}
@@ -0,0 +1,294 @@
+use axum::{
+    Json, debug_handler,
+    extract::{Path, Query, State},
+};
+use axum_extra::extract::cookie::PrivateCookieJar;
+use chrono::Utc;
+use entity::{sea_orm_active_enums::WebhookEvent, webhook, webhook_delivery};
+use sea_orm::{entity::*, query::*};
+use tracing::instrument;
+use url::Url;
+use uuid::Uuid;
+
+use super::structs::{
+    DeliveryParams, NewWebhookRequest, UpdateWebhookRequest, WebhookInfo, WebhookResponse,
+};
+use crate::{state::ServerState, user::session_user, utils::ensure_public_url};
+
+const DEFAULT_DELIVERY_LIMIT: u64 = 50;
+const MAX_DELIVERY_LIMIT: u64 = 500;
+
+/// Finds one of the user's webhooks. Webhooks belonging to someone else look the same as
+/// webhooks that don't exist.
+async fn find_webhook(
+    state: &ServerState,
+    user: Uuid,
+    id: Uuid,
+) -> Result<webhook::Model, WebhookResponse> {
+    webhook::Entity::find_by_id(id)
+        .filter(webhook::Column::UserId.eq(user))
+        .one(&state.conn)
+        .await?
+        .ok_or(WebhookResponse::WebhookNotFound)
+}
+
+/// Checks that deliveries can be sent to `url`, which unless private addresses are allowed
+/// means it has to resolve to public addresses only.
+async fn validate_url(url: &str, state: &ServerState) -> Result<String, WebhookResponse> {
+    let parsed = Url::parse(url)
+        .map_err(|e| WebhookResponse::InvalidWebhook(format!("Invalid url {url:?}: {e}").into()))?;
+    if !state.config.actors.webhooks.allow_private {
+        ensure_public_url(&parsed)
+            .await
+            .map_err(|e| WebhookResponse::InvalidWebhook(e.to_string().into()))?;
+    } else if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(WebhookResponse::InvalidWebhook(
+            format!("Unsupported scheme {}", parsed.scheme()).into(),
+        ));
+    }
+    Ok(parsed.to_string())
+}
+
+fn validate_events(events: &[WebhookEvent]) -> Result<(), WebhookResponse> {
+    if events.is_empty() {
+        return Err(WebhookResponse::InvalidWebhook(
+            "A webhook needs at least one event".into(),
+        ));
+    }
+    // Pings are only ever sent as test deliveries
+    if events.contains(&WebhookEvent::Ping) {
+        return Err(WebhookResponse::InvalidWebhook(
+            "ping can't be subscribed to".into(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_sample_rate(rate: f64) -> Result<(), WebhookResponse> {
+    if !(0.0..=1.0).contains(&rate) {
+        return Err(WebhookResponse::InvalidWebhook(
+            format!("The click sample rate must be between 0 and 1, not {rate}").into(),
+        ));
+    }
+    Ok(())
+}
+
+fn new_secret() -> String {
+    format!(
+        "whsec_{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+// /api/user/webhooks
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    context_path = super::WEBHOOK_PREFIX,
+    responses(WebhookResponse),
+    tag = super::WEBHOOK_TAG,
+    security(("session_id" = []))
+)]
+pub async fn list_webhooks(
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<WebhookResponse, WebhookResponse> {
+    let user = session_user(&jar, &state, WebhookResponse::InvalidSession).await?;
+
+    let hooks = webhook::Entity::find()
+        .filter(webhook::Column::UserId.eq(user))
+        .order_by_asc(webhook::Column::CreatedAt)
+        .all(&state.conn)
+        .await?;
+
+    Ok(WebhookResponse::Webhooks(
+        hooks.into_iter().map(WebhookInfo::from).collect(),
+    ))
+}
+
+// /api/user/webhooks
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    context_path = super::WEBHOOK_PREFIX,
+    request_body = NewWebhookRequest,
+    responses(WebhookResponse),
+    tag = super::WEBHOOK_TAG,
+    security(("session_id" = []))
+)]
+pub async fn new_webhook(
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+    Json(payload): Json<NewWebhookRequest>,
+) -> Result<WebhookResponse, WebhookResponse> {
+    let user = session_user(&jar, &state, WebhookResponse::InvalidSession).await?;
+
+    let url = validate_url(&payload.url, &state).await?;
+    validate_events(&payload.events)?;
+    let click_sample_rate = payload.click_sample_rate.unwrap_or(1.0);
+    validate_sample_rate(click_sample_rate)?;
+
+    let now = Utc::now().naive_utc();
+    let hook = webhook::ActiveModel {
+        id: ActiveValue::set(Uuid::new_v4()),
+        user_id: ActiveValue::set(user),
+        url: ActiveValue::set(url),
+        secret: ActiveValue::set(new_secret()),
+        events: ActiveValue::set(payload.events),
+        click_sample_rate: ActiveValue::set(click_sample_rate),
+        enabled: ActiveValue::set(payload.enabled.unwrap_or(true)),
+        created_at: ActiveValue::set(now),
+        updated_at: ActiveValue::set(now),
+    }
+    .insert(&state.conn)
+    .await?;
+
+    Ok(WebhookResponse::Webhook(Box::new(
+        WebhookInfo::with_secret(hook),
+    )))
+}
+
+// /api/user/webhooks/{id}
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    put,
+    path = "/webhooks/{id}",
+    context_path = super::WEBHOOK_PREFIX,
+    params(("id", description = "The webhook ID")),
+    request_body = UpdateWebhookRequest,
+    responses(WebhookResponse),
+    tag = super::WEBHOOK_TAG,
+    security(("session_id" = []))
+)]
+pub async fn update_webhook(
+    Path(id): Path<Uuid>,
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+    Json(payload): Json<UpdateWebhookRequest>,
+) -> Result<WebhookResponse, WebhookResponse> {
+    let user = session_user(&jar, &state, WebhookResponse::InvalidSession).await?;
+    let hook = find_webhook(&state, user, id).await?;
+
+    let mut updated = hook.into_active_model();
+    if let Some(url) = payload.url {
+        updated.url = ActiveValue::set(validate_url(&url, &state).await?);
+    }
+    if let Some(events) = payload.events {
+        validate_events(&events)?;
+        updated.events = ActiveValue::set(events);
+    }
+    if let Some(rate) = payload.click_sample_rate {
+        validate_sample_rate(rate)?;
+        updated.click_sample_rate = ActiveValue::set(rate);
+    }
+    if let Some(enabled) = payload.enabled {
+        updated.enabled = ActiveValue::set(enabled);
+    }
+    let rotate_secret = payload.rotate_secret.unwrap_or(false);
+    if rotate_secret {
+        updated.secret = ActiveValue::set(new_secret());
+    }
+    updated.updated_at = ActiveValue::set(Utc::now().naive_utc());
+    let hook = updated.update(&state.conn).await?;
+
+    let info = if rotate_secret {
+        WebhookInfo::with_secret(hook)
+    } else {
+        hook.into()
+    };
+    Ok(WebhookResponse::Webhook(Box::new(info)))
+}
+
+// /api/user/webhooks/{id}
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{id}",
+    context_path = super::WEBHOOK_PREFIX,
+    params(("id", description = "The webhook ID")),
+    responses(WebhookResponse),
+    tag = super::WEBHOOK_TAG,
+    security(("session_id" = []))
+)]
+pub async fn delete_webhook(
+    Path(id): Path<Uuid>,
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<WebhookResponse, WebhookResponse> {
+    let user = session_user(&jar, &state, WebhookResponse::InvalidSession).await?;
+    let hook = find_webhook(&state, user, id).await?;
+
+    hook.delete(&state.conn).await?;
+
+    Ok(WebhookResponse::Success(
+        format!("Deleted webhook {id}").into(),
+    ))
+}
+
+// /api/user/webhooks/{id}/deliveries
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}/deliveries",
+    context_path = super::WEBHOOK_PREFIX,
+    params(("id", description = "The webhook ID"), DeliveryParams),
+    responses(WebhookResponse),
+    tag = super::WEBHOOK_TAG,
+    security(("session_id" = []))
+)]
+pub async fn webhook_deliveries(
+    Path(id): Path<Uuid>,
+    Query(params): Query<DeliveryParams>,
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<WebhookResponse, WebhookResponse> {
+    let user = session_user(&jar, &state, WebhookResponse::InvalidSession).await?;
+    let hook = find_webhook(&state, user, id).await?;
+
+    let deliveries = webhook_delivery::Entity::find()
+        .filter(webhook_delivery::Column::WebhookId.eq(hook.id))
+        .order_by_desc(webhook_delivery::Column::CreatedAt)
+        .limit(
+            params
+                .limit
+                .unwrap_or(DEFAULT_DELIVERY_LIMIT)
+                .clamp(1, MAX_DELIVERY_LIMIT),
+        )
+        .all(&state.conn)
+        .await?;
+
+    Ok(WebhookResponse::Deliveries(deliveries))
+}
+
+// /api/user/webhooks/{id}/test
+#[instrument]
+#[debug_handler]
+#[utoipa::path(
+    post,
+    path = "/webhooks/{id}/test",
+    context_path = super::WEBHOOK_PREFIX,
+    params(("id", description = "The webhook ID")),
+    responses(WebhookResponse),
+    tag = super::WEBHOOK_TAG,
+    security(("session_id" = []))
+)]
+pub async fn test_webhook(
+    Path(id): Path<Uuid>,
+    jar: PrivateCookieJar,
+    State(state): State<ServerState>,
+) -> Result<WebhookResponse, WebhookResponse> {
+    let user = session_user(&jar, &state, WebhookResponse::InvalidSession).await?;
+    let hook = find_webhook(&state, user, id).await?;
+
+    let delivery = state.pool.test_webhook(&hook).await?;
+
+    Ok(WebhookResponse::Delivery(Box::new(delivery)))
+}
@@ -0,0 +1,193 @@
+use std::fmt;
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::NaiveDateTime;
+use entity::{sea_orm_active_enums::WebhookEvent, webhook, webhook_delivery};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, instrument};
+use ts_rs::TS;
+use utoipa::{IntoParams, IntoResponses, ToSchema};
+use uuid::Uuid;
+
+use crate::utils::{BasicError, BasicResponse};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct NewWebhookRequest {
+    /// Where deliveries are POSTed
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    /// The share of clicks delivered for `click.recorded`, from 0 to 1. Defaults to 1
+    #[ts(optional)]
+    pub click_sample_rate: Option<f64>,
+    /// Defaults to true
+    #[ts(optional)]
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct UpdateWebhookRequest {
+    #[ts(optional)]
+    pub url: Option<String>,
+    #[ts(optional)]
+    pub events: Option<Vec<WebhookEvent>>,
+    #[ts(optional)]
+    pub click_sample_rate: Option<f64>,
+    #[ts(optional)]
+    pub enabled: Option<bool>,
+    /// Replace the signing secret, returning the new one
+    #[ts(optional)]
+    pub rotate_secret: Option<bool>,
+}
+
+/// A webhook as its owner sees it.
+#[derive(Clone, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub struct WebhookInfo {
+    pub id: Uuid,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub click_sample_rate: f64,
+    pub enabled: bool,
+    /// The signing secret, only returned when the webhook is created or its secret rotated
+    #[ts(optional)]
+    pub secret: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl WebhookInfo {
+    /// The webhook along with its signing secret.
+    pub fn with_secret(hook: webhook::Model) -> Self {
+        let secret = hook.secret.clone();
+        Self {
+            secret: Some(secret),
+            ..hook.into()
+        }
+    }
+}
+
+// Responses are logged, and the secret must not end up in the logs
+impl fmt::Debug for WebhookInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookInfo")
+            .field("id", &self.id)
+            .field("url", &self.url)
+            .field("events", &self.events)
+            .field("click_sample_rate", &self.click_sample_rate)
+            .field("enabled", &self.enabled)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("created_at", &self.created_at)
+            .field("updated_at", &self.updated_at)
+            .finish()
+    }
+}
+
+impl From<webhook::Model> for WebhookInfo {
+    fn from(hook: webhook::Model) -> Self {
+        Self {
+            id: hook.id,
+            url: hook.url,
+            events: hook.events,
+            click_sample_rate: hook.click_sample_rate,
+            enabled: hook.enabled,
+            secret: None,
+            created_at: hook.created_at,
+            updated_at: hook.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, IntoParams, TS)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+#[into_params(parameter_in = Query, style = Form)]
+pub struct DeliveryParams {
+    /// How many of the latest deliveries to return, at most 500. Defaults to 50
+    #[ts(optional)]
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, IntoResponses, TS)]
+#[serde(untagged)]
+#[ts(export, export_to = "../../../js/frontend/src/lib/types/")]
+pub enum WebhookResponse {
+    #[response(status = StatusCode::UNAUTHORIZED)]
+    InvalidSession(#[to_schema] BasicError),
+    #[response(status = StatusCode::BAD_REQUEST)]
+    InvalidWebhook(#[to_schema] BasicError),
+    #[response(status = StatusCode::INTERNAL_SERVER_ERROR)]
+    DatabaseError(#[to_schema] BasicError),
+    #[response(status = StatusCode::NOT_FOUND)]
+    WebhookNotFound,
+    #[response(status = StatusCode::OK)]
+    Webhook(#[to_schema] Box<WebhookInfo>),
+    #[response(status = StatusCode::OK)]
+    Webhooks(#[to_schema] Vec<WebhookInfo>),
+    #[response(status = StatusCode::OK)]
+    Deliveries(#[to_schema] Vec<webhook_delivery::Model>),
+    #[response(status = StatusCode::OK)]
+    Delivery(#[to_schema] Box<webhook_delivery::Model>),
+    #[response(status = StatusCode::OK)]
+    Success(#[to_schema] BasicResponse),
+}
+
+impl IntoResponse for WebhookResponse {
+    #[instrument]
+    fn into_response(self) -> Response {
+        match self {
+            Self::InvalidSession(e) => {
+                error!(%e);
+                (StatusCode::UNAUTHORIZED, Json(e)).into_response()
+            }
+            Self::InvalidWebhook(e) => {
+                error!(%e);
+                (StatusCode::BAD_REQUEST, Json(e)).into_response()
+            }
+            Self::DatabaseError(e) => {
+                error!(%e);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(e)).into_response()
+            }
+            Self::WebhookNotFound => {
+                error!("Webhook not found");
+                (
+                    StatusCode::NOT_FOUND,
+                    Json(BasicError {
+                        error: "Webhook not found".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+            Self::Webhook(hook) => {
+                info!("Webhook {} to {}", hook.id, hook.url);
+                (StatusCode::OK, Json(hook)).into_response()
+            }
+            Self::Webhooks(hooks) => {
+                info!("{} webhooks", hooks.len());
+                (StatusCode::OK, Json(hooks)).into_response()
+            }
+            Self::Deliveries(deliveries) => {
+                info!("{} webhook deliveries", deliveries.len());
+                (StatusCode::OK, Json(deliveries)).into_response()
+            }
+            Self::Delivery(delivery) => {
+                info!("{delivery:?}");
+                (StatusCode::OK, Json(delivery)).into_response()
+            }
+            Self::Success(msg) => {
+                info!("{}", msg.message);
+                (StatusCode::OK, Json(msg)).into_response()
+            }
+        }
+    }
+}
+
+impl From<sea_orm::DbErr> for WebhookResponse {
+    fn from(e: sea_orm::DbErr) -> Self {
+        Self::DatabaseError(e.to_string().into())
+    }
+}
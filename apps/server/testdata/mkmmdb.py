@@ -0,0 +1,69 @@
+"""Writes the tiny MaxMind-format databases the GeoIP tests read.
+
+    python3 testdata/mkmmdb.py
+
+Only a handful of IPv4 networks are included:
+
+- 81.2.69.0/24 is in both files: GB, England, London, AS20712 Andrews & Arnold
+- 2.125.160.0/19 is only in the city file: GB
+- 1.128.0.0/11 is only in the ASN file: AS1221 Telstra
+"""
+import ipaddress, os, struct
+
+OUT = os.path.dirname(os.path.abspath(__file__))
+
+def enc(v):
+    def ctrl(t, size, payload):
+        out = b''
+        if size < 29: sz, extra = size, b''
+        elif size < 285: sz, extra = 29, bytes([size-29])
+        else: sz, extra = 30, struct.pack('>H', size-285)
+        if t <= 7: out = bytes([(t<<5)|sz])
+        else: out = bytes([sz, t-7])
+        return out + extra + payload
+    if isinstance(v, bool): return ctrl(14, 1 if v else 0, b'')
+    if isinstance(v, str): b=v.encode(); return ctrl(2, len(b), b)
+    if isinstance(v, dict):
+        body=b''.join(enc(k)+enc(x) for k,x in v.items()); return ctrl(7, len(v), body)
+    if isinstance(v, list):
+        return ctrl(11, len(v), b''.join(enc(x) for x in v))
+    if isinstance(v, tuple):  # (type, int)
+        t, n = v
+        if t=='u16': b=n.to_bytes(2,'big').lstrip(b'\0'); return ctrl(5,len(b),b)
+        if t=='u32': b=n.to_bytes(4,'big').lstrip(b'\0'); return ctrl(6,len(b),b)
+        if t=='u64': b=n.to_bytes(8,'big').lstrip(b'\0'); return ctrl(9,len(b),b)
+    raise Exception(v)
+def u(v):
+    # ints: pick u32 in data
+    if isinstance(v,int) and not isinstance(v,bool): return ('u32',v)
+    if isinstance(v,dict): return {k:u(x) for k,x in v.items()}
+    if isinstance(v,list): return [u(x) for x in v]
+    return v
+def build(entries, dbtype, out):
+    nodes=[[None,None]]
+    datas=[]
+    for net, rec in entries:
+        n=ipaddress.ip_network(net); bits=int(n.network_address); plen=n.prefixlen
+        datas.append(enc(u(rec))); di=len(datas)-1
+        cur=0
+        for i in range(plen):
+            b=(bits>>(31-i))&1
+            if i==plen-1: nodes[cur][b]=('d',di)
+            else:
+                if nodes[cur][b] is None: nodes.append([None,None]); nodes[cur][b]=len(nodes)-1
+                cur=nodes[cur][b]
+    nc=len(nodes); offs=[]; o=0
+    for d in datas: offs.append(o); o+=len(d)
+    tree=b''
+    for l,r in nodes:
+        for x in (l,r):
+            if x is None: v=nc
+            elif isinstance(x,tuple): v=nc+16+offs[x[1]]
+            else: v=x
+            tree+=v.to_bytes(3,'big')
+    meta={'node_count':('u32',nc),'record_size':('u16',24),'ip_version':('u16',4),'database_type':dbtype,'languages':['en'],'binary_format_major_version':('u16',2),'binary_format_minor_version':('u16',0),'build_epoch':('u64',1700000000),'description':{'en':'test'}}
+    open(out,'wb').write(tree+b'\0'*16+b''.join(datas)+b'\xab\xcd\xefMaxMind.com'+enc(meta))
+city = 'London'
+build([('81.2.69.0/24',{'country':{'iso_code':'GB'},'subdivisions':[{'iso_code':'ENG','names':{'en':'England'}}],'city':{'names':{'en':city,'de':'x'}}}),
+       ('2.125.160.0/19',{'country':{'iso_code':'GB'}})], 'GeoIP2-City', os.path.join(OUT, 'GeoIP2-City-Test.mmdb'))
+build([('81.2.69.0/24',{'autonomous_system_number':20712,'autonomous_system_organization':'Andrews & Arnold'}),('1.128.0.0/11',{'autonomous_system_number':1221,'autonomous_system_organization':'Telstra'})], 'GeoLite2-ASN', os.path.join(OUT, 'GeoLite2-ASN-Test.mmdb'))
@@ -2,6 +2,7 @@ import axios, { type AxiosError, type AxiosResponse } from "axios";
 import type {
   NewUrlRequest,
   ShortLink,
+  PublicUrlInfo,
   BasicError,
   QrCodeParams,
 } from "@/lib/types";
@@ -58,15 +59,15 @@ export function updateUrl(
   );
 }
 
-export function urlInfo(id: string): Promise<ShortLink> {
+export function urlInfo(id: string): Promise<PublicUrlInfo> {
   return new Promise(
     (
-      resolve: (value: ShortLink) => void,
+      resolve: (value: PublicUrlInfo) => void,
       reject: (reason: BasicError) => void,
     ) => {
       axios
         .get(urlRoutes.urlInfo(id))
-        .then((data: AxiosResponse<ShortLink>) => {
+        .then((data: AxiosResponse<PublicUrlInfo>) => {
           resolve(data.data);
         })
         .catch((error: AxiosError<BasicError>) => {
@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A click as it is recorded, carrying only what its view stored.
+ */
+export interface ClickEvent {
+  /**
+   * The short link that was clicked
+   */
+  id: string;
+  created_at: string;
+  /**
+   * The visitor's address, or their network when addresses are truncated
+   */
+  ip?: string;
+  /**
+   * A salted hash of the visitor's address when addresses are hashed
+   */
+  ip_hash?: string;
+  referrer?: string;
+  user_agent?: string;
+  bot: boolean;
+}
@@ -0,0 +1,32 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Click recording totals since the server started.
+ */
+export interface ClickStats {
+  /**
+   * Clicks handed straight to the batcher
+   */
+  queued: number;
+  /**
+   * Clicks that waited in the overflow buffer because the channel was full
+   */
+  overflowed: number;
+  /**
+   * Clicks that were never recorded because every buffer was full, the pool was closed or
+   * their link was deleted before they were written
+   */
+  dropped: number;
+  /**
+   * Views written to the database
+   */
+  written: number;
+  /**
+   * Views moved to the dead letter store after their insert kept failing
+   */
+  failed: number;
+  /**
+   * Clicks waiting in the channel, the overflow buffer or for their insert to be retried
+   */
+  pending: number;
+}
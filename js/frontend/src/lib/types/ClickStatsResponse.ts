@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BasicError } from "./BasicError";
+import type { ClickStats } from "./ClickStats";
+
+export type ClickStatsResponse =
+  | BasicError
+  | BasicError
+  | BasicError
+  | ClickStats;
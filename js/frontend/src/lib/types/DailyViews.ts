@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface DailyViews {
+  short_link: string;
+  day: string;
+  referrer_host: string;
+  views: bigint;
+  bot_views: bigint;
+}
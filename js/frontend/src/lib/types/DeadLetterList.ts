@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DeadLetterSummary } from "./DeadLetterSummary";
+
+/**
+ * The dead letter store's contents.
+ */
+export interface DeadLetterList {
+  capacity: number;
+  /**
+   * Dead letters pushed out to make room for newer ones
+   */
+  evicted: number;
+  letters: DeadLetterSummary[];
+}
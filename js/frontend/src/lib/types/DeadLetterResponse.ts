@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BasicError } from "./BasicError";
+import type { BasicResponse } from "./BasicResponse";
+import type { DeadLetterList } from "./DeadLetterList";
+
+export type DeadLetterResponse =
+  | BasicError
+  | BasicError
+  | BasicError
+  | null
+  | DeadLetterList
+  | BasicResponse;
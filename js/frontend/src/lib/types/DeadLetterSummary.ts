@@ -0,0 +1,17 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A dead letter as shown to operators.
+ */
+export interface DeadLetterSummary {
+  id: bigint;
+  kind: string;
+  description: string;
+  error: string;
+  /**
+   * Whether the last error was one that could clear up on its own
+   */
+  retryable: boolean;
+  attempts: number;
+  failed_at: string;
+}
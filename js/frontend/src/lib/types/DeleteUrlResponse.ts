@@ -1,4 +1,9 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { BasicError } from "./BasicError";
 
-export type DeleteUrlResponse = BasicError | BasicError | null | null;
+export type DeleteUrlResponse =
+  | BasicError
+  | BasicError
+  | BasicError
+  | null
+  | null;
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface DeliveryParams {
+  /**
+   * How many of the latest deliveries to return, at most 500. Defaults to 50
+   */
+  limit?: bigint;
+}
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DomainAction } from "./DomainAction";
+
+export interface DomainRule {
+  id: number;
+  pattern: string;
+  action: DomainAction;
+  note?: string;
+  created_by?: string;
+  created_at: string;
+}
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DomainRule } from "./DomainRule";
+
+export interface DomainRuleList {
+  allow_only: boolean;
+  file_rules: number;
+  rules: DomainRule[];
+}
@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BasicError } from "./BasicError";
+import type { BasicResponse } from "./BasicResponse";
+import type { DomainRule } from "./DomainRule";
+import type { DomainRuleList } from "./DomainRuleList";
+
+export type DomainRuleResponse =
+  | BasicError
+  | BasicError
+  | BasicError
+  | BasicError
+  | BasicError
+  | null
+  | DomainRuleList
+  | DomainRule
+  | BasicResponse;
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The file formats views can be exported as.
+ */
+export type ExportFormat = "csv" | "ndjson" | "parquet";
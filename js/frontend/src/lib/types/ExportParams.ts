@@ -0,0 +1,26 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ExportFormat } from "./ExportFormat";
+
+export interface ExportParams {
+  /**
+   * Only export the views of this link
+   */
+  link?: string;
+  /**
+   * Only export the views of links carrying this tag
+   */
+  tag?: string;
+  /**
+   * Start of the range (inclusive, UTC). Defaults to the first view
+   */
+  from?: string;
+  /**
+   * End of the range (exclusive, UTC). Defaults to now
+   */
+  to?: string;
+  format?: ExportFormat;
+  /**
+   * Export views from bots, crawlers and link previewers too. Defaults to false
+   */
+  include_bots?: boolean;
+}
@@ -6,4 +6,7 @@ export type GetUrlResponse =
   | null
   | string
   | BasicError
+  | BasicError
+  | BasicError
+  | BasicError
   | BasicError;
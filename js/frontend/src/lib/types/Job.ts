@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JobOutcome } from "./JobOutcome";
+
+export interface Job {
+  name: string;
+  schedule: string;
+  next_run_at: string;
+  locked_by?: string;
+  locked_until?: string;
+  last_started_at?: string;
+  last_finished_at?: string;
+  last_duration_ms?: bigint;
+  last_outcome?: JobOutcome;
+  last_error?: string;
+}
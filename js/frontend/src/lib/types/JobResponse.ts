@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BasicError } from "./BasicError";
+import type { Job } from "./Job";
+
+export type JobResponse =
+  | BasicError
+  | BasicError
+  | BasicError
+  | null
+  | Job[]
+  | Job;
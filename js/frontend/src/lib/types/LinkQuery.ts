@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LinkSort } from "./LinkSort";
+import type { SortOrder } from "./SortOrder";
+
+export interface LinkQuery {
+  /**
+   * Only return links carrying this tag
+   */
+  tag?: string;
+  /**
+   * Full-text search over the title, original URL and slug. Supports `"quoted phrases"`,
+   * `or` and `-excluded` words
+   */
+  search?: string;
+  /**
+   * Defaults to relevance when searching, and to `created` otherwise
+   */
+  sort?: LinkSort;
+  order?: SortOrder;
+}
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BasicError } from "./BasicError";
+
+/**
+ * Why a live click stream couldn't be opened. Once open, the stream itself answers with
+ * `text/event-stream`.
+ */
+export type LiveClicksResponse = BasicError | BasicError | null;
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DomainAction } from "./DomainAction";
+
+export interface NewDomainRuleRequest {
+  pattern: string;
+  action: DomainAction;
+  note?: string;
+}
@@ -5,4 +5,11 @@ export interface NewUrlRequest {
   short?: string;
   user?: string;
   expiry?: string;
+  title?: string;
+  notes?: string;
+  tags?: string[];
+  /**
+   * Stop redirecting once the link has been clicked this many times.
+   */
+  max_clicks?: bigint;
 }
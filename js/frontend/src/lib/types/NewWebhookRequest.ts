@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WebhookEvent } from "./WebhookEvent";
+
+export interface NewWebhookRequest {
+  /**
+   * Where deliveries are POSTed
+   */
+  url: string;
+  events: WebhookEvent[];
+  /**
+   * The share of clicks delivered for `click.recorded`, from 0 to 1. Defaults to 1
+   */
+  click_sample_rate?: number;
+  /**
+   * Defaults to true
+   */
+  enabled?: boolean;
+}
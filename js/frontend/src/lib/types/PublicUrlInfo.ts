@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * What anyone holding a short link may see of it. The owner and their notes are left out.
+ */
+export interface PublicUrlInfo {
+  id: string;
+  short_url: string;
+  original_url: string;
+  expiry_date?: string;
+  created_at: string;
+  updated_at: string;
+  enabled: boolean;
+  disabled_reason?: string;
+  title?: string;
+  description?: string;
+  favicon?: string;
+  og_image?: string;
+  max_clicks?: bigint;
+}
@@ -8,4 +8,15 @@ export interface ShortLink {
   expiry_date?: string;
   created_at: string;
   updated_at: string;
+  enabled: boolean;
+  disabled_reason?: string;
+  deleted_at?: string;
+  title?: string;
+  notes?: string;
+  description?: string;
+  favicon?: string;
+  og_image?: string;
+  metadata_fetched_at?: string;
+  max_clicks?: bigint;
+  clicks_remaining?: bigint;
 }
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Views sharing one referrer host, user agent, browser, OS, device class or country. `value`
+ * is missing for views without one.
+ */
+export interface StatsBreakdown {
+  value?: string;
+  views: bigint;
+  /**
+   * Distinct IP address and user agent pairs. Missing for referrers, which are counted
+   * from the rollups
+   */
+  unique_visitors?: bigint;
+}
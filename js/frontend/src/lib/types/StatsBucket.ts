@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How finely `/api/url/{id}/stats` buckets its time series.
+ */
+export type StatsBucket = "hour" | "day" | "week";
@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { StatsBucket } from "./StatsBucket";
+
+export interface StatsParams {
+  /**
+   * Start of the range (inclusive, UTC). Defaults to 30 days before `to`
+   */
+  from?: string;
+  /**
+   * End of the range (exclusive, UTC). Defaults to now
+   */
+  to?: string;
+  bucket?: StatsBucket;
+  /**
+   * How many entries each breakdown returns, at most 100. Defaults to 10
+   */
+  limit?: bigint;
+  /**
+   * Count views from bots, crawlers and link previewers too. Defaults to false
+   */
+  include_bots?: boolean;
+}
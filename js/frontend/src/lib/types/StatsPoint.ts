@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Views in one bucket of the time series.
+ */
+export interface StatsPoint {
+  /**
+   * Start of the bucket
+   */
+  start: string;
+  views: bigint;
+  /**
+   * Distinct IP address and user agent pairs for hour buckets, and the approximate count
+   * from the daily visitor sketches for day and week buckets. A week adds up its days, so
+   * someone who comes back on another day counts again
+   */
+  unique_visitors: bigint;
+}
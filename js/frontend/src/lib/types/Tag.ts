@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface Tag {
+  id: number;
+  user_id?: string;
+  name: string;
+  created_at: string;
+}
@@ -2,4 +2,10 @@
 import type { BasicError } from "./BasicError";
 import type { ShortLink } from "./ShortLink";
 
-export type UpdateUrlResponse = BasicError | BasicError | null | ShortLink;
+export type UpdateUrlResponse =
+  | BasicError
+  | BasicError
+  | BasicError
+  | BasicError
+  | null
+  | ShortLink;
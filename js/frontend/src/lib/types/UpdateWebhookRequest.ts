@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WebhookEvent } from "./WebhookEvent";
+
+export interface UpdateWebhookRequest {
+  url?: string;
+  events?: WebhookEvent[];
+  click_sample_rate?: number;
+  enabled?: boolean;
+  /**
+   * Replace the signing secret, returning the new one
+   */
+  rotate_secret?: boolean;
+}
@@ -0,0 +1,46 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { StatsBreakdown } from "./StatsBreakdown";
+import type { StatsBucket } from "./StatsBucket";
+import type { StatsPoint } from "./StatsPoint";
+
+export interface UrlStats {
+  id: string;
+  from: string;
+  to: string;
+  bucket: StatsBucket;
+  /**
+   * Whether views from bots were counted
+   */
+  include_bots: boolean;
+  views: bigint;
+  /**
+   * Approximate distinct human visitors over the whole days the range touches, from the
+   * daily visitor sketches. Someone who comes back on another day counts again
+   */
+  unique_visitors: bigint;
+  /**
+   * One point per bucket in the range, including empty ones
+   */
+  series: StatsPoint[];
+  /**
+   * The most common referring hosts
+   */
+  referrers: StatsBreakdown[];
+  /**
+   * The most common user agents
+   */
+  user_agents: StatsBreakdown[];
+  /**
+   * The most common browsers, with bots under their own name when they are included
+   */
+  browsers: StatsBreakdown[];
+  operating_systems: StatsBreakdown[];
+  /**
+   * Views by device class: desktop, mobile, tablet, tv, console or unknown
+   */
+  devices: StatsBreakdown[];
+  /**
+   * Views by ISO country code, when a GeoIP database is configured
+   */
+  countries: StatsBreakdown[];
+}
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BasicError } from "./BasicError";
+import type { UrlStats } from "./UrlStats";
+
+export type UrlStatsResponse =
+  | BasicError
+  | BasicError
+  | BasicError
+  | null
+  | UrlStats;
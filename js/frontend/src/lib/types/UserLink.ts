@@ -8,5 +8,20 @@ export interface UserLink {
   expiry_date?: string;
   created_at: string;
   updated_at: string;
+  enabled: boolean;
+  disabled_reason?: string;
+  deleted_at?: string;
+  title?: string;
+  notes?: string;
+  description?: string;
+  favicon?: string;
+  og_image?: string;
+  max_clicks?: bigint;
+  tags: string[];
   views: bigint;
+  /**
+   * Approximate distinct human visitors of each day, added up over the link's lifetime, so
+   * someone who comes back on another day counts again
+   */
+  unique_visitors: bigint;
 }
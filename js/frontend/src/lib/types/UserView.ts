@@ -3,7 +3,17 @@
 export interface UserView {
   id: number;
   headers?: Partial<Record<string, string[]>>;
+  /**
+   * The visitor's address, or their network when addresses are truncated
+   */
   ip?: string;
+  /**
+   * A salted hash of the visitor's address when addresses are hashed
+   */
+  ip_hash?: string;
+  referrer?: string;
+  user_agent?: string;
+  bot: boolean;
   cache_hit: boolean;
   created_at: string;
 }
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Which views count towards a link's clicks.
+ */
+export interface ViewFilter {
+  /**
+   * Count views from bots, crawlers and link previewers too. Defaults to false
+   */
+  include_bots?: boolean;
+}
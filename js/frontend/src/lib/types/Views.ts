@@ -1,5 +1,6 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { JsonValue } from "../../../../../libs/entity/bindings/serde_json/JsonValue";
+import type { DeviceClass } from "./DeviceClass";
 
 export interface Views {
   id: number;
@@ -8,4 +9,17 @@ export interface Views {
   ip?: string;
   cache_hit: boolean;
   created_at: string;
+  referrer?: string;
+  user_agent?: string;
+  browser?: string;
+  os?: string;
+  device?: DeviceClass;
+  bot: boolean;
+  country?: string;
+  region?: string;
+  city?: string;
+  asn?: bigint;
+  as_org?: string;
+  ip_hash?: string;
+  rolled_up: boolean;
 }
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WebhookEvent } from "./WebhookEvent";
+
+export interface Webhook {
+  id: string;
+  user_id: string;
+  url: string;
+  secret: string;
+  events: WebhookEvent[];
+  click_sample_rate: number;
+  enabled: boolean;
+  created_at: string;
+  updated_at: string;
+}
@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JsonValue } from "../../../../../apps/server/bindings/serde_json/JsonValue";
+import type { WebhookEvent } from "./WebhookEvent";
+
+export interface WebhookDelivery {
+  id: string;
+  webhook_id: string;
+  event: WebhookEvent;
+  payload: JsonValue;
+  attempts: number;
+  status_code?: number;
+  error?: string;
+  delivered: boolean;
+  created_at: string;
+  updated_at: string;
+}
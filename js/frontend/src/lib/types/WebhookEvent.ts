@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type WebhookEvent =
+  | "link.created"
+  | "link.updated"
+  | "link.deleted"
+  | "link.expired"
+  | "click.recorded"
+  | "ping";
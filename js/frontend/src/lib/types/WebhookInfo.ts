@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WebhookEvent } from "./WebhookEvent";
+
+/**
+ * A webhook as its owner sees it.
+ */
+export interface WebhookInfo {
+  id: string;
+  url: string;
+  events: WebhookEvent[];
+  click_sample_rate: number;
+  enabled: boolean;
+  /**
+   * The signing secret, only returned when the webhook is created or its secret rotated
+   */
+  secret?: string;
+  created_at: string;
+  updated_at: string;
+}
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JsonValue } from "../../../../../apps/server/bindings/serde_json/JsonValue";
+import type { WebhookEvent } from "./WebhookEvent";
+
+/**
+ * The body of every webhook delivery.
+ */
+export interface WebhookPayload {
+  /**
+   * The delivery's id, the same for every attempt so receivers can drop duplicates
+   */
+  id: string;
+  event: WebhookEvent;
+  created_at: string;
+  /**
+   * The link for link events, or the `ClickEvent` for `click.recorded`
+   */
+  data: JsonValue;
+}
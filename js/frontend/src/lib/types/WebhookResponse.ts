@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BasicError } from "./BasicError";
+import type { BasicResponse } from "./BasicResponse";
+import type { WebhookDelivery } from "./WebhookDelivery";
+import type { WebhookInfo } from "./WebhookInfo";
+
+export type WebhookResponse =
+  | BasicError
+  | BasicError
+  | BasicError
+  | null
+  | WebhookInfo
+  | WebhookInfo[]
+  | WebhookDelivery[]
+  | WebhookDelivery
+  | BasicResponse;
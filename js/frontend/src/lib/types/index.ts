@@ -32,3 +32,52 @@ export * from "./QrCodeResponse.ts";
 export * from "./Paginate.ts";
 export * from "./UserLink.ts";
 export * from "./UserLinksAndViews.ts";
+export * from "./DisableUrlRequest.ts";
+export * from "./RestoreUrlResponse.ts";
+export * from "./LinkQuery.ts";
+export * from "./LinkSort.ts";
+export * from "./ShortLinkTag.ts";
+export * from "./SortOrder.ts";
+export * from "./Tag.ts";
+export * from "./DomainAction.ts";
+export * from "./DomainRule.ts";
+export * from "./DomainRuleList.ts";
+export * from "./DomainRuleResponse.ts";
+export * from "./ImportBlocklistRequest.ts";
+export * from "./NewDomainRuleRequest.ts";
+export * from "./ClickStats.ts";
+export * from "./ClickStatsResponse.ts";
+export * from "./Job.ts";
+export * from "./JobOutcome.ts";
+export * from "./JobResponse.ts";
+export * from "./DeadLetterList.ts";
+export * from "./DeadLetterResponse.ts";
+export * from "./DeadLetterSummary.ts";
+export * from "./StatsBreakdown.ts";
+export * from "./StatsBucket.ts";
+export * from "./StatsParams.ts";
+export * from "./StatsPoint.ts";
+export * from "./UrlStats.ts";
+export * from "./UrlStatsResponse.ts";
+export * from "./DeviceClass.ts";
+export * from "./ViewFilter.ts";
+export * from "./DailyVisitors.ts";
+export * from "./LinkVisitors.ts";
+export * from "./DailyViews.ts";
+export * from "./HourlyViews.ts";
+export * from "./ClickEvent.ts";
+export * from "./LiveClicksResponse.ts";
+export * from "./DeliveryParams.ts";
+export * from "./NewWebhookRequest.ts";
+export * from "./UpdateWebhookRequest.ts";
+export * from "./Webhook.ts";
+export * from "./WebhookDelivery.ts";
+export * from "./WebhookEvent.ts";
+export * from "./WebhookInfo.ts";
+export * from "./WebhookPayload.ts";
+export * from "./WebhookResponse.ts";
+export * from "./ExportFormat.ts";
+export * from "./ExportParams.ts";
+export * from "./ExportResponse.ts";
+export * from "./PublicUrlInfo.ts";
+export * from "./VisitorSalt.ts";
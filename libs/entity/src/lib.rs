@@ -2,8 +2,20 @@
 
 pub mod prelude;
 
+pub mod daily_views;
+pub mod daily_visitors;
+pub mod domain_rule;
+pub mod hourly_views;
+pub mod job;
+pub mod link_visitors;
+pub mod sea_orm_active_enums;
 pub mod sessions;
 pub mod short_link;
+pub mod short_link_tag;
+pub mod tag;
 pub mod user;
 pub mod user_pass;
 pub mod views;
+pub mod visitor_salt;
+pub mod webhook;
+pub mod webhook_delivery;
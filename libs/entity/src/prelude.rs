@@ -1,6 +1,11 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.8
 
 pub use super::{
-    sessions::Entity as Sessions, short_link::Entity as ShortLink, user::Entity as User,
-    user_pass::Entity as UserPass, views::Entity as Views,
+    daily_views::Entity as DailyViews, daily_visitors::Entity as DailyVisitors,
+    domain_rule::Entity as DomainRule, hourly_views::Entity as HourlyViews, job::Entity as Job,
+    link_visitors::Entity as LinkVisitors, sessions::Entity as Sessions,
+    short_link::Entity as ShortLink, short_link_tag::Entity as ShortLinkTag, tag::Entity as Tag,
+    user::Entity as User, user_pass::Entity as UserPass, views::Entity as Views,
+    visitor_salt::Entity as VisitorSalt, webhook::Entity as Webhook,
+    webhook_delivery::Entity as WebhookDelivery,
 };
@@ -30,6 +30,33 @@ pub struct Model {
     pub expiry_date: Option<DateTime>,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    pub enabled: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[ts(optional)]
+    pub disabled_reason: Option<String>,
+    #[ts(optional)]
+    pub deleted_at: Option<DateTime>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[ts(optional)]
+    pub title: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[ts(optional)]
+    pub notes: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[ts(optional)]
+    pub description: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[ts(optional)]
+    pub favicon: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[ts(optional)]
+    pub og_image: Option<String>,
+    #[ts(optional)]
+    pub metadata_fetched_at: Option<DateTime>,
+    #[ts(optional)]
+    pub max_clicks: Option<i64>,
+    #[ts(optional)]
+    pub clicks_remaining: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -42,6 +69,16 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     User,
+    #[sea_orm(has_many = "super::daily_views::Entity")]
+    DailyViews,
+    #[sea_orm(has_many = "super::daily_visitors::Entity")]
+    DailyVisitors,
+    #[sea_orm(has_many = "super::hourly_views::Entity")]
+    HourlyViews,
+    #[sea_orm(has_one = "super::link_visitors::Entity")]
+    LinkVisitors,
+    #[sea_orm(has_many = "super::short_link_tag::Entity")]
+    ShortLinkTag,
     #[sea_orm(has_many = "super::views::Entity")]
     Views,
 }
@@ -52,6 +89,46 @@ impl Related<super::user::Entity> for Entity {
     }
 }
 
+impl Related<super::daily_views::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DailyViews.def()
+    }
+}
+
+impl Related<super::daily_visitors::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DailyVisitors.def()
+    }
+}
+
+impl Related<super::hourly_views::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::HourlyViews.def()
+    }
+}
+
+impl Related<super::link_visitors::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LinkVisitors.def()
+    }
+}
+
+impl Related<super::short_link_tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ShortLinkTag.def()
+    }
+}
+
+impl Related<super::tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::short_link_tag::Relation::Tag.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::short_link_tag::Relation::ShortLink.def().rev())
+    }
+}
+
 impl Related<super::views::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Views.def()
@@ -30,12 +30,24 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::domain_rule::Entity")]
+    DomainRule,
     #[sea_orm(has_many = "super::sessions::Entity")]
     Sessions,
     #[sea_orm(has_many = "super::short_link::Entity")]
     ShortLink,
+    #[sea_orm(has_many = "super::tag::Entity")]
+    Tag,
     #[sea_orm(has_many = "super::user_pass::Entity")]
     UserPass,
+    #[sea_orm(has_many = "super::webhook::Entity")]
+    Webhook,
+}
+
+impl Related<super::domain_rule::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DomainRule.def()
+    }
 }
 
 impl Related<super::sessions::Entity> for Entity {
@@ -50,10 +62,22 @@ impl Related<super::short_link::Entity> for Entity {
     }
 }
 
+impl Related<super::tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Tag.def()
+    }
+}
+
 impl Related<super::user_pass::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::UserPass.def()
     }
 }
 
+impl Related<super::webhook::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Webhook.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
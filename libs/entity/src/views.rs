@@ -3,6 +3,8 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use super::sea_orm_active_enums::DeviceClass;
+
 #[derive(
     Clone,
     Debug,
@@ -31,6 +33,37 @@ pub struct Model {
     pub ip: Option<IpNetwork>,
     pub cache_hit: bool,
     pub created_at: DateTime,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[ts(optional)]
+    pub referrer: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[ts(optional)]
+    pub user_agent: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[ts(optional)]
+    pub browser: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[ts(optional)]
+    pub os: Option<String>,
+    #[ts(optional)]
+    pub device: Option<DeviceClass>,
+    pub bot: bool,
+    #[ts(optional)]
+    pub country: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[ts(optional)]
+    pub region: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[ts(optional)]
+    pub city: Option<String>,
+    #[ts(optional)]
+    pub asn: Option<i64>,
+    #[sea_orm(column_type = "Text", nullable)]
+    #[ts(optional)]
+    pub as_org: Option<String>,
+    #[ts(optional)]
+    pub ip_hash: Option<String>,
+    pub rolled_up: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -3,11 +3,47 @@ pub use sea_orm_migration::prelude::*;
 pub struct Migrator;
 
 mod m20250325_204952_init;
+mod m20250714_181204_link_state;
+mod m20250722_093417_link_tags;
+mod m20250728_141052_link_preview;
+mod m20250804_110526_domain_rules;
+mod m20250811_152340_click_limits;
+mod m20250818_091205_view_context;
+mod m20250825_083012_jobs;
+mod m20250901_101530_view_stats;
+mod m20250908_141207_view_agents;
+mod m20250915_092644_view_geo;
+mod m20250922_143318_view_privacy;
+mod m20250929_104512_visitor_sketches;
+mod m20251006_093021_view_rollups;
+mod m20251013_101245_webhooks;
+mod m20251020_093512_link_search;
+mod m20251027_084215_click_budget;
+mod m20251103_091634_visitor_salts;
 pub(crate) mod table_types;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20250325_204952_init::Migration)]
+        vec![
+            Box::new(m20250325_204952_init::Migration),
+            Box::new(m20250714_181204_link_state::Migration),
+            Box::new(m20250722_093417_link_tags::Migration),
+            Box::new(m20250728_141052_link_preview::Migration),
+            Box::new(m20250804_110526_domain_rules::Migration),
+            Box::new(m20250811_152340_click_limits::Migration),
+            Box::new(m20250818_091205_view_context::Migration),
+            Box::new(m20250825_083012_jobs::Migration),
+            Box::new(m20250901_101530_view_stats::Migration),
+            Box::new(m20250908_141207_view_agents::Migration),
+            Box::new(m20250915_092644_view_geo::Migration),
+            Box::new(m20250922_143318_view_privacy::Migration),
+            Box::new(m20250929_104512_visitor_sketches::Migration),
+            Box::new(m20251006_093021_view_rollups::Migration),
+            Box::new(m20251013_101245_webhooks::Migration),
+            Box::new(m20251020_093512_link_search::Migration),
+            Box::new(m20251027_084215_click_budget::Migration),
+            Box::new(m20251103_091634_visitor_salts::Migration),
+        ]
     }
 }
@@ -0,0 +1,52 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShortLink::Table)
+                    .add_column_if_not_exists(boolean(ShortLink::Enabled).default(true))
+                    .add_column_if_not_exists(text_null(ShortLink::DisabledReason))
+                    .add_column_if_not_exists(timestamp_null(ShortLink::DeletedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .table(ShortLink::Table)
+                    .name(ShortLinkIdx::DeletedAt)
+                    .col(ShortLink::DeletedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(ShortLink::Table)
+                    .name(ShortLinkIdx::DeletedAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShortLink::Table)
+                    .drop_column(ShortLink::Enabled)
+                    .drop_column(ShortLink::DisabledReason)
+                    .drop_column(ShortLink::DeletedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,143 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShortLink::Table)
+                    .add_column_if_not_exists(text_null(ShortLink::Title))
+                    .add_column_if_not_exists(text_null(ShortLink::Notes))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Tag::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Tag::Id))
+                    .col(uuid_null(Tag::UserId))
+                    .col(string(Tag::Name))
+                    .col(timestamp(Tag::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(ShortLinkTag::Table)
+                    .if_not_exists()
+                    .col(string(ShortLinkTag::ShortLink))
+                    .col(integer(ShortLinkTag::TagId))
+                    .primary_key(
+                        Index::create()
+                            .col(ShortLinkTag::ShortLink)
+                            .col(ShortLinkTag::TagId),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .table(Tag::Table)
+                    .name(TagIdx::UserName)
+                    .col(Tag::UserId)
+                    .col(Tag::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .table(ShortLinkTag::Table)
+                    .name(ShortLinkTagIdx::TagId)
+                    .col(ShortLinkTag::TagId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(TagFk::UserId)
+                    .from(Tag::Table, Tag::UserId)
+                    .to(User::Table, User::UserId)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(ShortLinkTagFk::ShortLink)
+                    .from(ShortLinkTag::Table, ShortLinkTag::ShortLink)
+                    .to(ShortLink::Table, ShortLink::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(ShortLinkTagFk::TagId)
+                    .from(ShortLinkTag::Table, ShortLinkTag::TagId)
+                    .to(Tag::Table, Tag::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .table(ShortLinkTag::Table)
+                    .name(ShortLinkTagFk::TagId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .table(ShortLinkTag::Table)
+                    .name(ShortLinkTagFk::ShortLink)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .table(Tag::Table)
+                    .name(TagFk::UserId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(ShortLinkTag::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Tag::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShortLink::Table)
+                    .drop_column(ShortLink::Title)
+                    .drop_column(ShortLink::Notes)
+                    .to_owned(),
+            )
+            .await
+    }
+}
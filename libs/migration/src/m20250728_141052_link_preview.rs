@@ -0,0 +1,37 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShortLink::Table)
+                    .add_column_if_not_exists(text_null(ShortLink::Description))
+                    .add_column_if_not_exists(text_null(ShortLink::Favicon))
+                    .add_column_if_not_exists(text_null(ShortLink::OgImage))
+                    .add_column_if_not_exists(timestamp_null(ShortLink::MetadataFetchedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShortLink::Table)
+                    .drop_column(ShortLink::Description)
+                    .drop_column(ShortLink::Favicon)
+                    .drop_column(ShortLink::OgImage)
+                    .drop_column(ShortLink::MetadataFetchedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
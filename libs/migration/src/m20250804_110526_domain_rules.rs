@@ -0,0 +1,51 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DomainRule::Table)
+                    .if_not_exists()
+                    .col(pk_auto(DomainRule::Id))
+                    .col(string(DomainRule::Pattern).unique_key())
+                    .col(string(DomainRule::Action))
+                    .col(text_null(DomainRule::Note))
+                    .col(uuid_null(DomainRule::CreatedBy))
+                    .col(timestamp(DomainRule::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(DomainRuleFk::CreatedBy)
+                    .from(DomainRule::Table, DomainRule::CreatedBy)
+                    .to(User::Table, User::UserId)
+                    .on_delete(ForeignKeyAction::SetNull)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .table(DomainRule::Table)
+                    .name(DomainRuleFk::CreatedBy)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(DomainRule::Table).to_owned())
+            .await
+    }
+}
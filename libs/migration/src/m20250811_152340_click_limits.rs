@@ -0,0 +1,31 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShortLink::Table)
+                    .add_column_if_not_exists(big_integer_null(ShortLink::MaxClicks))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShortLink::Table)
+                    .drop_column(ShortLink::MaxClicks)
+                    .to_owned(),
+            )
+            .await
+    }
+}
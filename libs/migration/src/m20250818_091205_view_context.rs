@@ -0,0 +1,33 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Views::Table)
+                    .add_column_if_not_exists(text_null(Views::Referrer))
+                    .add_column_if_not_exists(text_null(Views::UserAgent))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Views::Table)
+                    .drop_column(Views::Referrer)
+                    .drop_column(Views::UserAgent)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,36 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Job::Table)
+                    .if_not_exists()
+                    .col(string(Job::Name).primary_key())
+                    .col(string(Job::Schedule))
+                    .col(timestamp(Job::NextRunAt))
+                    .col(uuid_null(Job::LockedBy))
+                    .col(timestamp_null(Job::LockedUntil))
+                    .col(timestamp_null(Job::LastStartedAt))
+                    .col(timestamp_null(Job::LastFinishedAt))
+                    .col(big_integer_null(Job::LastDurationMs))
+                    .col(string_null(Job::LastOutcome))
+                    .col(text_null(Job::LastError))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Job::Table).to_owned())
+            .await
+    }
+}
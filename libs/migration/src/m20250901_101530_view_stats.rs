@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .table(Views::Table)
+                    .name(ViewsIdx::ShortLinkCreatedAt)
+                    .col(Views::ShortLink)
+                    .col(Views::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(Views::Table)
+                    .name(ViewsIdx::ShortLinkCreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,37 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Views::Table)
+                    .add_column_if_not_exists(text_null(Views::Browser))
+                    .add_column_if_not_exists(text_null(Views::Os))
+                    .add_column_if_not_exists(string_null(Views::Device))
+                    .add_column_if_not_exists(boolean(Views::Bot).default(false))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Views::Table)
+                    .drop_column(Views::Browser)
+                    .drop_column(Views::Os)
+                    .drop_column(Views::Device)
+                    .drop_column(Views::Bot)
+                    .to_owned(),
+            )
+            .await
+    }
+}
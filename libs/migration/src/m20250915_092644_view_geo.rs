@@ -0,0 +1,39 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Views::Table)
+                    .add_column_if_not_exists(string_null(Views::Country))
+                    .add_column_if_not_exists(text_null(Views::Region))
+                    .add_column_if_not_exists(text_null(Views::City))
+                    .add_column_if_not_exists(big_integer_null(Views::Asn))
+                    .add_column_if_not_exists(text_null(Views::AsOrg))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Views::Table)
+                    .drop_column(Views::Country)
+                    .drop_column(Views::Region)
+                    .drop_column(Views::City)
+                    .drop_column(Views::Asn)
+                    .drop_column(Views::AsOrg)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,48 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Views::Table)
+                    .add_column_if_not_exists(string_null(Views::IpHash))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Addresses used to be stored with a /0 netmask; give them the host netmask they
+        // should have had
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Views::Table)
+                    .value(
+                        Views::Ip,
+                        Expr::cust(
+                            r#"set_masklen("ip", CASE family("ip") WHEN 4 THEN 32 ELSE 128 END)"#,
+                        ),
+                    )
+                    .and_where(Expr::cust(r#"masklen("ip") = 0"#))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Views::Table)
+                    .drop_column(Views::IpHash)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,85 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DailyVisitors::Table)
+                    .if_not_exists()
+                    .col(string(DailyVisitors::ShortLink))
+                    .col(date(DailyVisitors::Day))
+                    .col(binary(DailyVisitors::Sketch))
+                    .primary_key(
+                        Index::create()
+                            .col(DailyVisitors::ShortLink)
+                            .col(DailyVisitors::Day),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(LinkVisitors::Table)
+                    .if_not_exists()
+                    .col(string(LinkVisitors::ShortLink).primary_key())
+                    .col(binary(LinkVisitors::Sketch))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(DailyVisitorsFk::ShortLink)
+                    .from(DailyVisitors::Table, DailyVisitors::ShortLink)
+                    .to(ShortLink::Table, ShortLink::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(LinkVisitorsFk::ShortLink)
+                    .from(LinkVisitors::Table, LinkVisitors::ShortLink)
+                    .to(ShortLink::Table, ShortLink::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .table(LinkVisitors::Table)
+                    .name(LinkVisitorsFk::ShortLink)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .table(DailyVisitors::Table)
+                    .name(DailyVisitorsFk::ShortLink)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(LinkVisitors::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(DailyVisitors::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,135 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Views::Table)
+                    .add_column_if_not_exists(boolean(Views::RolledUp).default(false))
+                    .to_owned(),
+            )
+            .await?;
+        // Only the views still waiting for the rollup job are indexed, which keeps the index
+        // tiny however many views there are
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .table(Views::Table)
+                    .name(ViewsIdx::PendingRollup)
+                    .col(Views::Id)
+                    .and_where(Expr::col(Views::RolledUp).eq(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(HourlyViews::Table)
+                    .if_not_exists()
+                    .col(string(HourlyViews::ShortLink))
+                    .col(timestamp(HourlyViews::Hour))
+                    .col(string(HourlyViews::ReferrerHost).default(""))
+                    .col(big_integer(HourlyViews::Views).default(0))
+                    .col(big_integer(HourlyViews::BotViews).default(0))
+                    .primary_key(
+                        Index::create()
+                            .col(HourlyViews::ShortLink)
+                            .col(HourlyViews::Hour)
+                            .col(HourlyViews::ReferrerHost),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(DailyViews::Table)
+                    .if_not_exists()
+                    .col(string(DailyViews::ShortLink))
+                    .col(date(DailyViews::Day))
+                    .col(string(DailyViews::ReferrerHost).default(""))
+                    .col(big_integer(DailyViews::Views).default(0))
+                    .col(big_integer(DailyViews::BotViews).default(0))
+                    .primary_key(
+                        Index::create()
+                            .col(DailyViews::ShortLink)
+                            .col(DailyViews::Day)
+                            .col(DailyViews::ReferrerHost),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(HourlyViewsFk::ShortLink)
+                    .from(HourlyViews::Table, HourlyViews::ShortLink)
+                    .to(ShortLink::Table, ShortLink::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(DailyViewsFk::ShortLink)
+                    .from(DailyViews::Table, DailyViews::ShortLink)
+                    .to(ShortLink::Table, ShortLink::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .table(DailyViews::Table)
+                    .name(DailyViewsFk::ShortLink)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .table(HourlyViews::Table)
+                    .name(HourlyViewsFk::ShortLink)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(DailyViews::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(HourlyViews::Table).to_owned())
+            .await?;
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(Views::Table)
+                    .name(ViewsIdx::PendingRollup)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Views::Table)
+                    .drop_column(Views::RolledUp)
+                    .to_owned(),
+            )
+            .await
+    }
+}
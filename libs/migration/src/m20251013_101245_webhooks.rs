@@ -0,0 +1,116 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Webhook::Table)
+                    .if_not_exists()
+                    .col(uuid(Webhook::Id).primary_key())
+                    .col(uuid(Webhook::UserId))
+                    .col(text(Webhook::Url))
+                    .col(string(Webhook::Secret))
+                    .col(array(Webhook::Events, ColumnType::Text))
+                    .col(double(Webhook::ClickSampleRate).default(1.0))
+                    .col(boolean(Webhook::Enabled).default(true))
+                    .col(timestamp(Webhook::CreatedAt))
+                    .col(timestamp(Webhook::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .table(Webhook::Table)
+                    .name(WebhookIdx::UserId)
+                    .col(Webhook::UserId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(WebhookFk::UserId)
+                    .from(Webhook::Table, Webhook::UserId)
+                    .to(User::Table, User::UserId)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookDelivery::Table)
+                    .if_not_exists()
+                    .col(uuid(WebhookDelivery::Id).primary_key())
+                    .col(uuid(WebhookDelivery::WebhookId))
+                    .col(string(WebhookDelivery::Event))
+                    .col(json_binary(WebhookDelivery::Payload))
+                    .col(integer(WebhookDelivery::Attempts).default(0))
+                    .col(integer_null(WebhookDelivery::StatusCode))
+                    .col(text_null(WebhookDelivery::Error))
+                    .col(boolean(WebhookDelivery::Delivered).default(false))
+                    .col(timestamp(WebhookDelivery::CreatedAt))
+                    .col(timestamp(WebhookDelivery::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .table(WebhookDelivery::Table)
+                    .name(WebhookDeliveryIdx::WebhookIdCreatedAt)
+                    .col(WebhookDelivery::WebhookId)
+                    .col(WebhookDelivery::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(WebhookDeliveryFk::WebhookId)
+                    .from(WebhookDelivery::Table, WebhookDelivery::WebhookId)
+                    .to(Webhook::Table, Webhook::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .table(WebhookDelivery::Table)
+                    .name(WebhookDeliveryFk::WebhookId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(WebhookDelivery::Table).to_owned())
+            .await?;
+        manager
+            .drop_foreign_key(
+                ForeignKey::drop()
+                    .table(Webhook::Table)
+                    .name(WebhookFk::UserId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Webhook::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,62 @@
+use sea_orm_migration::prelude::*;
+
+use crate::table_types::*;
+
+/// Titles are stemmed as English, so `running` finds `runs`. URLs and slugs are split on
+/// punctuation and kept as they are, since their words are rarely prose.
+const SEARCH_DOCUMENT: &str = r#"GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', coalesce("title", '')), 'A')
+    || setweight(to_tsvector('simple', regexp_replace("id", '[^[:alnum:]]+', ' ', 'g')), 'A')
+    || setweight(to_tsvector('simple', regexp_replace("original_url", '[^[:alnum:]]+', ' ', 'g')), 'B')
+) STORED"#;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShortLink::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(ShortLink::Search)
+                            .custom(Alias::new("tsvector"))
+                            .extra(SEARCH_DOCUMENT),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .table(ShortLink::Table)
+                    .name(ShortLinkIdx::Search)
+                    .col(ShortLink::Search)
+                    .index_type(IndexType::Custom(SeaRc::new(Alias::new("GIN"))))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(ShortLink::Table)
+                    .name(ShortLinkIdx::Search)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShortLink::Table)
+                    .drop_column(ShortLink::Search)
+                    .to_owned(),
+            )
+            .await
+    }
+}
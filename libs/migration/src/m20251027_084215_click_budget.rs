@@ -0,0 +1,48 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::table_types::*;
+
+/// The clicks a link has already had, not counting bots.
+const HUMAN_VIEWS: &str = r#"(SELECT COALESCE(SUM("daily_views"."views"), 0) FROM "daily_views" WHERE "daily_views"."short_link" = "short_link"."id") + (SELECT COUNT(*) FROM "views" WHERE "views"."short_link" = "short_link"."id" AND NOT "views"."rolled_up" AND NOT "views"."bot")"#;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShortLink::Table)
+                    .add_column_if_not_exists(big_integer_null(ShortLink::ClicksRemaining))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Every limited link starts with its limit less the clicks it has already had
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(ShortLink::Table)
+                    .value(
+                        ShortLink::ClicksRemaining,
+                        Expr::cust(format!(r#"GREATEST("max_clicks" - ({HUMAN_VIEWS}), 0)"#)),
+                    )
+                    .and_where(Expr::col(ShortLink::MaxClicks).is_not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ShortLink::Table)
+                    .drop_column(ShortLink::ClicksRemaining)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -94,11 +94,25 @@ pub(crate) enum ShortLink {
     ExpiryDate,
     CreatedAt,
     UpdatedAt,
+    Enabled,
+    DisabledReason,
+    DeletedAt,
+    Title,
+    Notes,
+    Description,
+    Favicon,
+    OgImage,
+    MetadataFetchedAt,
+    MaxClicks,
+    Search,
+    ClicksRemaining,
 }
 
 pub(crate) enum ShortLinkIdx {
     ShortUrl,
     ExpiryDate,
+    DeletedAt,
+    Search,
 }
 
 impl Display for ShortLinkIdx {
@@ -106,6 +120,8 @@ impl Display for ShortLinkIdx {
         match self {
             Self::ShortUrl => write!(f, "idx_short_url"),
             Self::ExpiryDate => write!(f, "idx_expiry_date"),
+            Self::DeletedAt => write!(f, "idx_deleted_at"),
+            Self::Search => write!(f, "idx_short_link_search"),
         }
     }
 }
@@ -143,6 +159,39 @@ pub(crate) enum Views {
     Ip,
     CacheHit,
     CreatedAt,
+    Referrer,
+    UserAgent,
+    Browser,
+    Os,
+    Device,
+    Bot,
+    Country,
+    Region,
+    City,
+    Asn,
+    AsOrg,
+    IpHash,
+    RolledUp,
+}
+
+pub(crate) enum ViewsIdx {
+    ShortLinkCreatedAt,
+    PendingRollup,
+}
+
+impl Display for ViewsIdx {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ShortLinkCreatedAt => write!(f, "idx_views_short_link_created_at"),
+            Self::PendingRollup => write!(f, "idx_views_pending_rollup"),
+        }
+    }
+}
+
+impl From<ViewsIdx> for String {
+    fn from(idx: ViewsIdx) -> Self {
+        idx.to_string()
+    }
 }
 
 pub(crate) enum ViewsFk {
@@ -162,3 +211,352 @@ impl From<ViewsFk> for String {
         vi.to_string()
     }
 }
+
+#[derive(DeriveIden)]
+pub(crate) enum Tag {
+    Table,
+    Id,
+    UserId,
+    Name,
+    CreatedAt,
+}
+
+pub(crate) enum TagIdx {
+    UserName,
+}
+
+impl Display for TagIdx {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UserName => write!(f, "idx_tag_user_name"),
+        }
+    }
+}
+
+impl From<TagIdx> for String {
+    fn from(idx: TagIdx) -> Self {
+        idx.to_string()
+    }
+}
+
+pub(crate) enum TagFk {
+    UserId,
+}
+
+impl Display for TagFk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UserId => write!(f, "fk_tag_user_id"),
+        }
+    }
+}
+
+impl From<TagFk> for String {
+    fn from(fk: TagFk) -> Self {
+        fk.to_string()
+    }
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum ShortLinkTag {
+    Table,
+    ShortLink,
+    TagId,
+}
+
+pub(crate) enum ShortLinkTagIdx {
+    TagId,
+}
+
+impl Display for ShortLinkTagIdx {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TagId => write!(f, "idx_short_link_tag_tag_id"),
+        }
+    }
+}
+
+impl From<ShortLinkTagIdx> for String {
+    fn from(idx: ShortLinkTagIdx) -> Self {
+        idx.to_string()
+    }
+}
+
+pub(crate) enum ShortLinkTagFk {
+    ShortLink,
+    TagId,
+}
+
+impl Display for ShortLinkTagFk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ShortLink => write!(f, "fk_short_link_tag_short_link"),
+            Self::TagId => write!(f, "fk_short_link_tag_tag_id"),
+        }
+    }
+}
+
+impl From<ShortLinkTagFk> for String {
+    fn from(fk: ShortLinkTagFk) -> Self {
+        fk.to_string()
+    }
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum DomainRule {
+    Table,
+    Id,
+    Pattern,
+    Action,
+    Note,
+    CreatedBy,
+    CreatedAt,
+}
+
+pub(crate) enum DomainRuleFk {
+    CreatedBy,
+}
+
+impl Display for DomainRuleFk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CreatedBy => write!(f, "fk_domain_rule_created_by"),
+        }
+    }
+}
+
+impl From<DomainRuleFk> for String {
+    fn from(fk: DomainRuleFk) -> Self {
+        fk.to_string()
+    }
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum Job {
+    Table,
+    Name,
+    Schedule,
+    NextRunAt,
+    LockedBy,
+    LockedUntil,
+    LastStartedAt,
+    LastFinishedAt,
+    LastDurationMs,
+    LastOutcome,
+    LastError,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum DailyVisitors {
+    Table,
+    ShortLink,
+    Day,
+    Sketch,
+}
+
+pub(crate) enum DailyVisitorsFk {
+    ShortLink,
+}
+
+impl Display for DailyVisitorsFk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ShortLink => write!(f, "fk_daily_visitors_short_link"),
+        }
+    }
+}
+
+impl From<DailyVisitorsFk> for String {
+    fn from(fk: DailyVisitorsFk) -> Self {
+        fk.to_string()
+    }
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum LinkVisitors {
+    Table,
+    ShortLink,
+    Sketch,
+}
+
+pub(crate) enum LinkVisitorsFk {
+    ShortLink,
+}
+
+impl Display for LinkVisitorsFk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ShortLink => write!(f, "fk_link_visitors_short_link"),
+        }
+    }
+}
+
+impl From<LinkVisitorsFk> for String {
+    fn from(fk: LinkVisitorsFk) -> Self {
+        fk.to_string()
+    }
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum VisitorSalt {
+    Table,
+    Day,
+    Salt,
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum HourlyViews {
+    Table,
+    ShortLink,
+    Hour,
+    ReferrerHost,
+    Views,
+    BotViews,
+}
+
+pub(crate) enum HourlyViewsFk {
+    ShortLink,
+}
+
+impl Display for HourlyViewsFk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ShortLink => write!(f, "fk_hourly_views_short_link"),
+        }
+    }
+}
+
+impl From<HourlyViewsFk> for String {
+    fn from(fk: HourlyViewsFk) -> Self {
+        fk.to_string()
+    }
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum DailyViews {
+    Table,
+    ShortLink,
+    Day,
+    ReferrerHost,
+    Views,
+    BotViews,
+}
+
+pub(crate) enum DailyViewsFk {
+    ShortLink,
+}
+
+impl Display for DailyViewsFk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ShortLink => write!(f, "fk_daily_views_short_link"),
+        }
+    }
+}
+
+impl From<DailyViewsFk> for String {
+    fn from(fk: DailyViewsFk) -> Self {
+        fk.to_string()
+    }
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum Webhook {
+    Table,
+    Id,
+    UserId,
+    Url,
+    Secret,
+    Events,
+    ClickSampleRate,
+    Enabled,
+    CreatedAt,
+    UpdatedAt,
+}
+
+pub(crate) enum WebhookIdx {
+    UserId,
+}
+
+impl Display for WebhookIdx {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UserId => write!(f, "idx_webhook_user_id"),
+        }
+    }
+}
+
+impl From<WebhookIdx> for String {
+    fn from(idx: WebhookIdx) -> Self {
+        idx.to_string()
+    }
+}
+
+pub(crate) enum WebhookFk {
+    UserId,
+}
+
+impl Display for WebhookFk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UserId => write!(f, "fk_webhook_user_id"),
+        }
+    }
+}
+
+impl From<WebhookFk> for String {
+    fn from(fk: WebhookFk) -> Self {
+        fk.to_string()
+    }
+}
+
+#[derive(DeriveIden)]
+pub(crate) enum WebhookDelivery {
+    Table,
+    Id,
+    WebhookId,
+    Event,
+    Payload,
+    Attempts,
+    StatusCode,
+    Error,
+    Delivered,
+    CreatedAt,
+    UpdatedAt,
+}
+
+pub(crate) enum WebhookDeliveryIdx {
+    WebhookIdCreatedAt,
+}
+
+impl Display for WebhookDeliveryIdx {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WebhookIdCreatedAt => write!(f, "idx_webhook_delivery_webhook_id_created_at"),
+        }
+    }
+}
+
+impl From<WebhookDeliveryIdx> for String {
+    fn from(idx: WebhookDeliveryIdx) -> Self {
+        idx.to_string()
+    }
+}
+
+pub(crate) enum WebhookDeliveryFk {
+    WebhookId,
+}
+
+impl Display for WebhookDeliveryFk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WebhookId => write!(f, "fk_webhook_delivery_webhook_id"),
+        }
+    }
+}
+
+impl From<WebhookDeliveryFk> for String {
+    fn from(fk: WebhookDeliveryFk) -> Self {
+        fk.to_string()
+    }
+}